@@ -0,0 +1,108 @@
+//! Compare the nearest neighbors queries of the KdTree against brute force.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use geo::kdtree::KdTree;
+use geo::{Metric, PointU32};
+
+const SIZE: u32 = 1920;
+
+/// Return `n` pseudo random points in a `SIZE` x `SIZE` square, the same ones
+/// on every run.
+fn points(n: usize) -> Vec<PointU32> {
+    let mut state = 0x2545_f491_u64;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) as u32 % SIZE
+    };
+
+    (0..n).map(|_| PointU32::new(next(), next())).collect()
+}
+
+fn brute_force(points: &[PointU32], query: PointU32, k: usize) -> Vec<PointU32> {
+    let mut points = points.to_vec();
+    points.select_nth_unstable_by_key(k, |p| p.squared_dist::<i64>(&query));
+    points.truncate(k);
+    points.sort_by_key(|p| p.squared_dist::<i64>(&query));
+
+    points
+}
+
+fn nearest_neighbor(c: &mut Criterion) {
+    let queries = points(100);
+    let mut group = c.benchmark_group("nearest_neighbor");
+
+    for n in &[100, 1_000, 10_000] {
+        let pts = points(*n);
+        let tree = KdTree::from_vector(pts.iter().map(|p| (*p, ())).collect());
+
+        group.bench_with_input(BenchmarkId::new("kdtree", n), &tree, |b, tree| {
+            b.iter(|| {
+                for q in &queries {
+                    black_box(tree.nearest_neighbor(*q));
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("brute_force", n), &pts, |b, pts| {
+            b.iter(|| {
+                for q in &queries {
+                    black_box(pts.iter().min_by_key(|p| p.squared_dist::<i64>(q)));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn k_nearest_neighbors(c: &mut Criterion) {
+    let queries = points(100);
+    let pts = points(10_000);
+    let tree = KdTree::from_vector(pts.iter().map(|p| (*p, ())).collect());
+    let mut group = c.benchmark_group("k_nearest_neighbors");
+
+    for k in &[2, 8, 32] {
+        group.bench_with_input(BenchmarkId::new("kdtree", k), k, |b, k| {
+            b.iter(|| {
+                for q in &queries {
+                    black_box(tree.nearest_neighbors(*q, *k));
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("approximate", k), k, |b, k| {
+            b.iter(|| {
+                for q in &queries {
+                    black_box(tree.approximate_nearest_neighbors(*q, *k, Metric::Euclidean, 0.5));
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("brute_force", k), k, |b, k| {
+            b.iter(|| {
+                for q in &queries {
+                    black_box(brute_force(&pts, *q, *k));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn build(c: &mut Criterion) {
+    let pts = points(10_000)
+        .into_iter()
+        .map(|p| (p, ()))
+        .collect::<Vec<_>>();
+
+    c.bench_function("from_vector 10000", |b| {
+        b.iter(|| KdTree::from_vector(black_box(pts.clone())))
+    });
+}
+
+criterion_group!(benches, nearest_neighbor, k_nearest_neighbors, build);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+//! Compare the Bentley–Ottmann sweep against testing every pair of segments.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use geo::segment::{self, Segment};
+use geo::PointF64;
+
+const SIZE: f64 = 1920.0;
+
+/// Return `n` pseudo random short segments in a `SIZE` x `SIZE` square, the
+/// same ones on every run.
+fn segments(n: usize) -> Vec<Segment> {
+    let mut state = 0x2545_f491_u64;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (state >> 11) as f64 / (1_u64 << 53) as f64
+    };
+
+    (0..n)
+        .map(|_| {
+            let p = PointF64::new(next() * SIZE, next() * SIZE);
+            let q = PointF64::new(p.x + (next() - 0.5) * 100.0, p.y + (next() - 0.5) * 100.0);
+
+            Segment::new(p, q)
+        })
+        .collect()
+}
+
+fn brute_force(segments: &[Segment]) -> usize {
+    let mut count = 0;
+    for (i, a) in segments.iter().enumerate() {
+        for b in &segments[i + 1..] {
+            if a.intersection(b).is_some() {
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
+
+fn intersections(c: &mut Criterion) {
+    let mut group = c.benchmark_group("intersections");
+
+    for n in &[100, 1_000, 5_000] {
+        let segs = segments(*n);
+
+        group.bench_with_input(BenchmarkId::new("sweep", n), &segs, |b, segs| {
+            b.iter(|| black_box(segment::intersections(segs)))
+        });
+
+        group.bench_with_input(BenchmarkId::new("brute_force", n), &segs, |b, segs| {
+            b.iter(|| black_box(brute_force(segs)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, intersections);
+criterion_main!(benches);
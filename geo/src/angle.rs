@@ -39,7 +39,7 @@ pub fn angle_orientation(p1: &Point<f64>, p2: &Point<f64>, p3: &Point<f64>) -> A
 mod tests {
     use super::{angle_orientation, AngleOrientation};
 
-    use geo::PointF64;
+    use crate::PointF64;
 
     #[test]
     fn test_angle_orientation() {
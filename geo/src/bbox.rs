@@ -7,6 +7,7 @@ use crate::point::Point;
 
 /// Simple axis aligned bounding box implementation.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoundingBox<T> {
     min: Point<T>,
     max: Point<T>,
@@ -189,20 +190,20 @@ where
 mod test {
     use super::BoundingBox;
 
-    use geo::PointU32;
+    use crate::PointU32;
 
     #[test]
     fn test_contains() {
         let rec = BoundingBox::from_dimensions_and_origin(&PointU32::new(3, 5), 7, 5);
 
-        assert_eq!(rec.contains(&PointU32::new(0, 0)), false);
-        assert_eq!(rec.contains(&PointU32::new(4, 0)), false);
-        assert_eq!(rec.contains(&PointU32::new(0, 8)), false);
-        assert_eq!(rec.contains(&PointU32::new(40, 40)), false);
+        assert!(!rec.contains(&PointU32::new(0, 0)));
+        assert!(!rec.contains(&PointU32::new(4, 0)));
+        assert!(!rec.contains(&PointU32::new(0, 8)));
+        assert!(!rec.contains(&PointU32::new(40, 40)));
 
-        assert_eq!(rec.contains(&PointU32::new(3, 5)), true);
-        assert_eq!(rec.contains(&PointU32::new(5, 7)), true);
-        assert_eq!(rec.contains(&PointU32::new(10, 10)), true);
+        assert!(rec.contains(&PointU32::new(3, 5)));
+        assert!(rec.contains(&PointU32::new(5, 7)));
+        assert!(rec.contains(&PointU32::new(10, 10)));
     }
 
     #[test]
@@ -0,0 +1,519 @@
+//! Boolean operations between simple polygons in the style of the
+//! [Martinez–Rueda
+//! algorithm](https://www.sciencedirect.com/science/article/pii/S0965997813000379).
+//!
+//! The edges of both polygons are split at all their intersections, found with
+//! the Bentley–Ottmann sweep of `segment::intersections`, and each piece is
+//! classified as inside or outside the other polygon. The operation selects the
+//! pieces that bound the result which are then chained back into rings.
+//!
+//! The resulting rings are counter clockwise, according to `orient2d`, except
+//! for holes which are clockwise.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use crate::angle::AngleOrientation;
+use crate::point::PointF64;
+use crate::polygon::Polygon;
+use crate::predicates::orient2d;
+use crate::segment::{self, Intersection, Segment};
+use crate::utils::OrdF64;
+
+/// A boolean operation between two polygons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// The area covered by either polygon.
+    Union,
+
+    /// The area covered by both polygons. Intersecting a polygon with a frame
+    /// clips it to the frame.
+    Intersection,
+
+    /// The area covered by the first polygon, but not by the second one.
+    Difference,
+
+    /// The area covered by exactly one of the polygons.
+    Xor,
+}
+
+/// Where a piece of edge of a polygon lies relatively to the other polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Location {
+    Inside,
+    Outside,
+
+    // the piece is also an edge of the other polygon with the same or the
+    // opposite direction
+    SameEdge,
+    OppositeEdge,
+}
+
+impl FromStr for Operation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "union" => Ok(Operation::Union),
+            "intersection" => Ok(Operation::Intersection),
+            "difference" => Ok(Operation::Difference),
+            "xor" => Ok(Operation::Xor),
+            _ => Err(format!(
+                "unknown boolean operation {}, please use union, intersection, difference or xor",
+                s
+            )),
+        }
+    }
+}
+
+/// Apply the boolean operation to the two polygons and return the rings of
+/// the result. The polygons must be simple, but they can have any
+/// orientation.
+pub fn boolean(a: &Polygon<f64>, b: &Polygon<f64>, op: Operation) -> Vec<Polygon<f64>> {
+    let a_edges = ccw_edges(a);
+    let b_edges = ccw_edges(b);
+
+    let (a_pieces, b_pieces) = split_edges(&a_edges, &b_edges);
+
+    let a_pieces = classify(&a_pieces, &b_pieces, &b_edges);
+    let b_pieces = classify(
+        &b_pieces,
+        &a_pieces.iter().map(|(s, _)| *s).collect::<Vec<_>>(),
+        &a_edges,
+    );
+
+    let mut selected = vec![];
+    for (piece, loc) in a_pieces {
+        let keep = match (op, loc) {
+            (Operation::Union, Location::Outside)
+            | (Operation::Union, Location::SameEdge)
+            | (Operation::Intersection, Location::Inside)
+            | (Operation::Intersection, Location::SameEdge)
+            | (Operation::Difference, Location::Outside)
+            | (Operation::Difference, Location::OppositeEdge)
+            | (Operation::Xor, Location::Outside) => Some(piece),
+            (Operation::Xor, Location::Inside) => Some(reversed(&piece)),
+            _ => None,
+        };
+
+        selected.extend(keep);
+    }
+
+    // the shared edges are already taken from the first polygon
+    for (piece, loc) in b_pieces {
+        let keep = match (op, loc) {
+            (Operation::Union, Location::Outside)
+            | (Operation::Intersection, Location::Inside)
+            | (Operation::Xor, Location::Outside) => Some(piece),
+            (Operation::Difference, Location::Inside) | (Operation::Xor, Location::Inside) => {
+                Some(reversed(&piece))
+            }
+            _ => None,
+        };
+
+        selected.extend(keep);
+    }
+
+    chain(&selected)
+}
+
+/// Return the edges of the polygon in counter clockwise order. Degenerate
+/// polygons have no edges.
+pub(crate) fn ccw_edges(polygon: &Polygon<f64>) -> Vec<Segment> {
+    let edges = polygon
+        .segments()
+        .filter(|s| !s.is_degenerate())
+        .collect::<Vec<_>>();
+
+    match polygon.orientation() {
+        AngleOrientation::CounterClockwise => edges,
+        AngleOrientation::Clockwise => edges.iter().rev().map(reversed).collect(),
+        AngleOrientation::Colinear => vec![],
+    }
+}
+
+/// Split the edges of both polygons at all the intersections between them.
+fn split_edges(a_edges: &[Segment], b_edges: &[Segment]) -> (Vec<Segment>, Vec<Segment>) {
+    let edges = a_edges.iter().chain(b_edges).copied().collect::<Vec<_>>();
+
+    let mut pieces = split_at_intersections(&edges);
+    let b_pieces = pieces.split_off(a_edges.len());
+
+    (
+        pieces.into_iter().flatten().collect(),
+        b_pieces.into_iter().flatten().collect(),
+    )
+}
+
+/// Split every edge at its intersections with the others and return the
+/// pieces of each one in order.
+pub(crate) fn split_at_intersections(edges: &[Segment]) -> Vec<Vec<Segment>> {
+    let mut splits = vec![vec![]; edges.len()];
+
+    for (i, j, int) in segment::intersections(edges) {
+        let pts = match int {
+            Intersection::Point(pt) => vec![pt],
+            Intersection::Overlap(s) => vec![s.start, s.end],
+        };
+
+        splits[i].extend(&pts);
+        splits[j].extend(&pts);
+    }
+
+    edges
+        .iter()
+        .zip(splits)
+        .map(|(edge, mut pts)| {
+            pts.retain(|p| *p != edge.start && *p != edge.end);
+            pts.sort_by(|p, q| edge.project(p).total_cmp(&edge.project(q)));
+            pts.dedup();
+
+            let mut pieces = vec![];
+            let mut start = edge.start;
+            for p in pts.into_iter().chain(std::iter::once(edge.end)) {
+                pieces.push(Segment::new(start, p));
+                start = p;
+            }
+
+            pieces
+        })
+        .collect()
+}
+
+/// Classify each piece by where it lies relatively to the polygon with the
+/// given pieces and edges.
+fn classify(
+    pieces: &[Segment],
+    other_pieces: &[Segment],
+    other_edges: &[Segment],
+) -> Vec<(Segment, Location)> {
+    let other = other_pieces
+        .iter()
+        .map(|p| (key(&p.start), key(&p.end)))
+        .collect::<BTreeSet<_>>();
+
+    pieces
+        .iter()
+        .map(|p| {
+            let loc = if other.contains(&(key(&p.start), key(&p.end))) {
+                Location::SameEdge
+            } else if other.contains(&(key(&p.end), key(&p.start))) {
+                Location::OppositeEdge
+            } else if winding_number(other_edges, &p.start.midpoint(&p.end)) != 0 {
+                Location::Inside
+            } else {
+                Location::Outside
+            };
+
+            (*p, loc)
+        })
+        .collect()
+}
+
+/// Chain the directed edges into closed rings. When many edges leave the same
+/// vertex the one that turns the most to the left is followed, so that rings
+/// touching in a vertex are kept apart.
+pub(crate) fn chain(edges: &[Segment]) -> Vec<Polygon<f64>> {
+    let mut outgoing = BTreeMap::new();
+    for (i, e) in edges.iter().enumerate() {
+        outgoing
+            .entry(key(&e.start))
+            .or_insert_with(Vec::new)
+            .push(i);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut rings = vec![];
+
+    for first in 0..edges.len() {
+        if used[first] {
+            continue;
+        }
+
+        let mut ring = vec![edges[first].start];
+        let mut current = first;
+
+        loop {
+            used[current] = true;
+            let e = &edges[current];
+
+            if key(&e.end) == key(&ring[0]) {
+                break;
+            }
+            ring.push(e.end);
+
+            let next = outgoing.get(&key(&e.end)).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .filter(|c| !used[**c])
+                    .max_by(|c1, c2| turn(e, &edges[**c1]).total_cmp(&turn(e, &edges[**c2])))
+            });
+
+            match next {
+                Some(next) => current = *next,
+                // an open chain can only be the result of rounding errors
+                None => {
+                    ring.clear();
+                    break;
+                }
+            }
+        }
+
+        rings.extend(Polygon::new(ring));
+    }
+
+    rings
+}
+
+/// Return the angle between the direction of the two edges in the range
+/// (-π, π) where u-turns are the least preferred.
+fn turn(incoming: &Segment, outgoing: &Segment) -> f64 {
+    let (dx0, dy0) = (
+        incoming.end.x - incoming.start.x,
+        incoming.end.y - incoming.start.y,
+    );
+    let (dx1, dy1) = (
+        outgoing.end.x - outgoing.start.x,
+        outgoing.end.y - outgoing.start.y,
+    );
+
+    let angle = (dx0 * dy1 - dy0 * dx1).atan2(dx0 * dx1 + dy0 * dy1);
+
+    if angle >= std::f64::consts::PI {
+        -angle
+    } else {
+        angle
+    }
+}
+
+/// Return the winding number of the polygon with the given edges around the
+/// point.
+pub(crate) fn winding_number(edges: &[Segment], pt: &PointF64) -> i32 {
+    let mut wn = 0;
+
+    for e in edges {
+        if e.start.y <= pt.y {
+            if e.end.y > pt.y && orient2d(&e.start, &e.end, pt) > 0.0 {
+                wn += 1;
+            }
+        } else if e.end.y <= pt.y && orient2d(&e.start, &e.end, pt) < 0.0 {
+            wn -= 1;
+        }
+    }
+
+    wn
+}
+
+pub(crate) fn reversed(s: &Segment) -> Segment {
+    Segment::new(s.end, s.start)
+}
+
+fn key(p: &PointF64) -> (OrdF64, OrdF64) {
+    // get rid of negative zeros
+    (OrdF64(p.x + 0.0), OrdF64(p.y + 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn square(x: f64, y: f64, side: f64) -> Polygon<f64> {
+        Polygon::new(vec![
+            PointF64::new(x, y),
+            PointF64::new(x + side, y),
+            PointF64::new(x + side, y + side),
+            PointF64::new(x, y + side),
+        ])
+        .unwrap()
+    }
+
+    fn area(rings: &[Polygon<f64>]) -> f64 {
+        rings.iter().map(|r| r.signed_area()).sum()
+    }
+
+    #[test]
+    fn test_overlapping_squares() {
+        let a = square(0.0, 0.0, 4.0);
+        let b = square(2.0, 2.0, 4.0);
+
+        assert_eq!(area(&boolean(&a, &b, Operation::Union)), 28.0);
+        assert_eq!(area(&boolean(&a, &b, Operation::Intersection)), 4.0);
+        assert_eq!(area(&boolean(&a, &b, Operation::Difference)), 12.0);
+        assert_eq!(area(&boolean(&b, &a, Operation::Difference)), 12.0);
+        assert_eq!(area(&boolean(&a, &b, Operation::Xor)), 24.0);
+
+        let int = boolean(&a, &b, Operation::Intersection);
+        assert_eq!(int.len(), 1);
+        assert_eq!(int[0].bounding_box(), square(2.0, 2.0, 2.0).bounding_box());
+    }
+
+    #[test]
+    fn test_disjoint_and_nested() {
+        let a = square(0.0, 0.0, 4.0);
+        let far = square(10.0, 10.0, 1.0);
+        let inner = square(1.0, 1.0, 2.0);
+
+        assert_eq!(boolean(&a, &far, Operation::Union).len(), 2);
+        assert!(boolean(&a, &far, Operation::Intersection).is_empty());
+        assert_eq!(area(&boolean(&a, &far, Operation::Difference)), 16.0);
+
+        assert_eq!(area(&boolean(&a, &inner, Operation::Union)), 16.0);
+        assert_eq!(area(&boolean(&a, &inner, Operation::Intersection)), 4.0);
+
+        // the difference has a clockwise hole
+        let diff = boolean(&a, &inner, Operation::Difference);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(area(&diff), 12.0);
+        assert!(boolean(&inner, &a, Operation::Difference).is_empty());
+    }
+
+    #[test]
+    fn test_shared_edges() {
+        let a = square(0.0, 0.0, 4.0);
+        let right = square(4.0, 0.0, 4.0);
+        let corner = square(0.0, 0.0, 2.0);
+
+        let union = boolean(&a, &right, Operation::Union);
+        assert_eq!(union.len(), 1);
+        assert_eq!(area(&union), 32.0);
+        assert!(boolean(&a, &right, Operation::Intersection).is_empty());
+        assert_eq!(area(&boolean(&a, &right, Operation::Difference)), 16.0);
+
+        assert_eq!(area(&boolean(&a, &a, Operation::Union)), 16.0);
+        assert_eq!(area(&boolean(&a, &a, Operation::Intersection)), 16.0);
+        assert!(boolean(&a, &a, Operation::Difference).is_empty());
+        assert!(boolean(&a, &a, Operation::Xor).is_empty());
+
+        let diff = boolean(&a, &corner, Operation::Difference);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(area(&diff), 12.0);
+
+        // squares touching in a vertex stay apart
+        assert_eq!(
+            boolean(&a, &square(4.0, 4.0, 1.0), Operation::Union).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_orientation() {
+        let a = square(0.0, 0.0, 4.0);
+        let cw = Polygon::new(a.points().iter().rev().copied()).unwrap();
+
+        assert_eq!(area(&boolean(&a, &cw, Operation::Union)), 16.0);
+        assert_eq!(
+            area(&boolean(&cw, &square(2.0, 0.0, 4.0), Operation::Xor)),
+            16.0
+        );
+    }
+
+    #[test]
+    fn test_operation_from_str() {
+        assert_eq!("xor".parse(), Ok(Operation::Xor));
+        assert!("and".parse::<Operation>().is_err());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(300))]
+        #[test]
+        fn prop_areas(
+            a in prop::collection::vec((0_u8..10, 0_u8..10), 3..8),
+            b in prop::collection::vec((0_u8..10, 0_u8..10), 3..8)
+        ) {
+            let to_polygon = |pts: Vec<(u8, u8)>| {
+                let pts = pts.into_iter().map(|(x, y)| PointF64::new(f64::from(x), f64::from(y)));
+                // the convex hull is always a simple polygon
+                Polygon::new(crate::convex_hull::convex_hull(pts))
+            };
+
+            let (a, b) = match (to_polygon(a), to_polygon(b)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => return Ok(()),
+            };
+
+            let area_a = area(std::slice::from_ref(&a)).abs();
+            let area_b = area(std::slice::from_ref(&b)).abs();
+
+            let union = area(&boolean(&a, &b, Operation::Union));
+            let int = area(&boolean(&a, &b, Operation::Intersection));
+            let diff = area(&boolean(&a, &b, Operation::Difference));
+            let xor = area(&boolean(&a, &b, Operation::Xor));
+
+            let eq = |x: f64, y: f64| (x - y).abs() < 1e-6;
+            prop_assert!(eq(union + int, area_a + area_b), "{} {} {} {}", union, int, area_a, area_b);
+            prop_assert!(eq(diff + int, area_a), "{} {} {}", diff, int, area_a);
+            prop_assert!(eq(xor, union - int), "{} {} {}", xor, union, int);
+        }
+
+        #[test]
+        fn prop_star_areas(
+            a in prop::collection::vec((0_u8..16, 1_u8..8), 3..12),
+            b in prop::collection::vec((0_u8..16, 1_u8..8), 3..12),
+            (dx, dy) in (0_u8..6, 0_u8..6)
+        ) {
+            // sorting the vertices by angle around a center builds a simple,
+            // but not convex, polygon
+            let to_polygon = |mut pts: Vec<(u8, u8)>, dx: u8, dy: u8| {
+                pts.sort_by_key(|(a, _)| *a);
+                pts.dedup_by_key(|(a, _)| *a);
+
+                let pts = pts.into_iter().map(|(a, r)| {
+                    let a = f64::from(a) * std::f64::consts::PI / 8.0;
+                    PointF64::new(
+                        (a.cos() * f64::from(r)).round() + f64::from(dx),
+                        (a.sin() * f64::from(r)).round() + f64::from(dy),
+                    )
+                });
+
+                Polygon::new(pts)
+            };
+
+            let (a, b) = match (to_polygon(a, 0, 0), to_polygon(b, dx, dy)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => return Ok(()),
+            };
+            if !a.is_simple() || !b.is_simple() {
+                return Ok(());
+            }
+
+            let area_a = area(std::slice::from_ref(&a)).abs();
+            let area_b = area(std::slice::from_ref(&b)).abs();
+
+            let union = area(&boolean(&a, &b, Operation::Union));
+            let int = area(&boolean(&a, &b, Operation::Intersection));
+            let diff = area(&boolean(&a, &b, Operation::Difference));
+            let xor = area(&boolean(&a, &b, Operation::Xor));
+
+            let eq = |x: f64, y: f64| (x - y).abs() < 1e-6;
+            prop_assert!(eq(union + int, area_a + area_b), "{} {} {} {}", union, int, area_a, area_b);
+            prop_assert!(eq(diff + int, area_a), "{} {} {}", diff, int, area_a);
+            prop_assert!(eq(xor, union - int), "{} {} {}", xor, union, int);
+
+            let inside = |rings: &[Polygon<f64>], pt: &PointF64| {
+                let edges = rings.iter().flat_map(|r| r.segments()).collect::<Vec<_>>();
+                winding_number(&edges, pt) != 0
+            };
+            let ops = [
+                (Operation::Union, (|a, b| a || b) as fn(bool, bool) -> bool),
+                (Operation::Intersection, |a, b| a && b),
+                (Operation::Difference, |a, b| a && !b),
+                (Operation::Xor, |a, b| a != b),
+            ];
+            for (op, expected) in &ops {
+                let res = boolean(&a, &b, *op);
+
+                for x in -8..14 {
+                    for y in -8..14 {
+                        // never on the boundary of the integer polygons
+                        let pt = PointF64::new(f64::from(x) + 0.37, f64::from(y) + 0.61);
+                        let (in_a, in_b) = (inside(std::slice::from_ref(&a), &pt), inside(std::slice::from_ref(&b), &pt));
+
+                        prop_assert_eq!(inside(&res, &pt), expected(in_a, in_b), "{:?} {:?}", op, pt);
+                    }
+                }
+            }
+        }
+    }
+}
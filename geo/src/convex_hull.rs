@@ -80,7 +80,7 @@ mod tests {
 
     use proptest::prelude::*;
 
-    use geo::{Point, Polygon};
+    use crate::{Point, Polygon};
 
     #[test]
     fn test_convex_hull() {
@@ -1,109 +1,914 @@
-//! Simple module that implements [Delaunay
-//! triangulation](https://en.wikipedia.org/wiki/Delaunay_triangulation)
+//! [Delaunay triangulation](https://en.wikipedia.org/wiki/Delaunay_triangulation)
+//! of a set of points, optionally constrained to contain some edges.
+//!
+//! The triangulation is built incrementally with the Bowyer-Watson algorithm.
+//! The points are inserted along a Hilbert curve and each one is located by
+//! walking from the last inserted triangle which makes the whole triangulation
+//! run in about O(n log n). The space outside the convex hull is covered by
+//! "ghost" triangles that share a vertex at infinity so that points outside the
+//! hull need no special treatment and no super triangle is needed. All the
+//! decisions are taken with the exact predicates in `predicates` and therefore
+//! collinear and cocircular points are handled correctly.
+
+use std::collections::{HashMap, HashSet};
 
 use crate::bbox::BoundingBox;
 use crate::point::{Point, PointF64};
+use crate::predicates::{in_circle, orient2d};
 use crate::triangle::Triangle;
+use crate::utils::split_element_at;
+
+/// The vertex at infinity shared by all the ghost triangles.
+const GHOST: usize = usize::MAX;
+
+/// A Delaunay triangulation. The triangles and their vertices are stored as
+/// indices into the points the triangulation was built from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triangulation {
+    points: Vec<PointF64>,
+    triangles: Vec<[usize; 3]>,
+    neighbors: Vec<[Option<usize>; 3]>,
+    constrained: HashSet<(usize, usize)>,
+}
 
-/// Triangulate the given set of points. This blows up if degenerate triangles
-/// are formed(e.g. completely flat triangles).
-pub fn triangulate(bounding_box: &BoundingBox<f64>, points: Vec<PointF64>) -> Vec<Triangle<f64>> {
-    if points.len() < 3 {
+/// Triangulate the given set of points along with the corners of the bounding
+/// box so that the triangles always cover the whole box.
+pub fn triangulate(
+    bounding_box: &BoundingBox<f64>,
+    mut points: Vec<PointF64>,
+) -> Vec<Triangle<f64>> {
+    if points.is_empty() {
         return vec![];
     }
 
-    let mut points = points.into_iter();
-    let super_triangles = super_triangles(bounding_box, &points.next().unwrap());
+    points.extend_from_slice(&bounding_box.points());
+
+    Triangulation::new(&points).triangles().collect()
+}
+
+impl Triangulation {
+    /// Build the Delaunay triangulation of the given points. Duplicated points
+    /// are part of the triangulation only once and if all the points are
+    /// collinear there are no triangles at all.
+    pub fn new(points: &[PointF64]) -> Self {
+        let (mesh, _) = Mesh::build(points);
+
+        mesh.into_triangulation()
+    }
+
+    /// Build the Delaunay triangulation of the given points that contains all
+    /// the edges in `edges`, each one given as a pair of indices into
+    /// `points`. Such triangulation is not Delaunay anymore, but it's as close
+    /// as it can be. Edges that pass through other points are split at those
+    /// points. An error is returned if two edges cross each other or if an
+    /// edge refers to a point that doesn't exist.
+    pub fn with_constraints(points: &[PointF64], edges: &[(usize, usize)]) -> Result<Self, String> {
+        let (mut mesh, representative) = Mesh::build(points);
+
+        for &(a, b) in edges {
+            if a >= points.len() || b >= points.len() {
+                return Err(format!(
+                    "edge ({}, {}) refers to a point that doesn't exist",
+                    a, b
+                ));
+            }
+
+            if mesh.tris.is_empty() {
+                return Err("cannot constrain the edges of an empty triangulation".to_string());
+            }
+
+            mesh.insert_constraint(representative[a], representative[b])?;
+        }
+
+        Ok(mesh.into_triangulation())
+    }
+
+    /// Return the points of the triangulation, the same it was built with.
+    pub fn points(&self) -> &[PointF64] {
+        &self.points
+    }
+
+    /// Return the vertices of each triangle as indices into `points`. The
+    /// vertices are in counter clockwise order according to
+    /// `predicates::orient2d`.
+    pub fn triangle_indices(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    /// Return the neighbors of each triangle. The i-th neighbor is the triangle
+    /// across the edge opposite to the i-th vertex, if any.
+    pub fn neighbors(&self) -> &[[Option<usize>; 3]] {
+        &self.neighbors
+    }
+
+    /// Return the triangle at the given index.
+    pub fn triangle(&self, i: usize) -> Triangle<f64> {
+        let [a, b, c] = self.triangles[i];
+
+        Triangle::new(self.points[a], self.points[b], self.points[c])
+    }
+
+    /// Return an iterator over all the triangles.
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle<f64>> + '_ {
+        (0..self.triangles.len()).map(move |i| self.triangle(i))
+    }
+
+    /// Return an iterator over all the edges of the triangulation, each edge is
+    /// returned only once.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.triangles
+            .iter()
+            .zip(&self.neighbors)
+            .enumerate()
+            .flat_map(|(ti, (t, n))| {
+                (0..3).filter_map(move |i| {
+                    // every inner edge is shared by two triangles, pick the one
+                    // with the smallest index
+                    match n[i] {
+                        Some(other) if other < ti => None,
+                        _ => Some((t[(i + 1) % 3], t[(i + 2) % 3])),
+                    }
+                })
+            })
+    }
+
+    /// Return the vertices of the convex hull in counter clockwise order.
+    pub fn hull(&self) -> Vec<usize> {
+        let mut next = HashMap::new();
+
+        for (t, n) in self.triangles.iter().zip(&self.neighbors) {
+            for i in 0..3 {
+                if n[i].is_none() {
+                    next.insert(t[(i + 1) % 3], t[(i + 2) % 3]);
+                }
+            }
+        }
+
+        let start = match next.keys().min() {
+            Some(start) => *start,
+            None => return vec![],
+        };
 
-    // theoretically we should remove the triangles that share vertices with the
-    // initial point, but this thing is not for real use.
+        let mut hull = vec![start];
+        let mut cur = next[&start];
+        while cur != start {
+            hull.push(cur);
+            cur = next[&cur];
+        }
 
-    points.fold(super_triangles, |triangles, point| {
-        add_point(triangles, &point)
-    })
+        hull
+    }
+
+    /// Return whether the edge between the two points was one of the
+    /// constrained edges or a piece of one.
+    pub fn is_constrained(&self, a: usize, b: usize) -> bool {
+        self.constrained.contains(&edge_key(a, b))
+    }
 }
 
-// the original algorithm works by finding a super triangle that encloses
-// all the points, but since we live in a finite space just pickup a random
-// point and divide the bounding box in 4 triangles that always cover the
-// entire space. It's not acceptable for real triangulation but we're having
-// fun here :).
-fn super_triangles(bounding_box: &BoundingBox<f64>, first_point: &PointF64) -> Vec<Triangle<f64>> {
-    let bounds = bounding_box.points();
-
-    (0..bounds.len())
-        .map(|i| Triangle::new(bounds[i], bounds[(i + 1) % bounds.len()], *first_point))
-        .collect()
+/// The triangulation while it's being built, ghost triangles included. The
+/// triangles are never removed, but marked as dead and later reused.
+struct Mesh<'a> {
+    points: &'a [PointF64],
+    tris: Vec<[usize; 3]>,
+    adj: Vec<[usize; 3]>,
+    dead: Vec<bool>,
+    free: Vec<usize>,
+    last: usize,
+    steps: usize,
+    constrained: HashSet<(usize, usize)>,
 }
 
-fn add_point(triangles: Vec<Triangle<f64>>, point: &Point<f64>) -> Vec<Triangle<f64>> {
-    let mut edges = vec![];
-    let mut new_triangles = Vec::with_capacity(triangles.len());
+impl<'a> Mesh<'a> {
+    /// Triangulate the points and return the triangulation along with the
+    /// index of the point each point has been merged with, which is itself
+    /// unless it's a duplicate.
+    fn build(points: &'a [PointF64]) -> (Self, Vec<usize>) {
+        let mut mesh = Mesh {
+            points,
+            tris: vec![],
+            adj: vec![],
+            dead: vec![],
+            free: vec![],
+            last: 0,
+            steps: 0,
+            constrained: HashSet::new(),
+        };
 
-    for triangle in triangles {
-        let (circumcenter, radius) = triangle.squared_circumcircle().unwrap();
+        let mut representative = (0..points.len()).collect::<Vec<_>>();
 
-        if circumcenter.squared_dist::<f64>(point) <= radius {
-            edges.push((triangle.points[0], triangle.points[1]));
-            edges.push((triangle.points[1], triangle.points[2]));
-            edges.push((triangle.points[2], triangle.points[0]));
-        } else {
-            new_triangles.push(triangle);
+        let order = hilbert_order(points);
+        let rest = match mesh.init(&order) {
+            Some(rest) => rest,
+            None => return (mesh, representative),
+        };
+
+        for i in rest {
+            if let Some(dup) = mesh.insert(i) {
+                representative[i] = dup;
+            }
         }
+
+        (mesh, representative)
     }
 
-    edges = dedup_edges(&edges);
+    /// Create the first triangle from the first three non collinear points
+    /// and return the points that still need to be inserted.
+    fn init(&mut self, order: &[usize]) -> Option<Vec<usize>> {
+        let first = *order.first()?;
+        let second = order
+            .iter()
+            .position(|i| self.points[*i] != self.points[first])?;
+        let third = order.iter().position(|i| {
+            orient2d(
+                &self.points[first],
+                &self.points[order[second]],
+                &self.points[*i],
+            ) != 0.0
+        })?;
+
+        let (a, mut b, mut c) = (first, order[second], order[third]);
+        if orient2d(&self.points[a], &self.points[b], &self.points[c]) < 0.0 {
+            std::mem::swap(&mut b, &mut c);
+        }
+
+        self.replace(
+            &[],
+            vec![[a, b, c], [c, b, GHOST], [a, c, GHOST], [b, a, GHOST]],
+        );
+        self.last = 0;
+
+        Some(
+            order
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != 0 && *i != second && *i != third)
+                .map(|(_, p)| *p)
+                .collect(),
+        )
+    }
+
+    /// Insert the point at index `p` and return the point it's a duplicate of,
+    /// if any.
+    fn insert(&mut self, p: usize) -> Option<usize> {
+        let start = self.locate(p);
+
+        if !self.in_conflict(start, p) {
+            let t = self.tris[start];
+            return t
+                .iter()
+                .find(|v| **v != GHOST && self.points[**v] == self.points[p])
+                .cloned();
+        }
+
+        // the triangles whose circumcircle contains the point form a star
+        // shaped cavity around it, find them by visiting the neighbors
+        let mut cavity = vec![start];
+        let mut visited = HashSet::new();
+        visited.insert(start);
+
+        let mut i = 0;
+        while i < cavity.len() {
+            let t = cavity[i];
+            i += 1;
 
-    new_triangles.extend(
-        edges
+            for n in self.adj[t] {
+                if visited.insert(n) && self.in_conflict(n, p) {
+                    cavity.push(n);
+                }
+            }
+        }
+
+        let mut boundary = vec![];
+        for t in &cavity {
+            let tri = self.tris[*t];
+
+            for (i, n) in self.adj[*t].iter().enumerate() {
+                if !cavity.contains(n) {
+                    boundary.push([tri[(i + 1) % 3], tri[(i + 2) % 3], p]);
+                }
+            }
+        }
+
+        let created = self.replace(&cavity, boundary);
+        self.last = created
             .into_iter()
-            .map(|(pt0, pt1)| Triangle::new(pt0, pt1, *point)),
-    );
+            .find(|t| !self.tris[*t].contains(&GHOST))
+            .unwrap();
 
-    new_triangles
-}
+        None
+    }
 
-fn dedup_edges(edges: &[(Point<f64>, Point<f64>)]) -> Vec<(Point<f64>, Point<f64>)> {
-    // super ugly and super inefficient, but we cannot use hashmaps with f64...
+    /// Walk from the last triangle towards `p` and return the triangle that
+    /// contains it or the ghost triangle the walk ended in if it's outside the
+    /// convex hull.
+    fn locate(&mut self, p: usize) -> usize {
+        let pt = &self.points[p];
+        let mut t = self.last;
 
-    let mut out = vec![];
+        'walk: loop {
+            let tri = self.tris[t];
+            if tri.contains(&GHOST) {
+                return t;
+            }
 
-    for i in 0..edges.len() {
-        let mut count = 0;
+            // changing the first edge to check at every step avoids walking in
+            // circles
+            self.steps += 1;
+            for k in 0..3 {
+                let i = (k + self.steps) % 3;
+                let (a, b) = (tri[(i + 1) % 3], tri[(i + 2) % 3]);
 
-        for j in 0..edges.len() {
-            let (start, end) = &edges[j];
-            if edges[i] == (*start, *end) || edges[i] == (*end, *start) {
-                count += 1;
+                if orient2d(&self.points[a], &self.points[b], pt) < 0.0 {
+                    t = self.adj[t][i];
+                    continue 'walk;
+                }
             }
+
+            return t;
         }
+    }
+
+    /// Return whether the point `p` lies strictly inside the circumcircle of
+    /// the triangle `t`. Ghost triangles behave as if the vertex at infinity
+    /// was a point very far away in the direction opposite to the hull.
+    fn in_conflict(&self, t: usize, p: usize) -> bool {
+        let tri = self.tris[t];
+        let pt = &self.points[p];
 
-        if count == 1 {
-            out.push(edges[i]);
+        match tri.iter().position(|v| *v == GHOST) {
+            None => {
+                let [a, b, c] = tri;
+                in_circle(&self.points[a], &self.points[b], &self.points[c], pt) > 0.0
+            }
+            Some(g) => {
+                let a = &self.points[tri[(g + 1) % 3]];
+                let b = &self.points[tri[(g + 2) % 3]];
+
+                let o = orient2d(a, b, pt);
+                o > 0.0 || (o == 0.0 && strictly_between(a, b, pt))
+            }
         }
     }
 
-    out
+    /// Insert the edge between the two points into the triangulation by
+    /// removing all the edges it crosses and by triangulating again the
+    /// polygons on its sides.
+    fn insert_constraint(&mut self, mut u: usize, v: usize) -> Result<(), String> {
+        while u != v {
+            let (t, crossed) = match self.segment_start(u, v)? {
+                // the segment goes along an existing edge up to the point `w`
+                SegmentStart::Edge(w) => {
+                    self.constrained.insert(edge_key(u, w));
+                    u = w;
+                    continue;
+                }
+                SegmentStart::Crossing(t, crossed) => (t, crossed),
+            };
+
+            let (end, removed, left, mut right) = self.crossed_triangles(u, v, t, crossed)?;
+
+            // both chains must go from the start to the end of their edge
+            right.reverse();
+
+            let mut created = vec![];
+            triangulate_pseudo_polygon(self.points, u, end, left, &mut created);
+            triangulate_pseudo_polygon(self.points, end, u, right, &mut created);
+            self.replace(&removed, created);
+
+            self.constrained.insert(edge_key(u, end));
+            u = end;
+        }
+
+        Ok(())
+    }
+
+    /// Find where the segment from `u` to `v` leaves `u`, that is either along
+    /// an existing edge or by crossing the edge opposite to `u` of a triangle.
+    fn segment_start(&self, u: usize, v: usize) -> Result<SegmentStart, String> {
+        let (pu, pv) = (&self.points[u], &self.points[v]);
+
+        for t in self.triangles_around(u) {
+            let tri = self.tris[t];
+            let i = tri.iter().position(|x| *x == u).unwrap();
+            let (a, b) = (tri[(i + 1) % 3], tri[(i + 2) % 3]);
+
+            for w in [a, b] {
+                if w != GHOST
+                    && orient2d(pu, pv, &self.points[w]) == 0.0
+                    && (w == v || strictly_between(pu, pv, &self.points[w]))
+                {
+                    return Ok(SegmentStart::Edge(w));
+                }
+            }
+
+            if a == GHOST || b == GHOST {
+                continue;
+            }
+
+            let (pa, pb) = (&self.points[a], &self.points[b]);
+            if orient2d(pu, pa, pv) > 0.0 && orient2d(pu, pb, pv) < 0.0 {
+                if self.constrained.contains(&edge_key(a, b)) {
+                    return Err(crossing_error(u, v, a, b));
+                }
+
+                return Ok(SegmentStart::Crossing(t, i));
+            }
+        }
+
+        Err(format!("cannot find the edge from point {} to {}", u, v))
+    }
+
+    /// Walk along the segment from `u` to `v` starting from the triangle `t`
+    /// whose edge opposite to the vertex at index `crossed` is crossed by the
+    /// segment. Return the point the walk stopped at, `v` unless another point
+    /// lies on the segment, the crossed triangles and the points on the left
+    /// and on the right of the segment in the order they were met.
+    #[allow(clippy::type_complexity)]
+    fn crossed_triangles(
+        &self,
+        u: usize,
+        v: usize,
+        mut t: usize,
+        crossed: usize,
+    ) -> Result<(usize, Vec<usize>, Vec<usize>, Vec<usize>), String> {
+        let (pu, pv) = (&self.points[u], &self.points[v]);
+
+        let tri = self.tris[t];
+        let (mut right, mut left) = (tri[(crossed + 1) % 3], tri[(crossed + 2) % 3]);
+
+        let mut removed = vec![t];
+        let mut lefts = vec![left];
+        let mut rights = vec![right];
+
+        loop {
+            let i = self.tris[t]
+                .iter()
+                .position(|x| *x != left && *x != right)
+                .unwrap();
+            t = self.adj[t][i];
+            removed.push(t);
+
+            let w = self.tris[t]
+                .iter()
+                .cloned()
+                .find(|x| *x != left && *x != right)
+                .unwrap();
+
+            if w == v {
+                return Ok((v, removed, lefts, rights));
+            }
+
+            let o = orient2d(pu, pv, &self.points[w]);
+            if o == 0.0 {
+                return Ok((w, removed, lefts, rights));
+            }
+
+            let other = if o > 0.0 { right } else { left };
+            if self.constrained.contains(&edge_key(w, other)) {
+                return Err(crossing_error(u, v, w, other));
+            }
+
+            if o > 0.0 {
+                left = w;
+                lefts.push(w);
+            } else {
+                right = w;
+                rights.push(w);
+            }
+        }
+    }
+
+    /// Return the triangles around the point `u`, ghosts included.
+    fn triangles_around(&self, u: usize) -> Vec<usize> {
+        let start =
+            match (0..self.tris.len()).find(|t| !self.dead[*t] && self.tris[*t].contains(&u)) {
+                Some(start) => start,
+                None => return vec![],
+            };
+
+        let mut around = vec![start];
+        let mut t = start;
+        loop {
+            let i = self.tris[t].iter().position(|x| *x == u).unwrap();
+            t = self.adj[t][(i + 1) % 3];
+
+            if t == start {
+                return around;
+            }
+            around.push(t);
+        }
+    }
+
+    /// Remove the given triangles and add the new ones that must cover exactly
+    /// the same area. The adjacency is updated accordingly. Return the indices
+    /// of the new triangles.
+    fn replace(&mut self, removed: &[usize], created: Vec<[usize; 3]>) -> Vec<usize> {
+        // the triangles outside that share an edge with the removed ones
+        let mut outside = HashMap::new();
+        for t in removed {
+            let tri = self.tris[*t];
+
+            for (i, n) in self.adj[*t].iter().enumerate() {
+                if !removed.contains(n) {
+                    outside.insert((tri[(i + 1) % 3], tri[(i + 2) % 3]), *n);
+                }
+            }
+        }
+
+        for t in removed {
+            self.dead[*t] = true;
+            self.free.push(*t);
+        }
+
+        let indices = created
+            .into_iter()
+            .map(|tri| match self.free.pop() {
+                Some(t) => {
+                    self.tris[t] = tri;
+                    self.dead[t] = false;
+                    t
+                }
+                None => {
+                    self.tris.push(tri);
+                    self.adj.push([0; 3]);
+                    self.dead.push(false);
+                    self.tris.len() - 1
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut inside = HashMap::new();
+        for t in &indices {
+            let tri = self.tris[*t];
+
+            for i in 0..3 {
+                let (a, b) = (tri[(i + 1) % 3], tri[(i + 2) % 3]);
+
+                if let Some(n) = outside.get(&(a, b)) {
+                    self.adj[*t][i] = *n;
+
+                    let j = self.tris[*n]
+                        .iter()
+                        .position(|x| *x != a && *x != b)
+                        .unwrap();
+                    self.adj[*n][j] = *t;
+                } else if let Some((n, j)) = inside.remove(&(b, a)) {
+                    self.adj[*t][i] = n;
+                    self.adj[n][j] = *t;
+                } else {
+                    inside.insert((a, b), (*t, i));
+                }
+            }
+        }
+
+        indices
+    }
+
+    fn into_triangulation(self) -> Triangulation {
+        let mut index = vec![None; self.tris.len()];
+        let mut triangles = vec![];
+
+        for (t, tri) in self.tris.iter().enumerate() {
+            if !self.dead[t] && !tri.contains(&GHOST) {
+                index[t] = Some(triangles.len());
+                triangles.push(*tri);
+            }
+        }
+
+        let neighbors = (0..self.tris.len())
+            .filter(|t| index[*t].is_some())
+            .map(|t| {
+                let adj = self.adj[t];
+                [index[adj[0]], index[adj[1]], index[adj[2]]]
+            })
+            .collect();
+
+        Triangulation {
+            points: self.points.to_vec(),
+            triangles,
+            neighbors,
+            constrained: self.constrained,
+        }
+    }
+}
+
+enum SegmentStart {
+    Edge(usize),
+    Crossing(usize, usize),
+}
+
+/// Triangulate the polygon made by the edge from `a` to `b` and by the chain of
+/// points that lie on the left of it. Each new triangle is chosen so that its
+/// circumcircle doesn't contain any other point of the polygon.
+fn triangulate_pseudo_polygon(
+    points: &[PointF64],
+    a: usize,
+    b: usize,
+    chain: Vec<usize>,
+    out: &mut Vec<[usize; 3]>,
+) {
+    if chain.is_empty() {
+        return;
+    }
+
+    let mut c = 0;
+    for i in 1..chain.len() {
+        let (pa, pb) = (&points[a], &points[b]);
+
+        if in_circle(pa, pb, &points[chain[c]], &points[chain[i]]) > 0.0 {
+            c = i;
+        }
+    }
+
+    let (before, pc, after) = split_element_at(chain, c);
+    let pc = pc.unwrap();
+
+    triangulate_pseudo_polygon(points, a, pc, before, out);
+    triangulate_pseudo_polygon(points, pc, b, after, out);
+    out.push([a, b, pc]);
+}
+
+/// Return whether `p`, that must be collinear with `a` and `b`, lies strictly
+/// between them.
+fn strictly_between(a: &PointF64, b: &PointF64, p: &PointF64) -> bool {
+    let between = |a: f64, b: f64, p: f64| (a < p && p < b) || (b < p && p < a);
+
+    if a.x != b.x {
+        between(a.x, b.x, p.x)
+    } else {
+        between(a.y, b.y, p.y)
+    }
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn crossing_error(u: usize, v: usize, a: usize, b: usize) -> String {
+    format!(
+        "the constrained edges ({}, {}) and ({}, {}) cross each other",
+        u, v, a, b
+    )
+}
+
+/// Return the indices of the points sorted along a Hilbert curve so that
+/// consecutive points are close to each other.
+fn hilbert_order(points: &[PointF64]) -> Vec<usize> {
+    const ORDER: u32 = 16;
+    let side = f64::from((1_u32 << ORDER) - 1);
+
+    let bbox = points
+        .iter()
+        .fold(None, |bbox: Option<(PointF64, PointF64)>, p| match bbox {
+            None => Some((*p, *p)),
+            Some((min, max)) => Some((
+                Point::new(min.x.min(p.x), min.y.min(p.y)),
+                Point::new(max.x.max(p.x), max.y.max(p.y)),
+            )),
+        });
+
+    let (min, max) = match bbox {
+        Some(bbox) => bbox,
+        None => return vec![],
+    };
+    let scale = |v: f64, min: f64, max: f64| {
+        if max > min {
+            ((v - min) / (max - min) * side) as u32
+        } else {
+            0
+        }
+    };
+
+    let mut keys = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let x = scale(p.x, min.x, max.x);
+            let y = scale(p.y, min.y, max.y);
+
+            (hilbert_index(ORDER, x, y), i)
+        })
+        .collect::<Vec<_>>();
+
+    keys.sort();
+    keys.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Return the position of `(x, y)` along the Hilbert curve that fills a grid
+/// of side `2^order`.
+fn hilbert_index(order: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut d = 0;
+    let mut s = 1 << (order - 1);
+
+    while s > 0 {
+        let rx = u32::from(x & s > 0);
+        let ry = u32::from(y & s > 0);
+        d += u64::from(s) * u64::from(s) * u64::from((3 * rx) ^ ry);
+
+        // rotate the quadrant so that the curve is continuous
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        s >>= 1;
+    }
+
+    d
 }
 
 #[cfg(test)]
 mod test {
-    use super::dedup_edges;
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn grid(n: u32) -> Vec<PointF64> {
+        (0..n * n)
+            .map(|i| PointF64::new(f64::from(i % n), f64::from(i / n)))
+            .collect()
+    }
 
-    use geo::Point;
+    /// Check that the triangulation is valid and Delaunay, constrained edges
+    /// aside.
+    fn check(tri: &Triangulation) {
+        let points = tri.points();
+
+        for (t, (vs, ns)) in tri
+            .triangle_indices()
+            .iter()
+            .zip(tri.neighbors())
+            .enumerate()
+        {
+            let [a, b, c] = *vs;
+            assert!(orient2d(&points[a], &points[b], &points[c]) > 0.0);
+
+            for i in 0..3 {
+                let n = match ns[i] {
+                    Some(n) => n,
+                    None => continue,
+                };
+
+                // the neighbor shares the edge and points back to us
+                let (ea, eb) = (vs[(i + 1) % 3], vs[(i + 2) % 3]);
+                let other = tri.triangle_indices()[n];
+                assert!(other.contains(&ea) && other.contains(&eb));
+                assert!(tri.neighbors()[n].contains(&Some(t)));
+
+                if tri.is_constrained(ea, eb) {
+                    continue;
+                }
+
+                let opposite = other.iter().find(|v| **v != ea && **v != eb).unwrap();
+                assert!(in_circle(&points[a], &points[b], &points[c], &points[*opposite]) <= 0.0);
+            }
+        }
+    }
+
+    /// Twice the area covered by the triangles.
+    fn area(tri: &Triangulation) -> f64 {
+        tri.triangles().map(|t| t.area() * 2.0).sum()
+    }
 
     #[test]
-    fn test_dedup_edges() {
-        let edge1 = (Point::new(42.0, 12.0), Point::new(7.0, 12.0));
-        let redge1 = (edge1.1, edge1.0);
+    fn test_grid() {
+        // lots of cocircular and collinear points
+        let tri = Triangulation::new(&grid(10));
 
-        let edge2 = (Point::new(42.0, 73.0), Point::new(84.0, 146.0));
-        let redge2 = (edge2.1, edge2.0);
+        check(&tri);
+        assert_eq!(tri.triangle_indices().len(), 2 * 9 * 9);
+        assert_eq!(area(&tri), 2.0 * 81.0);
+        assert_eq!(tri.hull().len(), 36);
+        assert_eq!(tri.edges().count(), 9 * 10 * 2 + 81);
+    }
 
-        let edge3 = (Point::new(23.0, 32.0), Point::new(32.0, 23.0));
+    #[test]
+    fn test_degenerate() {
+        assert_eq!(Triangulation::new(&[]).triangle_indices().len(), 0);
 
-        let edges = vec![edge1, edge2, edge1, redge2, edge3, redge1, edge1, redge1];
+        let collinear = (0..5)
+            .map(|i| PointF64::new(f64::from(i), 2.0 * f64::from(i)))
+            .collect::<Vec<_>>();
+        assert_eq!(Triangulation::new(&collinear).triangle_indices().len(), 0);
 
-        assert_eq!(dedup_edges(&edges), vec![edge3]);
+        let points = vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(1.0, 0.0),
+            PointF64::new(0.0, 0.0),
+            PointF64::new(0.0, 1.0),
+            PointF64::new(1.0, 0.0),
+        ];
+        let tri = Triangulation::new(&points);
+        check(&tri);
+        assert_eq!(tri.triangle_indices().len(), 1);
+    }
+
+    #[test]
+    fn test_hull() {
+        let points = vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(1.0, 1.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(2.0, 0.0),
+            PointF64::new(4.0, 4.0),
+            PointF64::new(0.0, 4.0),
+        ];
+
+        let tri = Triangulation::new(&points);
+        check(&tri);
+        assert_eq!(tri.hull(), vec![0, 3, 2, 4, 5]);
+        assert_eq!(tri.triangle_indices().len(), 5);
+    }
+
+    #[test]
+    fn test_constraints() {
+        // two parallel constraints across the grid, through some of its points
+        let points = grid(6);
+        let tri = Triangulation::with_constraints(&points, &[(0, 35), (1, 29)]).unwrap();
+
+        check(&tri);
+        assert_eq!(area(&tri), 2.0 * 25.0);
+        assert!(tri.is_constrained(0, 7));
+        assert!(tri.is_constrained(28, 35));
+        assert!(tri.is_constrained(1, 8));
+        assert!(tri.is_constrained(22, 29));
+        assert!(!tri.is_constrained(0, 1));
+
+        // a constraint that crosses lots of edges
+        let tri = Triangulation::with_constraints(&points, &[(1, 34)]).unwrap();
+        check(&tri);
+        assert!(tri.edges().any(|(a, b)| edge_key(a, b) == (1, 34)));
+        assert_eq!(area(&tri), 2.0 * 25.0);
+
+        assert!(Triangulation::with_constraints(&points, &[(0, 35), (5, 30)]).is_err());
+        assert!(Triangulation::with_constraints(&points, &[(0, 36)]).is_err());
+    }
+
+    #[test]
+    fn test_triangulate() {
+        let bbox = BoundingBox::from_dimensions(10.0, 10.0);
+        let triangles = triangulate(&bbox, vec![PointF64::new(3.0, 4.0)]);
+
+        assert_eq!(triangles.len(), 4);
+        assert_eq!(triangles.iter().map(|t| t.area()).sum::<f64>(), 100.0);
+    }
+
+    #[test]
+    fn test_hilbert_index() {
+        assert_eq!(hilbert_index(1, 0, 0), 0);
+        assert_eq!(hilbert_index(1, 0, 1), 1);
+        assert_eq!(hilbert_index(1, 1, 1), 2);
+        assert_eq!(hilbert_index(1, 1, 0), 3);
+        assert_eq!(hilbert_index(2, 3, 0), 15);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_delaunay(points in prop::collection::vec((0_u8..32, 0_u8..32), 3..120)) {
+            let points = points
+                .into_iter()
+                .map(|(x, y)| PointF64::new(f64::from(x), f64::from(y)))
+                .collect::<Vec<_>>();
+
+            let tri = Triangulation::new(&points);
+            check(&tri);
+
+            // the triangles cover the convex hull
+            let hull = crate::convex_hull::convex_hull(points);
+            let hull_area = (0..hull.len())
+                .map(|i| {
+                    let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+                    a.x * b.y - b.x * a.y
+                })
+                .sum::<f64>()
+                .abs();
+            prop_assert!((area(&tri) - hull_area).abs() < 1e-9);
+        }
+
+        #[test]
+        fn prop_constrained_delaunay(
+            points in prop::collection::vec((0_u8..32, 0_u8..32), 3..120),
+            edge in (0_usize..120, 0_usize..120),
+        ) {
+            let points = points
+                .into_iter()
+                .map(|(x, y)| PointF64::new(f64::from(x), f64::from(y)))
+                .collect::<Vec<_>>();
+            let (a, b) = (edge.0 % points.len(), edge.1 % points.len());
+
+            let unconstrained = Triangulation::new(&points);
+            prop_assume!(!unconstrained.triangle_indices().is_empty());
+
+            let tri = Triangulation::with_constraints(&points, &[(a, b)]).unwrap();
+            check(&tri);
+            prop_assert!((area(&tri) - area(&unconstrained)).abs() < 1e-9);
+            prop_assert_eq!(tri.triangle_indices().len(), unconstrained.triangle_indices().len());
+        }
     }
 }
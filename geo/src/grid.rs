@@ -0,0 +1,427 @@
+//! A uniform [spatial hash grid](https://en.wikipedia.org/wiki/Spatial_hashing)
+//! that buckets the points into square cells of a fixed size.
+//!
+//! It's simpler and faster than a `KdTree` when the points are spread evenly
+//! and the queries look only at a small neighborhood, e.g. collision checks
+//! between particles of about the same size of the cells.
+
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::kdtree::{Axis, Range};
+use crate::point::Point;
+use crate::utils::{OrdF64, OrdWrapper};
+
+/// The index of a cell of the grid.
+type Cell = (i64, i64);
+
+/// A uniform spatial hash grid.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid<T, V> {
+    cell_size: f64,
+    cells: HashMap<Cell, Vec<(Point<T>, V)>>,
+    length: usize,
+
+    // the smallest and biggest indices of the cells that ever contained a
+    // point, nothing can be found outside of them
+    extent: Option<(Cell, Cell)>,
+}
+
+impl<T, V> SpatialGrid<T, V>
+where
+    T: Copy + PartialEq + num::ToPrimitive,
+{
+    /// Create a new empty grid whose cells have the given side. Panics if the
+    /// size is not positive.
+    pub fn new(cell_size: f64) -> Self {
+        assert!(cell_size > 0.0, "the cell size must be positive");
+
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+            length: 0,
+            extent: None,
+        }
+    }
+
+    /// Return the side of the cells.
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    /// Check if this grid is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the number of points in this grid.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Add a point to this grid, return the old value if the point was already
+    /// there.
+    pub fn add(&mut self, point: Point<T>, value: V) -> Option<V> {
+        let cell = self.cell_of(&point);
+
+        let bucket = self.cells.entry(cell).or_default();
+        if let Some((_, v)) = bucket.iter_mut().find(|(p, _)| *p == point) {
+            return Some(std::mem::replace(v, value));
+        }
+
+        bucket.push((point, value));
+        self.length += 1;
+
+        self.extent = Some(match self.extent {
+            None => (cell, cell),
+            Some((min, max)) => (
+                (min.0.min(cell.0), min.1.min(cell.1)),
+                (max.0.max(cell.0), max.1.max(cell.1)),
+            ),
+        });
+
+        None
+    }
+
+    /// Remove the given point from this grid and return its value if it was
+    /// present.
+    pub fn remove(&mut self, point: &Point<T>) -> Option<V> {
+        let cell = self.cell_of(point);
+
+        let bucket = self.cells.get_mut(&cell)?;
+        let i = bucket.iter().position(|(p, _)| p == point)?;
+        let (_, value) = bucket.swap_remove(i);
+
+        if bucket.is_empty() {
+            self.cells.remove(&cell);
+        }
+
+        self.length -= 1;
+        Some(value)
+    }
+
+    /// Return the value of the given point if it's in this grid.
+    pub fn get(&self, point: &Point<T>) -> Option<&V> {
+        self.cells
+            .get(&self.cell_of(point))?
+            .iter()
+            .find(|(p, _)| p == point)
+            .map(|(_, v)| v)
+    }
+
+    /// Return a mutable reference to the value of the given point if it's in
+    /// this grid.
+    pub fn get_mut(&mut self, point: &Point<T>) -> Option<&mut V> {
+        let cell = self.cell_of(point);
+
+        self.cells
+            .get_mut(&cell)?
+            .iter_mut()
+            .find(|(p, _)| p == point)
+            .map(|(_, v)| v)
+    }
+
+    /// Return an iterator over all the points of the grid in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Point<T>, &V)> {
+        self.cells.values().flatten().map(|(p, v)| (p, v))
+    }
+
+    /// Return all the points that are in the given range.
+    pub fn in_range_iter<'s, 'r, R>(
+        &'s self,
+        range: &'r R,
+    ) -> impl Iterator<Item = (&'s Point<T>, &'s V)> + 'r
+    where
+        R: Range<Point<T>, AxisValue = T>,
+        's: 'r,
+    {
+        let (x0, x1) = range.axis_value_range(Axis::X);
+        let (y0, y1) = range.axis_value_range(Axis::Y);
+
+        let min = self.cell_of(&Point { x: x0, y: y0 });
+        let max = self.cell_of(&Point { x: x1, y: y1 });
+
+        self.buckets_between(min, max)
+            .into_iter()
+            .flatten()
+            .filter(move |(p, _)| range.contains(p))
+            .map(|(p, v)| (p, v))
+    }
+
+    /// Return the nearest neighbor to the given point.
+    pub fn nearest_neighbor(&self, point: Point<T>) -> Option<(&Point<T>, &V)> {
+        self.nearest_neighbors(point, 1).into_iter().next()
+    }
+
+    /// Return, at most, the k nearest neighbors to the given point, closest
+    /// first.
+    pub fn nearest_neighbors<'s>(
+        &'s self,
+        point: Point<T>,
+        k: usize,
+    ) -> Vec<(&'s Point<T>, &'s V)> {
+        let (min, max) = match self.extent {
+            Some(extent) if k > 0 => extent,
+            _ => return vec![],
+        };
+
+        let target = to_f64(&point);
+        let center = self.cell_of(&point);
+        let mut neighbors = BinaryHeap::with_capacity(k + 1);
+
+        let push_bucket = |neighbors: &mut BinaryHeap<_>, bucket: &'s Vec<(Point<T>, V)>| {
+            for (p, v) in bucket {
+                let d = to_f64(p).squared_dist::<f64>(&target);
+
+                neighbors.push(OrdWrapper::new((p, v), OrdF64(d)));
+                if neighbors.len() > k {
+                    neighbors.pop();
+                }
+            }
+        };
+
+        // no cell is farther than this ring from the center
+        let last_ring = (center.0 - min.0)
+            .abs()
+            .max((center.0 - max.0).abs())
+            .max((center.1 - min.1).abs())
+            .max((center.1 - max.1).abs());
+
+        // visit the rings of cells around the one of the point until the
+        // points in the next rings cannot be closer than the ones found
+        for ring in 0..=last_ring {
+            let ring_cells = if ring == 0 { 1 } else { 8 * ring };
+
+            if (ring_cells as usize) > self.cells.len() {
+                // the grid is sparse, checking all the cells is faster
+                neighbors.clear();
+                for bucket in self.cells.values() {
+                    push_bucket(&mut neighbors, bucket);
+                }
+                break;
+            }
+
+            for cell in ring_around(center, ring) {
+                if let Some(bucket) = self.cells.get(&cell) {
+                    push_bucket(&mut neighbors, bucket);
+                }
+            }
+
+            if neighbors.len() == k {
+                let worst: &OrdWrapper<_, OrdF64> = neighbors.peek().unwrap();
+                let reach = ring as f64 * self.cell_size;
+
+                if worst.key().0 <= reach * reach {
+                    break;
+                }
+            }
+        }
+
+        neighbors
+            .into_sorted_vec()
+            .into_iter()
+            .map(|ow| *ow.data())
+            .collect()
+    }
+
+    /// Return all the points whose distance from the given point is at most
+    /// `radius` along with their distances, in no particular order.
+    pub fn radius_search(&self, point: Point<T>, radius: f64) -> Vec<(&Point<T>, &V, f64)> {
+        let target = to_f64(&point);
+
+        let min = self.cell_of_f64(target.x - radius, target.y - radius);
+        let max = self.cell_of_f64(target.x + radius, target.y + radius);
+
+        self.buckets_between(min, max)
+            .into_iter()
+            .flatten()
+            .filter_map(|(p, v)| {
+                let d = to_f64(p).dist::<f64>(&target);
+                if d <= radius {
+                    Some((p, v, d))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Return the non empty buckets of the cells between the given ones
+    /// included.
+    fn buckets_between(&self, min: Cell, max: Cell) -> Vec<&Vec<(Point<T>, V)>> {
+        if min.0 > max.0 || min.1 > max.1 {
+            return vec![];
+        }
+
+        let ncells = (max.0 - min.0 + 1) as f64 * (max.1 - min.1 + 1) as f64;
+        let inside = |c: &Cell| c.0 >= min.0 && c.0 <= max.0 && c.1 >= min.1 && c.1 <= max.1;
+
+        if ncells > self.cells.len() as f64 {
+            return self
+                .cells
+                .iter()
+                .filter(|(c, _)| inside(c))
+                .map(|(_, b)| b)
+                .collect();
+        }
+
+        (min.0..=max.0)
+            .flat_map(|x| (min.1..=max.1).map(move |y| (x, y)))
+            .filter_map(|c| self.cells.get(&c))
+            .collect()
+    }
+
+    fn cell_of(&self, point: &Point<T>) -> Cell {
+        let p = to_f64(point);
+        self.cell_of_f64(p.x, p.y)
+    }
+
+    fn cell_of_f64(&self, x: f64, y: f64) -> Cell {
+        (
+            (x / self.cell_size).floor() as i64,
+            (y / self.cell_size).floor() as i64,
+        )
+    }
+}
+
+/// Return the cells whose Chebyshev distance from the center is `ring`.
+fn ring_around(center: Cell, ring: i64) -> impl Iterator<Item = Cell> {
+    let (cx, cy) = center;
+
+    // the top and bottom rows are the same cell when the ring is the center
+    let rows = if ring == 0 {
+        vec![cy]
+    } else {
+        vec![cy - ring, cy + ring]
+    };
+
+    let horizontal =
+        (-ring..=ring).flat_map(move |dx| rows.clone().into_iter().map(move |y| (cx + dx, y)));
+    let vertical =
+        (1 - ring..ring).flat_map(move |dy| [(cx - ring, cy + dy), (cx + ring, cy + dy)]);
+
+    horizontal.chain(vertical)
+}
+
+fn to_f64<T: num::ToPrimitive>(point: &Point<T>) -> Point<f64> {
+    Point::new(point.x.to_f64().unwrap(), point.y.to_f64().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    use proptest::prelude::*;
+
+    use crate::{BoundingBox, PointF64, PointU32};
+
+    #[test]
+    fn test_ring_around() {
+        assert_eq!(ring_around((3, 4), 0).collect::<Vec<_>>(), vec![(3, 4)]);
+
+        let ring = ring_around((0, 0), 2).collect::<HashSet<_>>();
+        assert_eq!(ring.len(), 16);
+        assert!(ring.iter().all(|(x, y)| x.abs().max(y.abs()) == 2));
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let mut grid = SpatialGrid::new(10.0);
+
+        assert_eq!(grid.add(PointU32::new(5, 5), "a"), None);
+        assert_eq!(grid.add(PointU32::new(15, 5), "b"), None);
+        assert_eq!(grid.add(PointU32::new(5, 5), "c"), Some("a"));
+        assert_eq!(grid.len(), 2);
+
+        *grid.get_mut(&PointU32::new(15, 5)).unwrap() = "d";
+        assert_eq!(grid.get(&PointU32::new(15, 5)), Some(&"d"));
+
+        assert_eq!(grid.remove(&PointU32::new(5, 5)), Some("c"));
+        assert_eq!(grid.remove(&PointU32::new(5, 5)), None);
+        assert_eq!(grid.get(&PointU32::new(5, 5)), None);
+        assert_eq!(grid.len(), 1);
+
+        assert_eq!(
+            grid.nearest_neighbor(PointU32::new(0, 0)),
+            Some((&PointU32::new(15, 5), &"d"))
+        );
+    }
+
+    #[test]
+    fn test_negative_coordinates() {
+        let mut grid = SpatialGrid::new(1.0);
+        grid.add(PointF64::new(-0.5, -0.5), 1);
+        grid.add(PointF64::new(0.5, 0.5), 2);
+
+        assert_eq!(
+            grid.nearest_neighbor(PointF64::new(-3.0, -1.0)).unwrap().1,
+            &1
+        );
+        assert_eq!(grid.radius_search(PointF64::new(0.0, 0.0), 1.0).len(), 2);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(200))]
+        #[test]
+        fn prop_grid_same_as_loop(
+            points in prop::collection::hash_set((0_u32..255, 0_u32..255), 0..100),
+            to_search in (0_u32..300, 0_u32..300),
+            k in 0_usize..6,
+            radius in 0.0..100.0,
+            rect in (0_u32..255, 0_u32..255, 0_u32..255, 0_u32..255),
+            cell_size in 1.0..64.0,
+        ) {
+            let points = points
+                .into_iter()
+                .map(|(x, y)| PointU32::new(x, y))
+                .collect::<Vec<_>>();
+            let mut grid = SpatialGrid::new(cell_size);
+            for p in &points {
+                grid.add(*p, ());
+            }
+            let to_search = PointU32::new(to_search.0, to_search.1);
+
+            let mut distances = points
+                .iter()
+                .map(|p| p.squared_dist::<i64>(&to_search))
+                .collect::<Vec<_>>();
+            distances.sort();
+            distances.truncate(k);
+
+            let found = grid
+                .nearest_neighbors(to_search, k)
+                .into_iter()
+                .map(|(p, _)| p.squared_dist::<i64>(&to_search))
+                .collect::<Vec<_>>();
+            prop_assert_eq!(found, distances);
+
+            let mut found = grid
+                .radius_search(to_search, radius)
+                .into_iter()
+                .map(|(p, _, _)| *p)
+                .collect::<Vec<_>>();
+            let mut brute_force = points
+                .iter()
+                .filter(|p| p.dist::<f64>(&to_search) <= radius)
+                .cloned()
+                .collect::<Vec<_>>();
+            found.sort_by_key(|p| (p.x, p.y));
+            brute_force.sort_by_key(|p| (p.x, p.y));
+            prop_assert_eq!(found, brute_force);
+
+            let range =
+                BoundingBox::from_dimensions_and_origin(&PointU32::new(rect.0, rect.1), rect.2, rect.3);
+            let mut found = grid.in_range_iter(&range).map(|(p, _)| *p).collect::<Vec<_>>();
+            let mut brute_force = points
+                .iter()
+                .filter(|p| range.contains(p))
+                .cloned()
+                .collect::<Vec<_>>();
+            found.sort_by_key(|p| (p.x, p.y));
+            brute_force.sort_by_key(|p| (p.x, p.y));
+            prop_assert_eq!(found, brute_force);
+        }
+    }
+}
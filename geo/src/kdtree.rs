@@ -1,22 +1,26 @@
-//! A simple(and probably inefficient) implementation of a [K-d
-//! Tree](https://en.wikipedia.org/wiki/K-d_tree). Only 2D as of now.
+//! An implementation of a [K-d Tree](https://en.wikipedia.org/wiki/K-d_tree)
+//! over points of any number of dimensions, e.g. `Point`s in the plane or
+//! `[u8; 3]` colors.
+//!
+//! The nearest neighbors queries keep the best candidates in a bounded max heap,
+//! or just the best one when looking for the nearest neighbor only, and skip
+//! every branch that cannot contain anything closer than the worst of them,
+//! optionally trading some accuracy for speed. See `benches/kdtree.rs`
+//! for a comparison against brute force.
 
 use std::cmp::{Ord, Ordering};
-use std::collections::{BinaryHeap, VecDeque};
+use std::collections::BinaryHeap;
+use std::iter::FromIterator;
 
 use crate::bbox::BoundingBox;
+use crate::metric::{Metric, Weighting};
 use crate::point::Point;
-use crate::utils::{ksmallest_by_key, split_element_at, OrdWrapper};
+use crate::utils::{split_element_at, OrdF64, OrdWrapper};
 
-/// The axis used to split the space at a given point.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Axis {
-    /// X axis.
-    X,
-
-    /// Y axis.
-    Y,
-}
+/// The axis used to split the space at a given point. Axes are numbered from
+/// 0, that is x, to the number of dimensions of the space excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Axis(pub usize);
 
 /// Trait that allows to extract the axis value for a given axis from an entity
 /// contained in the KdTree.
@@ -24,25 +28,28 @@ pub trait AxisValue {
     /// The value that will be returned by axis_value.
     type Value;
 
+    /// The number of axes of the entity.
+    const DIMENSIONS: usize;
+
     /// Return the value for the given axis.
     fn axis_value(&self, axis: Axis) -> &Self::Value;
 }
 
 /// A [K-d Tree](https://en.wikipedia.org/wiki/K-d_tree).
 #[derive(Debug, PartialEq)]
-pub struct KdTree<T, V> {
-    root: Option<Node<T, V>>,
+pub struct KdTree<P, V> {
+    root: Option<Node<P, V>>,
     length: usize,
 }
 
 #[derive(Debug, PartialEq)]
-struct Node<T, V> {
+struct Node<P, V> {
     axis: Axis,
-    median: Point<T>,
+    median: P,
     value: V,
 
-    left: Option<Box<Node<T, V>>>,
-    right: Option<Box<Node<T, V>>>,
+    left: Option<Box<Node<P, V>>>,
+    right: Option<Box<Node<P, V>>>,
 }
 
 /// Simple trait that allow to support range queries for multiple types of
@@ -58,7 +65,7 @@ pub trait Range<T> {
     fn axis_value_range(&self, axis: Axis) -> (Self::AxisValue, Self::AxisValue);
 }
 
-impl<T, V> Default for KdTree<T, V> {
+impl<P, V> Default for KdTree<P, V> {
     fn default() -> Self {
         KdTree {
             root: None,
@@ -67,10 +74,10 @@ impl<T, V> Default for KdTree<T, V> {
     }
 }
 
-impl<T, V> KdTree<T, V>
+impl<P, V> KdTree<P, V>
 where
-    T: Copy + Ord,
-    Point<T>: AxisValue<Value = T>,
+    P: AxisValue,
+    P::Value: Copy + Ord,
 {
     /// Create a new empty KdTree.
     pub fn new() -> Self {
@@ -87,46 +94,36 @@ where
         self.length
     }
 
-    /// Build a KdTree from a vector of points. This method should be preferred
-    /// over to add when the set of points doesn't change because it creates a
-    /// tree that is often more balanced. The construction is a bit slower
-    /// though.
-    pub fn from_vector(points: Vec<(Point<T>, V)>) -> Self {
-        let mut kdtree = KdTree::default();
-
-        let mut ranges = VecDeque::new();
-        ranges.push_back((points, Axis::X));
-
-        while let Some((mut points, axis)) = ranges.pop_front() {
-            if points.is_empty() {
-                continue;
-            }
-
-            let mid = points.len() / 2;
-
-            // this is actually partitioning data at the median
-            ksmallest_by_key(&mut points, mid, |(pt, _val)| {
-                (*pt.axis_value(axis), *pt.axis_value(axis.next()))
-            })
-            .unwrap();
-
-            let (left, elem, right) = split_element_at(points, mid);
+    /// Return the number of levels of this kdtree, 0 if it's empty. A balanced
+    /// tree has about log2(len) levels.
+    pub fn depth(&self) -> usize {
+        self.root.as_ref().map_or(0, Node::depth)
+    }
 
-            let (new_point, new_val) = elem.unwrap();
-            kdtree.add(new_point, new_val);
+    /// Build a balanced KdTree from a vector of points. This method should be
+    /// preferred over to add when the set of points doesn't change because it
+    /// creates a tree that is often more balanced. The construction is a bit
+    /// slower though. If a point appears more than once only its last value is
+    /// kept.
+    pub fn from_vector(mut points: Vec<(P, V)>) -> Self {
+        // a stable sort keeps the duplicates in order and dedup keeps the
+        // first one, hence the reverse to keep the last one.
+        points.reverse();
+        points.sort_by(|(p1, _), (p2, _)| cmp_points(p1, p2, Axis(0)));
+        points.dedup_by(|(p1, _), (p2, _)| cmp_points(p1, p2, Axis(0)) == Ordering::Equal);
 
-            ranges.push_back((left, axis.next()));
-            ranges.push_back((right, axis.next()));
+        KdTree {
+            length: points.len(),
+            root: Node::build(points, Axis(0)).map(|root| *root),
         }
-
-        kdtree
     }
 
     /// Add a point to this KdTree. Note that this could unbalance the tree,
-    /// prefer from_vector if the set of points is not dynamic.
-    pub fn add(&mut self, point: Point<T>, value: V) -> Option<V> {
+    /// prefer from_vector if the set of points is not dynamic or call
+    /// `rebalance` once in a while.
+    pub fn add(&mut self, point: P, value: V) -> Option<V> {
         if self.root.is_none() {
-            self.root = Some(Node::new(point, value, Axis::X));
+            self.root = Some(Node::new(point, value, Axis(0)));
             self.length = 1;
 
             return None;
@@ -142,79 +139,224 @@ where
         old_value
     }
 
+    /// Remove the given point from this KdTree and return its value if it was
+    /// present. The subtree below the point is rebuilt.
+    pub fn remove(&mut self, point: &P) -> Option<V> {
+        let root_node = self.root.as_mut()?;
+
+        let removed = if root_node.cmp_to_point(point) == Ordering::Equal {
+            let root_node = self.root.take().unwrap();
+            let axis = root_node.axis;
+
+            let mut rest = vec![];
+            let (_, value) = root_node.into_entries(&mut rest);
+            self.root = Node::build(rest, axis).map(|root| *root);
+
+            Some(value)
+        } else {
+            root_node.remove(point)
+        };
+
+        if removed.is_some() {
+            self.length -= 1;
+        }
+
+        removed
+    }
+
+    /// Return the value of the given point if it's in this KdTree.
+    pub fn get(&self, point: &P) -> Option<&V> {
+        let mut node = self.root.as_ref();
+
+        while let Some(n) = node {
+            node = match n.cmp_to_point(point) {
+                Ordering::Equal => return Some(&n.value),
+                Ordering::Less => n.left.as_deref(),
+                Ordering::Greater => n.right.as_deref(),
+            };
+        }
+
+        None
+    }
+
+    /// Return a mutable reference to the value of the given point if it's in
+    /// this KdTree.
+    pub fn get_mut(&mut self, point: &P) -> Option<&mut V> {
+        let mut node = self.root.as_mut();
+
+        while let Some(n) = node {
+            node = match n.cmp_to_point(point) {
+                Ordering::Equal => return Some(&mut n.value),
+                Ordering::Less => n.left.as_deref_mut(),
+                Ordering::Greater => n.right.as_deref_mut(),
+            };
+        }
+
+        None
+    }
+
+    /// Rebuild this KdTree so that it's balanced again after many additions
+    /// or removals.
+    pub fn rebalance(&mut self) {
+        let entries = self.drain();
+        *self = Self::from_vector(entries);
+    }
+
+    /// Remove all the points from this KdTree and return them along with their
+    /// values.
+    pub fn drain(&mut self) -> Vec<(P, V)> {
+        let mut entries = Vec::with_capacity(self.length);
+
+        if let Some(root_node) = self.root.take() {
+            let entry = root_node.into_entries(&mut entries);
+            entries.push(entry);
+        }
+
+        self.length = 0;
+        entries
+    }
+}
+
+impl<P, V> KdTree<P, V>
+where
+    P: AxisValue,
+    P::Value: Copy + Ord,
+{
     /// Return an iterator over all the elements of the tree.
-    pub fn iter(&self) -> KdTreeIter<T, V> {
+    pub fn iter(&self) -> KdTreeIter<'_, P, V> {
         KdTreeIter {
-            nodes: self.root.as_ref().map(|r| vec![r]).unwrap_or_else(Vec::new),
+            nodes: self.root.as_ref().map(|r| vec![r]).unwrap_or_default(),
         }
     }
 
     /// Return all the points that are in the given range.
-    pub fn in_range_iter<'s, 'r, R>(self: &'s Self, range: &'r R) -> InRangeIter<'s, 'r, T, V, R>
+    pub fn in_range_iter<'s, 'r, R>(&'s self, range: &'r R) -> InRangeIter<'s, 'r, P, V, R>
     where
-        R: Range<Point<T>, AxisValue = T>,
+        R: Range<P, AxisValue = P::Value>,
     {
         InRangeIter {
-            nodes: self.root.as_ref().map(|r| vec![r]).unwrap_or_else(Vec::new),
+            nodes: self.root.as_ref().map(|r| vec![r]).unwrap_or_default(),
             range,
         }
     }
 
     /// Return the nearest neighbor to the given point.
-    pub fn nearest_neighbor(&self, point: Point<T>) -> Option<(&Point<T>, &V)>
+    pub fn nearest_neighbor(&self, point: P) -> Option<(&P, &V)>
     where
-        T: num::Num + From<u8> + ::std::fmt::Debug,
-        V: ::std::fmt::Debug,
-        i64: From<T>,
+        P::Value: num::ToPrimitive,
     {
         self.nearest_neighbors(point, 1).into_iter().next()
     }
 
-    /// Return, at most, the k nearest neighbors to the given point.
-    pub fn nearest_neighbors(&self, point: Point<T>, k: usize) -> Vec<(&Point<T>, &V)>
+    /// Return, at most, the k nearest neighbors to the given point, closest
+    /// first.
+    pub fn nearest_neighbors(&self, point: P, k: usize) -> Vec<(&P, &V)>
     where
-        T: num::Num + From<u8> + ::std::fmt::Debug,
-        V: ::std::fmt::Debug,
-        i64: From<T>,
+        P::Value: num::ToPrimitive,
     {
-        if self.root.is_none() || k == 0 {
-            return vec![];
-        }
+        self.nearest_neighbors_by(point, k, Metric::SquaredEuclidean)
+            .into_iter()
+            .map(|(pt, v, _)| (pt, v))
+            .collect()
+    }
 
-        let root_node = self.root.as_ref().unwrap();
-        let mut nodes = vec![root_node];
+    /// Return, at most, k approximate nearest neighbors to the given point
+    /// according to the given metric along with their distances, closest
+    /// first. The distance of the i-th neighbor is at most `1 + epsilon` times
+    /// the distance of the actual i-th nearest neighbor, but in exchange many
+    /// more branches of the tree are skipped. An `epsilon` of 0 gives the
+    /// exact neighbors.
+    pub fn approximate_nearest_neighbors(
+        &self,
+        point: P,
+        k: usize,
+        metric: Metric,
+        epsilon: f64,
+    ) -> Vec<(&P, &V, f64)>
+    where
+        P::Value: num::ToPrimitive,
+    {
+        // squared distances are squared also in the approximation factor
+        let slack = match metric {
+            Metric::SquaredEuclidean => (1.0 + epsilon) * (1.0 + epsilon),
+            _ => 1.0 + epsilon,
+        };
 
-        let mut neighbors = BinaryHeap::new();
-        let mut min_dist = i64::max_value();
+        self.search(point, k, metric, slack, |_, d| d, |d| d)
+    }
 
-        while let Some(node) = nodes.pop() {
-            let node_dist = node.median.squared_dist(&point);
+    /// Return, at most, the k nearest neighbors to the given point according
+    /// to the given metric along with their distances, closest first.
+    pub fn nearest_neighbors_by(&self, point: P, k: usize, metric: Metric) -> Vec<(&P, &V, f64)>
+    where
+        P::Value: num::ToPrimitive,
+    {
+        self.search(point, k, metric, 1.0, |_, d| d, |d| d)
+    }
 
-            min_dist = min_dist.min(node_dist);
-            neighbors.push(OrdWrapper::new(node, node_dist));
+    /// Return, at most, the k nearest neighbors to the given point according
+    /// to the given metric where the distance from every point is weighted by
+    /// the weight of its value. `max_weight` must not be smaller than any
+    /// weight and multiplicative weights must be positive.
+    ///
+    /// Additively weighted Euclidean distances give Apollonius diagrams while
+    /// additively weighted squared Euclidean distances give power diagrams.
+    pub fn weighted_nearest_neighbors(
+        &self,
+        point: P,
+        k: usize,
+        metric: Metric,
+        weighting: Weighting,
+        weight: impl Fn(&V) -> f64,
+        max_weight: f64,
+    ) -> Vec<(&P, &V, f64)>
+    where
+        P::Value: num::ToPrimitive,
+    {
+        self.search(
+            point,
+            k,
+            metric,
+            1.0,
+            |v, d| weighting.apply(d, weight(v)),
+            |d| weighting.apply(d, max_weight),
+        )
+    }
 
-            if neighbors.len() > k {
-                neighbors.pop();
-            }
+    /// Return all the points whose Euclidean distance from the given point is
+    /// at most `radius` along with their distances, in no particular order.
+    pub fn radius_search(&self, point: P, radius: f64) -> Vec<(&P, &V, f64)>
+    where
+        P::Value: num::ToPrimitive,
+    {
+        self.radius_search_by(point, radius, Metric::Euclidean)
+    }
 
-            // since nodes is a stack, push first the data that must be computed
-            // last. In this case we want to perform the wrong path after we
-            // checked the good one.
+    /// Return all the points whose distance from the given point according to
+    /// the given metric is at most `radius` along with their distances, in no
+    /// particular order.
+    pub fn radius_search_by(&self, point: P, radius: f64, metric: Metric) -> Vec<(&P, &V, f64)>
+    where
+        P::Value: num::ToPrimitive,
+    {
+        let mut nodes = self.root.as_ref().map(|r| vec![r]).unwrap_or_default();
+        let mut found = vec![];
+
+        while let Some(node) = nodes.pop() {
+            let dist = metric.distance(deltas(&node.median, &point));
+            if dist <= radius {
+                found.push((&node.median, &node.value, dist));
+            }
 
-            let (next, candidate) = match node.cmp_to_point_value(point) {
-                Ordering::Less | Ordering::Equal => (&node.left, &node.right),
-                Ordering::Greater => (&node.right, &node.left),
+            let plane_dist = axis_delta(&point, &node.median, node.axis);
+            let (next, candidate) = if plane_dist <= 0.0 {
+                (&node.left, &node.right)
+            } else {
+                (&node.right, &node.left)
             };
 
             if let Some(candidate_node) = candidate {
-                // check if there could be intersection on the wrong side of the
-                // plane. This is done by checking whether the candidate point's
-                // axis is still reachable within the current minimum distance.
-                let split_plane = i64::from(*node.median.axis_value(node.axis));
-                let plane_dist = i64::from(*point.axis_value(node.axis)) - split_plane;
-                let plane_dist2 = plane_dist * plane_dist;
-
-                if plane_dist2 <= min_dist {
+                if metric.axis_distance(plane_dist) <= radius {
                     nodes.push(candidate_node);
                 }
             }
@@ -224,23 +366,141 @@ where
             }
         }
 
+        found
+    }
+
+    /// Find the k nearest neighbors where `distance` turns the metric distance
+    /// from the point of a node into the actual distance and `lower_bound`
+    /// turns a metric distance into the smallest actual distance it can be
+    /// turned into.
+    ///
+    /// The neighbors are kept in a max heap of at most k elements, or just
+    /// aside when k is 1, so that the farthest one is always at hand: a
+    /// subtree is skipped when the closest it can be, multiplied by `slack`,
+    /// is not closer than the farthest neighbor found so far.
+    fn search(
+        &self,
+        point: P,
+        k: usize,
+        metric: Metric,
+        slack: f64,
+        distance: impl Fn(&V, f64) -> f64,
+        lower_bound: impl Fn(f64) -> f64,
+    ) -> Vec<(&P, &V, f64)>
+    where
+        P::Value: num::ToPrimitive,
+    {
+        let root_node = match self.root {
+            Some(ref root) if k > 0 => root,
+            _ => return vec![],
+        };
+
+        // the nodes to visit along with a lower bound of the distance of any
+        // point in their subtree
+        let mut nodes = vec![(root_node, f64::NEG_INFINITY)];
+        let mut neighbors = Neighbors::new(k);
+
+        while let Some((node, bound)) = nodes.pop() {
+            if let Some(worst) = neighbors.worst() {
+                if bound * slack >= worst {
+                    continue;
+                }
+            }
+
+            let node_dist = distance(&node.value, metric.distance(deltas(&node.median, &point)));
+            neighbors.push(node, node_dist);
+
+            let plane_dist = axis_delta(&point, &node.median, node.axis);
+            let (next, candidate) = if plane_dist <= 0.0 {
+                (&node.left, &node.right)
+            } else {
+                (&node.right, &node.left)
+            };
+
+            // since nodes is a stack, push the wrong side of the plane first so
+            // that it's visited after the good one and hopefully pruned.
+            if let Some(candidate_node) = candidate {
+                let plane_bound = lower_bound(metric.axis_distance(plane_dist));
+                nodes.push((candidate_node, bound.max(plane_bound)));
+            }
+
+            if let Some(next_node) = next {
+                nodes.push((next_node, bound));
+            }
+        }
+
         neighbors
             .into_sorted_vec()
             .into_iter()
-            .map(|ow| {
-                let (node, _) = ow.into();
-                (&node.median, &node.value)
-            })
+            .map(|(node, d)| (&node.median, &node.value, d))
             .collect()
     }
 }
 
-impl<T, V> Node<T, V>
+/// The best candidates found so far by a nearest neighbors search. The single
+/// nearest neighbor is by far the most common query, e.g. once per pixel in a
+/// Voronoi diagram, and it doesn't need a heap at all.
+enum Neighbors<'a, P, V> {
+    Nearest(Option<(&'a Node<P, V>, f64)>),
+    Heap(BinaryHeap<OrdWrapper<&'a Node<P, V>, OrdF64>>, usize),
+}
+
+impl<'a, P, V> Neighbors<'a, P, V> {
+    fn new(k: usize) -> Self {
+        if k == 1 {
+            Neighbors::Nearest(None)
+        } else {
+            Neighbors::Heap(BinaryHeap::with_capacity(k + 1), k)
+        }
+    }
+
+    /// Return the distance of the farthest neighbor if all the k neighbors
+    /// have been found already.
+    fn worst(&self) -> Option<f64> {
+        match self {
+            Neighbors::Nearest(best) => best.map(|(_, d)| d),
+            Neighbors::Heap(heap, k) if heap.len() == *k => heap.peek().map(|w| w.key().0),
+            Neighbors::Heap(_, _) => None,
+        }
+    }
+
+    fn push(&mut self, node: &'a Node<P, V>, dist: f64) {
+        match self {
+            Neighbors::Nearest(best) => {
+                if best.is_none_or(|(_, d)| dist < d) {
+                    *best = Some((node, dist));
+                }
+            }
+            Neighbors::Heap(heap, k) => {
+                heap.push(OrdWrapper::new(node, OrdF64(dist)));
+                if heap.len() > *k {
+                    heap.pop();
+                }
+            }
+        }
+    }
+
+    fn into_sorted_vec(self) -> Vec<(&'a Node<P, V>, f64)> {
+        match self {
+            Neighbors::Nearest(best) => best.into_iter().collect(),
+            Neighbors::Heap(heap, _) => heap
+                .into_sorted_vec()
+                .into_iter()
+                .map(|ow| {
+                    let (node, OrdF64(d)) = ow.into();
+                    (node, d)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<P, V> Node<P, V>
 where
-    T: Copy + Ord,
-    Point<T>: AxisValue<Value = T>,
+    P: AxisValue,
+    P::Value: Copy + Ord,
 {
-    fn new(pt: Point<T>, value: V, axis: Axis) -> Self {
+    fn new(pt: P, value: V, axis: Axis) -> Self {
         Node {
             median: pt,
             axis,
@@ -250,56 +510,208 @@ where
         }
     }
 
-    fn add(&mut self, point: Point<T>, value: V) -> Option<V> {
-        if point == self.median {
-            let old_value = ::std::mem::replace(&mut self.value, value);
-            return Some(old_value);
+    /// Build a balanced subtree of the given points that must be unique.
+    fn build(mut points: Vec<(P, V)>, axis: Axis) -> Option<Box<Self>> {
+        if points.is_empty() {
+            return None;
         }
 
-        let child = match self.cmp_to_point_value(point) {
-            Ordering::Less | Ordering::Equal => &mut self.left,
+        let mid = points.len() / 2;
+
+        // this is actually partitioning data at the median
+        points.select_nth_unstable_by(mid, |(p1, _), (p2, _)| cmp_points(p1, p2, axis));
+
+        let (left, elem, right) = split_element_at(points, mid);
+        let (median, value) = elem.unwrap();
+        let next_axis = axis.next(P::DIMENSIONS);
+
+        Some(Box::new(Node {
+            axis,
+            median,
+            value,
+            left: Node::build(left, next_axis),
+            right: Node::build(right, next_axis),
+        }))
+    }
+
+    fn depth(&self) -> usize {
+        let left = self.left.as_ref().map_or(0, |n| n.depth());
+        let right = self.right.as_ref().map_or(0, |n| n.depth());
+
+        1 + left.max(right)
+    }
+
+    fn add(&mut self, point: P, value: V) -> Option<V> {
+        let child = match self.cmp_to_point(&point) {
+            Ordering::Equal => {
+                let old_value = ::std::mem::replace(&mut self.value, value);
+                return Some(old_value);
+            }
+            Ordering::Less => &mut self.left,
             Ordering::Greater => &mut self.right,
         };
 
         if child.is_none() {
-            *child = Some(Box::new(Node::new(point, value, self.axis.next())));
+            *child = Some(Box::new(Node::new(
+                point,
+                value,
+                self.axis.next(P::DIMENSIONS),
+            )));
             return None;
         }
 
         child.as_mut().unwrap().add(point, value)
     }
 
+    /// Remove the point from the subtree of this node, this node excluded.
+    fn remove(&mut self, point: &P) -> Option<V> {
+        let child = match self.cmp_to_point(point) {
+            Ordering::Equal => return None,
+            Ordering::Less => &mut self.left,
+            Ordering::Greater => &mut self.right,
+        };
+
+        let child_node = child.as_mut()?;
+        if child_node.cmp_to_point(point) != Ordering::Equal {
+            return child_node.remove(point);
+        }
+
+        let child_node = child.take().unwrap();
+        let axis = child_node.axis;
+
+        let mut rest = vec![];
+        let (_, value) = child_node.into_entries(&mut rest);
+        *child = Node::build(rest, axis);
+
+        Some(value)
+    }
+
+    /// Move the points below this node in `entries` and return the point of
+    /// this node.
+    fn into_entries(self, entries: &mut Vec<(P, V)>) -> (P, V) {
+        let mut nodes = vec![];
+        nodes.extend(self.left);
+        nodes.extend(self.right);
+
+        while let Some(node) = nodes.pop() {
+            let node = *node;
+
+            nodes.extend(node.left);
+            nodes.extend(node.right);
+            entries.push((node.median, node.value));
+        }
+
+        (self.median, self.value)
+    }
+
     /// Return whether the given point lies before, in the same place or after
     /// this point.
-    fn cmp_to_point_value(&self, point: Point<T>) -> Ordering {
-        let cur_axis_value = self.median.axis_value(self.axis);
-        let point_axis_value = point.axis_value(self.axis);
+    fn cmp_to_point(&self, point: &P) -> Ordering {
+        cmp_points(point, &self.median, self.axis)
+    }
+}
 
-        point_axis_value.cmp(&cur_axis_value)
+/// Compare the two points by their value on the given axis and then by the
+/// ones on the following axes to break ties.
+fn cmp_points<P>(p1: &P, p2: &P, axis: Axis) -> Ordering
+where
+    P: AxisValue,
+    P::Value: Ord,
+{
+    (0..P::DIMENSIONS)
+        .map(|i| Axis((axis.0 + i) % P::DIMENSIONS))
+        .map(|a| p1.axis_value(a).cmp(p2.axis_value(a)))
+        .find(|o| *o != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+fn to_f64<T: num::ToPrimitive>(v: &T) -> f64 {
+    v.to_f64().unwrap()
+}
+
+/// Return the signed distance of `target` from `point` along the given axis.
+fn axis_delta<P>(target: &P, point: &P, axis: Axis) -> f64
+where
+    P: AxisValue,
+    P::Value: num::ToPrimitive,
+{
+    to_f64(target.axis_value(axis)) - to_f64(point.axis_value(axis))
+}
+
+fn deltas<'a, P>(point: &'a P, target: &'a P) -> impl Iterator<Item = f64> + 'a
+where
+    P: AxisValue,
+    P::Value: num::ToPrimitive,
+{
+    (0..P::DIMENSIONS).map(move |i| axis_delta(point, target, Axis(i)))
+}
+
+impl<P, V> Extend<(P, V)> for KdTree<P, V>
+where
+    P: AxisValue,
+    P::Value: Copy + Ord,
+{
+    /// Add all the given points at once and rebuild the tree so that it stays
+    /// balanced. Like `add` the new values replace the old ones.
+    fn extend<I: IntoIterator<Item = (P, V)>>(&mut self, iter: I) {
+        let mut entries = self.drain();
+        entries.extend(iter);
+
+        *self = Self::from_vector(entries);
+    }
+}
+
+impl<P, V> FromIterator<(P, V)> for KdTree<P, V>
+where
+    P: AxisValue,
+    P::Value: Copy + Ord,
+{
+    fn from_iter<I: IntoIterator<Item = (P, V)>>(iter: I) -> Self {
+        Self::from_vector(iter.into_iter().collect())
     }
 }
 
 impl Axis {
-    /// Return the next axis, going back to the beginning if necessary.
-    pub fn next(self) -> Self {
-        match self {
-            Axis::X => Axis::Y,
-            Axis::Y => Axis::X,
-        }
+    /// The x axis.
+    pub const X: Axis = Axis(0);
+
+    /// The y axis.
+    pub const Y: Axis = Axis(1);
+
+    /// The z axis.
+    pub const Z: Axis = Axis(2);
+
+    /// Return the next axis in a space with the given number of dimensions,
+    /// going back to the beginning if necessary.
+    pub fn next(self, dimensions: usize) -> Self {
+        Axis((self.0 + 1) % dimensions)
     }
 }
 
 impl<T> AxisValue for Point<T> {
     type Value = T;
 
+    const DIMENSIONS: usize = 2;
+
     fn axis_value(&self, axis: Axis) -> &Self::Value {
         match axis {
             Axis::X => &self.x,
             Axis::Y => &self.y,
+            _ => panic!("points have only 2 axes, got {:?}", axis),
         }
     }
 }
 
+impl<T, const N: usize> AxisValue for [T; N] {
+    type Value = T;
+
+    const DIMENSIONS: usize = N;
+
+    fn axis_value(&self, axis: Axis) -> &Self::Value {
+        &self[axis.0]
+    }
+}
+
 impl<T> Range<Point<T>> for BoundingBox<T>
 where
     T: num::Num + num::Bounded + From<u8> + Copy + PartialOrd,
@@ -311,20 +723,17 @@ where
     }
 
     fn axis_value_range(&self, axis: Axis) -> (Self::AxisValue, Self::AxisValue) {
-        match axis {
-            Axis::X => (self.min().x, self.max().x),
-            Axis::Y => (self.min().y, self.max().y),
-        }
+        (*self.min().axis_value(axis), *self.max().axis_value(axis))
     }
 }
 
 /// Iterator over all the elements of a KdTree.
-pub struct KdTreeIter<'a, T: 'a, V: 'a> {
-    nodes: Vec<&'a Node<T, V>>,
+pub struct KdTreeIter<'a, P: 'a, V: 'a> {
+    nodes: Vec<&'a Node<P, V>>,
 }
 
-impl<'a, T, V> Iterator for KdTreeIter<'a, T, V> {
-    type Item = (&'a Point<T>, &'a V);
+impl<'a, P, V> Iterator for KdTreeIter<'a, P, V> {
+    type Item = (&'a P, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
         self.nodes.pop().map(|node| {
@@ -342,24 +751,25 @@ impl<'a, T, V> Iterator for KdTreeIter<'a, T, V> {
 }
 
 /// Iterator over the points contained in the given range in the kdtree.
-pub struct InRangeIter<'a, 'r, T: 'a, V: 'a, R: 'r> {
-    nodes: Vec<&'a Node<T, V>>,
+pub struct InRangeIter<'a, 'r, P: 'a, V: 'a, R: 'r> {
+    nodes: Vec<&'a Node<P, V>>,
     range: &'r R,
 }
 
-impl<'a, 'r, T, V, R> Iterator for InRangeIter<'a, 'r, T, V, R>
+impl<'a, 'r, P, V, R> Iterator for InRangeIter<'a, 'r, P, V, R>
 where
-    T: Copy + Ord,
-    R: Range<Point<T>, AxisValue = T>,
+    P: AxisValue,
+    P::Value: Copy + Ord,
+    R: Range<P, AxisValue = P::Value>,
 {
-    type Item = (&'a Point<T>, &'a V);
+    type Item = (&'a P, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
         while let Some(node) = self.nodes.pop() {
             let (range_low, range_high) = self.range.axis_value_range(node.axis);
             let median_axis_value = node.median.axis_value(node.axis);
 
-            let mut push_node = |node: &'a Option<Box<Node<T, V>>>| {
+            let mut push_node = |node: &'a Option<Box<Node<P, V>>>| {
                 if let Some(ref n) = node {
                     self.nodes.push(n);
                 }
@@ -391,9 +801,9 @@ where
 mod test {
     use super::{Axis, KdTree, Node};
 
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
 
-    use geo::{BoundingBox, PointU32};
+    use crate::{BoundingBox, Metric, PointU32, Weighting};
 
     // arbitrary sorting just to ensure the order is the same in both arrays
     fn sort_points(pts: &mut [(&PointU32, &())]) {
@@ -627,4 +1037,330 @@ mod test {
             tree
         )
     }
+
+    #[test]
+    fn test_nearest_neighbors_by() {
+        let kdtree = KdTree::from_vector(vec![
+            (PointU32::new(0, 0), "origin"),
+            (PointU32::new(3, 3), "diagonal"),
+            (PointU32::new(5, 0), "straight"),
+        ]);
+
+        // (3, 3) is closer in a straight line, (5, 0) walking the streets
+        let closest = |metric| kdtree.nearest_neighbors_by(PointU32::new(5, 5), 1, metric)[0];
+        assert_eq!(closest(Metric::Euclidean).1, &"diagonal");
+        assert_eq!(
+            closest(Metric::Manhattan),
+            (&PointU32::new(3, 3), &"diagonal", 4.0)
+        );
+        assert_eq!(closest(Metric::Chebyshev).2, 2.0);
+
+        let found = kdtree.nearest_neighbors_by(PointU32::new(5, 1), 5, Metric::Manhattan);
+        assert_eq!(
+            found.iter().map(|(_, _, d)| *d).collect::<Vec<_>>(),
+            vec![1.0, 4.0, 6.0]
+        );
+        assert!(kdtree
+            .nearest_neighbors_by(PointU32::new(5, 1), 0, Metric::Manhattan)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_weighted_nearest_neighbors() {
+        let kdtree = KdTree::from_vector(vec![
+            (PointU32::new(0, 0), 10.0),
+            (PointU32::new(10, 0), 1.0),
+        ]);
+        let query = PointU32::new(7, 0);
+
+        let found = kdtree.weighted_nearest_neighbors(
+            query,
+            1,
+            Metric::Euclidean,
+            Weighting::Additive,
+            |w| *w,
+            10.0,
+        );
+        assert_eq!(found, vec![(&PointU32::new(0, 0), &10.0, -3.0)]);
+
+        let found = kdtree.weighted_nearest_neighbors(
+            query,
+            2,
+            Metric::Euclidean,
+            Weighting::Multiplicative,
+            |w| *w,
+            10.0,
+        );
+        assert_eq!(
+            found,
+            vec![
+                (&PointU32::new(0, 0), &10.0, 0.7),
+                (&PointU32::new(10, 0), &1.0, 3.0)
+            ]
+        );
+    }
+
+    proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(200))]
+        #[test]
+        fn prop_kdtree_nearest_neighbors_by_same_as_loop(
+            points in proptest::collection::hash_map((0_u32..255, 0_u32..255), 1_u32..20, 1..100),
+            to_search in (0_u32..255, 0_u32..255),
+            k in 1_usize..6,
+        ) {
+            let points = points
+                .into_iter()
+                .map(|((x, y), w)| (PointU32::new(x, y), f64::from(w)))
+                .collect::<Vec<_>>();
+            let tree = KdTree::from_vector(points.clone());
+            let to_search = PointU32::new(to_search.0, to_search.1);
+
+            let metrics = [
+                Metric::Euclidean,
+                Metric::SquaredEuclidean,
+                Metric::Manhattan,
+                Metric::Chebyshev,
+                Metric::Minkowski(3.0),
+            ];
+
+            for metric in &metrics {
+                let distance = |pt: &PointU32| {
+                    metric.distance([
+                        f64::from(pt.x) - f64::from(to_search.x),
+                        f64::from(pt.y) - f64::from(to_search.y),
+                    ])
+                };
+
+                let found = tree.nearest_neighbors_by(to_search, k, *metric);
+                let found = found.iter().map(|(_, _, d)| *d).collect::<Vec<_>>();
+                assert_eq!(found, k_smallest(points.iter().map(|(pt, _)| distance(pt)), k));
+
+                for weighting in &[Weighting::Additive, Weighting::Multiplicative] {
+                    let found = tree.weighted_nearest_neighbors(
+                        to_search,
+                        k,
+                        *metric,
+                        *weighting,
+                        |w| *w,
+                        20.0,
+                    );
+                    let found = found.iter().map(|(_, _, d)| *d).collect::<Vec<_>>();
+                    let brute_force = points
+                        .iter()
+                        .map(|(pt, w)| weighting.apply(distance(pt), *w));
+
+                    assert_eq!(found, k_smallest(brute_force, k));
+                }
+            }
+        }
+    }
+
+    fn k_smallest(distances: impl Iterator<Item = f64>, k: usize) -> Vec<f64> {
+        let mut distances = distances.collect::<Vec<_>>();
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        distances.truncate(k);
+
+        distances
+    }
+
+    #[test]
+    fn test_remove_and_get_mut() {
+        let mut kdtree = KdTree::from_vector(vec![
+            (PointU32::new(4, 5), "root"),
+            (PointU32::new(1, 2), "p(1,2)"),
+            (PointU32::new(7, 8), "p(7,8)"),
+            (PointU32::new(5, 2), "p(5,2)"),
+        ]);
+
+        *kdtree.get_mut(&PointU32::new(5, 2)).unwrap() = "changed";
+        assert_eq!(kdtree.get(&PointU32::new(5, 2)), Some(&"changed"));
+        assert_eq!(kdtree.get_mut(&PointU32::new(5, 3)), None);
+
+        assert_eq!(kdtree.remove(&PointU32::new(4, 5)), Some("root"));
+        assert_eq!(kdtree.remove(&PointU32::new(4, 5)), None);
+        assert_eq!(kdtree.remove(&PointU32::new(7, 8)), Some("p(7,8)"));
+        assert_eq!(kdtree.len(), 2);
+        assert_eq!(kdtree.get(&PointU32::new(5, 2)), Some(&"changed"));
+        assert_eq!(
+            kdtree.nearest_neighbor(PointU32::new(6, 6)),
+            Some((&PointU32::new(5, 2), &"changed"))
+        );
+
+        assert_eq!(kdtree.remove(&PointU32::new(1, 2)), Some("p(1,2)"));
+        assert_eq!(kdtree.remove(&PointU32::new(5, 2)), Some("changed"));
+        assert!(kdtree.is_empty());
+        assert_eq!(kdtree.depth(), 0);
+    }
+
+    #[test]
+    fn test_rebalance_and_extend() {
+        // adding sorted points one by one degenerates into a list
+        let mut kdtree = KdTree::new();
+        for i in 0..64 {
+            kdtree.add(PointU32::new(i, i), i);
+        }
+        assert_eq!(kdtree.depth(), 64);
+
+        kdtree.rebalance();
+        assert_eq!(kdtree.len(), 64);
+        assert_eq!(kdtree.depth(), 7);
+
+        kdtree.extend((60..128).map(|i| (PointU32::new(i, i), i * 2)));
+        assert_eq!(kdtree.len(), 128);
+        assert_eq!(kdtree.depth(), 8);
+        assert_eq!(kdtree.get(&PointU32::new(10, 10)), Some(&10));
+        assert_eq!(kdtree.get(&PointU32::new(60, 60)), Some(&120));
+
+        let kdtree = vec![(PointU32::new(1, 1), 'a'), (PointU32::new(1, 1), 'b')]
+            .into_iter()
+            .collect::<KdTree<_, _>>();
+        assert_eq!(kdtree.len(), 1);
+        assert_eq!(kdtree.get(&PointU32::new(1, 1)), Some(&'b'));
+    }
+
+    #[test]
+    fn test_colors() {
+        let palette = KdTree::from_vector(vec![
+            ([0_u8, 0, 0], "black"),
+            ([255, 255, 255], "white"),
+            ([255, 0, 0], "red"),
+            ([0, 0, 255], "blue"),
+        ]);
+
+        let closest = |c| palette.nearest_neighbor(c).unwrap().1;
+        assert_eq!(closest([200, 30, 30]), &"red");
+        assert_eq!(closest([200, 200, 180]), &"white");
+        assert_eq!(closest([20, 20, 100]), &"black");
+        assert_eq!(closest([20, 20, 150]), &"blue");
+
+        let mut near_black = palette
+            .radius_search([10, 10, 10], 255.0)
+            .into_iter()
+            .map(|(_, v, _)| *v)
+            .collect::<Vec<_>>();
+        near_black.sort();
+        assert_eq!(near_black, vec!["black", "blue", "red"]);
+    }
+
+    proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(200))]
+        #[test]
+        fn prop_kdtree_add_remove_same_as_hashmap(
+            ops in proptest::collection::vec((proptest::bool::ANY, 0_u8..8, 0_u8..8, 0_u8..8), 0..200),
+        ) {
+            let mut kdtree = KdTree::new();
+            let mut model = HashMap::new();
+
+            for (i, (add, r, g, b)) in ops.into_iter().enumerate() {
+                let color = [r, g, b];
+
+                if add {
+                    assert_eq!(kdtree.add(color, i), model.insert(color, i));
+                } else {
+                    assert_eq!(kdtree.remove(&color), model.remove(&color));
+                }
+
+                assert_eq!(kdtree.len(), model.len());
+            }
+
+            for (color, v) in &model {
+                assert_eq!(kdtree.get(color), Some(v));
+            }
+
+            kdtree.rebalance();
+            assert_eq!(kdtree.len(), model.len());
+            assert!(kdtree.depth() <= (model.len() as f64 + 1.0).log2().ceil() as usize);
+
+            let mut entries = kdtree.iter().map(|(c, v)| (*c, *v)).collect::<Vec<_>>();
+            let mut model = model.into_iter().collect::<Vec<_>>();
+            entries.sort();
+            model.sort();
+            assert_eq!(entries, model);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(200))]
+        #[test]
+        fn prop_kdtree_radius_search_same_as_loop(
+            points in proptest::collection::hash_set((0_u8..255, 0_u8..255, 0_u8..255), 0..100),
+            to_search in (0_u8..255, 0_u8..255, 0_u8..255),
+            radius in 0.0..200.0,
+        ) {
+            let points = points
+                .into_iter()
+                .map(|(r, g, b)| ([r, g, b], ()))
+                .collect::<Vec<_>>();
+            let tree = KdTree::from_vector(points.clone());
+            let to_search = [to_search.0, to_search.1, to_search.2];
+
+            for metric in &[Metric::Euclidean, Metric::Manhattan, Metric::Chebyshev] {
+                let distance = |c: &[u8; 3]| {
+                    metric.distance(c.iter().zip(&to_search).map(|(a, b)| f64::from(*a) - f64::from(*b)))
+                };
+
+                let mut found = tree
+                    .radius_search_by(to_search, radius, *metric)
+                    .into_iter()
+                    .map(|(c, _, d)| (*c, d))
+                    .collect::<Vec<_>>();
+                found.sort_by_key(|(c, _)| *c);
+
+                let mut brute_force = points
+                    .iter()
+                    .map(|(c, _)| (*c, distance(c)))
+                    .filter(|(_, d)| *d <= radius)
+                    .collect::<Vec<_>>();
+                brute_force.sort_by_key(|(c, _)| *c);
+
+                assert_eq!(found, brute_force);
+            }
+        }
+    }
+
+    proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(300))]
+        #[test]
+        fn prop_kdtree_k_nearest_neighbors_same_as_loop(
+            points in proptest::collection::hash_set((0_u32..255, 0_u32..255), 0..100),
+            to_search in (0_u32..255, 0_u32..255),
+            k in 0_usize..10,
+            epsilon in 0.0..2.0,
+        ) {
+            let points = points
+                .into_iter()
+                .map(|(x, y)| (PointU32::new(x, y), ()))
+                .collect::<Vec<_>>();
+            let tree = KdTree::from_vector(points.clone());
+            let to_search = PointU32::new(to_search.0, to_search.1);
+
+            let brute_force = k_smallest(
+                points.iter().map(|(pt, _)| pt.squared_dist::<f64>(&to_search)),
+                k,
+            );
+
+            let found = tree
+                .nearest_neighbors(to_search, k)
+                .into_iter()
+                .map(|(pt, _)| pt.squared_dist::<f64>(&to_search))
+                .collect::<Vec<_>>();
+            assert_eq!(found, brute_force);
+
+            for metric in &[Metric::Euclidean, Metric::SquaredEuclidean] {
+                let exact = tree.approximate_nearest_neighbors(to_search, k, *metric, 0.0);
+                let exact = exact.iter().map(|(_, _, d)| *d).collect::<Vec<_>>();
+                let approx = tree.approximate_nearest_neighbors(to_search, k, *metric, epsilon);
+
+                assert_eq!(approx.len(), exact.len());
+
+                let factor = match metric {
+                    Metric::SquaredEuclidean => (1.0 + epsilon) * (1.0 + epsilon),
+                    _ => 1.0 + epsilon,
+                };
+                for ((_, _, d), e) in approx.iter().zip(&exact) {
+                    assert!(*d <= e * factor + 1e-9);
+                }
+            }
+        }
+    }
 }
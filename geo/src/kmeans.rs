@@ -33,7 +33,7 @@ where
         return points.into_iter().map(|p| (p, vec![p])).collect();
     }
 
-    let mut clusters = iter::repeat(vec![]).take(k).collect::<Vec<_>>();
+    let mut clusters = iter::repeat_n(vec![], k).collect::<Vec<_>>();
 
     // don't want to pickup random values, the caller can always shuffle the
     // array to achieve the same effect.
@@ -65,7 +65,7 @@ where
 
     pivots
         .into_iter()
-        .zip(clusters.into_iter())
+        .zip(clusters)
         .filter(|(_, c)| !c.is_empty())
         .collect()
 }
@@ -128,7 +128,7 @@ mod tests {
 
     use proptest::prelude::*;
 
-    use geo::Point;
+    use crate::Point;
 
     type PointK = Point<i32>;
 
@@ -155,7 +155,7 @@ mod tests {
     }
 
     fn _prop_kmeans_clusters_contains_closest_point(points: Vec<PointK>, k: usize) {
-        let clusters = kmeans(points.clone(), k, usize::max_value());
+        let clusters = kmeans(points.clone(), k, usize::MAX);
         assert!(clusters.len() <= k);
 
         for (pivot, cluster) in &clusters {
@@ -1,20 +1,37 @@
 //! Geometric functions, algorithms and data structures.
 
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
+
 pub mod angle;
 pub mod bbox;
+pub mod boolean;
 pub mod convex_hull;
 pub mod delaunay;
+pub mod grid;
 pub mod kdtree;
 pub mod kmeans;
 pub mod line;
+pub mod metric;
+pub mod offset;
 pub mod point;
 pub mod polygon;
+pub mod predicates;
+pub mod quadtree;
+pub mod segment;
+pub mod simplify;
+pub mod transform;
 pub mod triangle;
 pub mod utils;
+pub mod voronoi;
 
 pub use self::angle::{angle_orientation, polar_angle, AngleOrientation};
 pub use self::bbox::BoundingBox;
 pub use self::line::LineEquation;
+pub use self::metric::{Metric, Weighting};
 pub use self::point::{Point, PointF64, PointI32, PointU32};
 pub use self::polygon::Polygon;
+pub use self::segment::Segment;
+pub use self::transform::{Transform, Transformable};
 pub use self::triangle::Triangle;
@@ -169,7 +169,7 @@ where
 #[cfg(test)]
 mod test {
     use super::LineEquation;
-    use geo::PointI32;
+    use crate::PointI32;
 
     #[test]
     fn test_line_between() {
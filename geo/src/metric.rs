@@ -0,0 +1,162 @@
+//! Distance metrics that can be used to find the nearest neighbors of a point
+//! and how the distances can be weighted.
+
+use std::str::FromStr;
+
+/// How the distance between two points is measured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    /// The usual straight line distance.
+    Euclidean,
+
+    /// The square of the Euclidean distance. It's cheaper than `Euclidean` and
+    /// additively weighted it gives power diagrams.
+    SquaredEuclidean,
+
+    /// The sum of the absolute differences of the coordinates, aka taxicab
+    /// distance.
+    Manhattan,
+
+    /// The biggest absolute difference among the coordinates, aka chessboard
+    /// distance.
+    Chebyshev,
+
+    /// The p-th root of the sum of the absolute differences of the coordinates
+    /// raised to p. p = 1 is `Manhattan`, p = 2 is `Euclidean` and p →∞ is
+    /// `Chebyshev`.
+    Minkowski(f64),
+}
+
+/// How the weight of a site changes the distance from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weighting {
+    /// The weight is subtracted from the distance.
+    Additive,
+
+    /// The distance is divided by the weight that must be positive.
+    Multiplicative,
+}
+
+impl Metric {
+    /// Return the distance between two points whose coordinates differ by the
+    /// given deltas.
+    pub fn distance(self, deltas: impl IntoIterator<Item = f64>) -> f64 {
+        let deltas = deltas.into_iter();
+
+        match self {
+            Metric::Euclidean => Metric::SquaredEuclidean.distance(deltas).sqrt(),
+            Metric::SquaredEuclidean => deltas.map(|d| d * d).sum(),
+            Metric::Manhattan => deltas.map(f64::abs).sum(),
+            Metric::Chebyshev => deltas.fold(0.0, |m, d| d.abs().max(m)),
+            Metric::Minkowski(p) => deltas.map(|d| d.abs().powf(p)).sum::<f64>().powf(1.0 / p),
+        }
+    }
+
+    /// Return the distance between two points that differ by `delta` only
+    /// along a single axis. It's never bigger than the distance between two
+    /// points whose coordinates differ by `delta` along that axis.
+    pub fn axis_distance(self, delta: f64) -> f64 {
+        match self {
+            Metric::SquaredEuclidean => delta * delta,
+            _ => delta.abs(),
+        }
+    }
+}
+
+impl Weighting {
+    /// Return the distance from a site with the given weight.
+    pub fn apply(self, distance: f64, weight: f64) -> f64 {
+        match self {
+            Weighting::Additive => distance - weight,
+            Weighting::Multiplicative => distance / weight,
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "euclidean" => Ok(Metric::Euclidean),
+            "squared-euclidean" => Ok(Metric::SquaredEuclidean),
+            "manhattan" => Ok(Metric::Manhattan),
+            "chebyshev" => Ok(Metric::Chebyshev),
+            _ => match s.split_at(s.find(':').unwrap_or(0)) {
+                ("minkowski", p) => match p[1..].parse::<f64>() {
+                    Ok(p) if p > 0.0 => Ok(Metric::Minkowski(p)),
+                    _ => Err(format!(
+                        "invalid minkowski exponent {}, please use a positive number",
+                        &p[1..]
+                    )),
+                },
+                _ => Err(format!(
+                    "unknown metric {}, please use euclidean, squared-euclidean, manhattan, chebyshev or minkowski:<p>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl FromStr for Weighting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "additive" => Ok(Weighting::Additive),
+            "multiplicative" => Ok(Weighting::Multiplicative),
+            _ => Err(format!(
+                "unknown weighting {}, please use additive or multiplicative",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        let deltas = [3.0, -4.0];
+
+        assert_eq!(Metric::Euclidean.distance(deltas), 5.0);
+        assert_eq!(Metric::SquaredEuclidean.distance(deltas), 25.0);
+        assert_eq!(Metric::Manhattan.distance(deltas), 7.0);
+        assert_eq!(Metric::Chebyshev.distance(deltas), 4.0);
+        assert_eq!(Metric::Minkowski(1.0).distance(deltas), 7.0);
+        assert!((Metric::Minkowski(2.0).distance(deltas) - 5.0).abs() < 1e-12);
+        assert!((Metric::Minkowski(3.0).distance(deltas) - 91.0_f64.cbrt()).abs() < 1e-12);
+
+        for metric in &[
+            Metric::Euclidean,
+            Metric::SquaredEuclidean,
+            Metric::Manhattan,
+            Metric::Chebyshev,
+            Metric::Minkowski(0.5),
+        ] {
+            assert!(metric.axis_distance(-4.0) <= metric.distance(deltas));
+        }
+    }
+
+    #[test]
+    fn test_weighting() {
+        assert_eq!(Weighting::Additive.apply(5.0, 2.0), 3.0);
+        assert_eq!(Weighting::Multiplicative.apply(5.0, 2.0), 2.5);
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("manhattan".parse(), Ok(Metric::Manhattan));
+        assert_eq!("minkowski:3".parse(), Ok(Metric::Minkowski(3.0)));
+        assert!("minkowski:0".parse::<Metric>().is_err());
+        assert!("minkowski:".parse::<Metric>().is_err());
+        assert!("minkowski".parse::<Metric>().is_err());
+        assert!("hamming".parse::<Metric>().is_err());
+
+        assert_eq!("additive".parse(), Ok(Weighting::Additive));
+        assert!("power".parse::<Weighting>().is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Inward and outward offsetting of polygons.
+//!
+//! Every edge is moved along its normal by the offset distance and consecutive
+//! edges are joined with a miter, or with a bevel when the miter would be too
+//! long. Shrinking a polygon, or growing a concave one, makes the raw offset
+//! ring intersect itself, therefore only the boundary of the area where its
+//! winding number is positive is kept like in [Polygon offsetting by computing
+//! winding numbers](https://mcmains.me.berkeley.edu/pubs/DAC05OffsetPolygon.pdf).
+
+use crate::angle::AngleOrientation;
+use crate::boolean::{ccw_edges, chain, split_at_intersections, winding_number};
+use crate::point::PointF64;
+use crate::polygon::Polygon;
+use crate::segment::Segment;
+
+/// The maximum distance of a miter from its vertex in terms of the offset
+/// distance, longer miters are replaced by a bevel.
+pub const MITER_LIMIT: f64 = 2.0;
+
+/// Offset the polygon by the given distance, positive distances grow the
+/// polygon while negative ones shrink it. Shrinking can split the polygon in
+/// many parts or make it disappear completely. The offset polygons are counter
+/// clockwise.
+pub fn offset(polygon: &Polygon<f64>, distance: f64) -> Vec<Polygon<f64>> {
+    let edges = ccw_edges(polygon);
+    if edges.is_empty() {
+        return vec![];
+    }
+
+    if distance == 0.0 {
+        return match polygon.orientation() {
+            AngleOrientation::Clockwise => vec![polygon.reversed()],
+            _ => vec![polygon.clone()],
+        };
+    }
+
+    let raw = raw_offset(&edges, distance);
+    let raw_edges = raw
+        .iter()
+        .zip(raw.iter().cycle().skip(1))
+        .map(|(a, b)| Segment::new(*a, *b))
+        .filter(|s| !s.is_degenerate())
+        .collect::<Vec<_>>();
+
+    let bbox = polygon.bounding_box();
+    let scale = bbox.min().dist::<f64>(bbox.max()) + distance.abs();
+    let delta = scale * 1e-9;
+
+    let boundary = split_at_intersections(&raw_edges)
+        .into_iter()
+        .flatten()
+        .filter(|piece| {
+            let (dx, dy) = (piece.end.x - piece.start.x, piece.end.y - piece.start.y);
+            let len = (dx * dx + dy * dy).sqrt();
+            let mid = piece.start.midpoint(&piece.end);
+
+            let left = PointF64::new(mid.x - dy / len * delta, mid.y + dx / len * delta);
+            let right = PointF64::new(mid.x + dy / len * delta, mid.y - dx / len * delta);
+
+            winding_number(&raw_edges, &left) > 0 && winding_number(&raw_edges, &right) <= 0
+        })
+        .collect::<Vec<_>>();
+
+    chain(&boundary)
+}
+
+/// Return the vertices of the offset ring before removing its loops.
+fn raw_offset(edges: &[Segment], distance: f64) -> Vec<PointF64> {
+    // the outward normals of the counter clockwise edges
+    let normals = edges
+        .iter()
+        .map(|e| {
+            let (dx, dy) = (e.end.x - e.start.x, e.end.y - e.start.y);
+            let len = (dx * dx + dy * dy).sqrt();
+
+            (dy / len, -dx / len)
+        })
+        .collect::<Vec<_>>();
+
+    let mut ring = vec![];
+    for (i, edge) in edges.iter().enumerate() {
+        let v = edge.start;
+        let (px, py) = normals[(i + normals.len() - 1) % normals.len()];
+        let (nx, ny) = normals[i];
+
+        let before = PointF64::new(v.x + px * distance, v.y + py * distance);
+        let after = PointF64::new(v.x + nx * distance, v.y + ny * distance);
+
+        // the offset edges overlap at the vertices that are concave in the
+        // direction of the offset, the loop around the vertex is removed later
+        let cross = px * ny - py * nx;
+        if cross * distance < 0.0 {
+            ring.push(before);
+            ring.push(v);
+            ring.push(after);
+            continue;
+        }
+
+        let cos = px * nx + py * ny;
+        if 1.0 + cos > 1e-12 && (2.0 / (1.0 + cos)).sqrt() <= MITER_LIMIT {
+            let k = distance / (1.0 + cos);
+            ring.push(PointF64::new(v.x + (px + nx) * k, v.y + (py + ny) * k));
+        } else {
+            ring.push(before);
+            ring.push(after);
+        }
+    }
+
+    ring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn square(side: f64) -> Polygon<f64> {
+        Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(side, 0.0),
+            PointF64::new(side, side),
+            PointF64::new(0.0, side),
+        ])
+        .unwrap()
+    }
+
+    fn area(polygons: &[Polygon<f64>]) -> f64 {
+        polygons.iter().map(|p| p.signed_area()).sum()
+    }
+
+    #[test]
+    fn test_offset_square() {
+        let sq = square(4.0);
+
+        let grown = offset(&sq, 1.0);
+        assert_eq!(grown.len(), 1);
+        assert_eq!(area(&grown), 36.0);
+
+        let shrunk = offset(&sq, -1.0);
+        assert_eq!(shrunk.len(), 1);
+        assert_eq!(area(&shrunk), 4.0);
+        assert_eq!(shrunk[0].bounding_box().min(), &PointF64::new(1.0, 1.0));
+        assert_eq!(shrunk[0].bounding_box().max(), &PointF64::new(3.0, 3.0));
+
+        assert!(offset(&sq, -2.5).is_empty());
+        assert_eq!(offset(&sq.reversed(), -1.0).len(), 1);
+        assert_eq!(offset(&sq.reversed(), 0.0), vec![sq]);
+    }
+
+    #[test]
+    fn test_offset_bevel() {
+        // the sharp corner of the triangle would have a very long miter
+        let triangle = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(10.0, 0.0),
+            PointF64::new(0.0, 1.0),
+        ])
+        .unwrap();
+
+        let grown = offset(&triangle, 1.0);
+        assert_eq!(grown.len(), 1);
+        assert!(grown[0].bounding_box().max().x < 10.0 + MITER_LIMIT);
+    }
+
+    #[test]
+    fn test_offset_splits() {
+        // two squares joined by a thin bridge split when shrunk
+        let dumbbell = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(4.0, 1.75),
+            PointF64::new(6.0, 1.75),
+            PointF64::new(6.0, 0.0),
+            PointF64::new(10.0, 0.0),
+            PointF64::new(10.0, 4.0),
+            PointF64::new(6.0, 4.0),
+            PointF64::new(6.0, 2.25),
+            PointF64::new(4.0, 2.25),
+            PointF64::new(4.0, 4.0),
+            PointF64::new(0.0, 4.0),
+        ])
+        .unwrap();
+
+        let shrunk = offset(&dumbbell, -1.0);
+        assert_eq!(shrunk.len(), 2);
+        assert_eq!(area(&shrunk), 8.0);
+
+        // the concave corners are filled when growing
+        let grown = offset(&dumbbell, 1.0);
+        assert_eq!(grown.len(), 1);
+        assert_eq!(grown[0].bounding_box().min(), &PointF64::new(-1.0, -1.0));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(300))]
+        #[test]
+        fn prop_offset_distance(
+            pts in prop::collection::vec((0_u8..16, 1_u8..8), 3..12),
+            distance in prop_oneof![-3.0..-0.01, 0.01..3.0]
+        ) {
+            // sorting the vertices by angle around a center builds a star
+            // shaped polygon, that can still be not simple due to rounding
+            let mut pts = pts;
+            pts.sort_by_key(|(a, _)| *a);
+            pts.dedup_by_key(|(a, _)| *a);
+
+            let polygon = Polygon::new(pts.into_iter().map(|(a, r)| {
+                let a = f64::from(a) * std::f64::consts::PI / 8.0;
+                PointF64::new((a.cos() * f64::from(r)).round(), (a.sin() * f64::from(r)).round())
+            }));
+            let polygon = match polygon {
+                Some(p) if p.area() > 0.0 && p.is_simple() => p,
+                _ => return Ok(()),
+            };
+
+            let offset = offset(&polygon, distance);
+            if distance > 0.0 {
+                prop_assert_eq!(offset.len(), 1);
+                prop_assert!(area(&offset) > polygon.area());
+            } else {
+                prop_assert!(area(&offset) < polygon.area() + 1e-9);
+            }
+
+            // every vertex of the offset polygons is on the right side of the
+            // boundary, but not farther than a miter, bevels can cut closer
+            // than the offset distance though
+            let edges = polygon.segments().collect::<Vec<_>>();
+            for pt in offset.iter().flat_map(|p| p.points()) {
+                let d = edges.iter().map(|e| e.dist(pt)).fold(f64::INFINITY, f64::min);
+                prop_assert!(d > 0.0 && d < distance.abs() * MITER_LIMIT + 1e-6, "{:?} {}", pt, d);
+                prop_assert_eq!(winding_number(&edges, pt) != 0, distance < 0.0);
+            }
+
+            if distance > 0.0 {
+                let grown = offset[0].segments().collect::<Vec<_>>();
+                for pt in polygon.points() {
+                    prop_assert!(winding_number(&grown, pt) != 0);
+                }
+            }
+        }
+    }
+}
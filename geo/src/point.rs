@@ -14,6 +14,7 @@ pub type PointU32 = Point<u32>;
 
 /// Simple 2d Point struct
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point<T> {
     /// x coordinate
     pub x: T,
@@ -129,7 +130,7 @@ where
         let points: Result<Vec<T>, T::Err> = s.trim().split(',').map(|p| p.parse()).collect();
 
         match points {
-            Err(e) => Err("bad coord number format, ".to_string() + e.description()),
+            Err(e) => Err(format!("bad coord number format, {}", e)),
             Ok(points) => {
                 if points.len() != 2 {
                     Err("wrong number of coords, please pass x and y coords as floats separated by ','".to_string())
@@ -209,7 +210,7 @@ mod test {
 
         // vertical
         assert_eq!(
-            PointU32::new(7_, 0).slope::<i64>(&PointU32::new(7, 53)),
+            PointU32::new(7, 0).slope::<i64>(&PointU32::new(7, 53)),
             None
         );
         assert_eq!(
@@ -2,9 +2,15 @@
 
 use std::cmp::Ordering;
 
+use crate::angle::AngleOrientation;
 use crate::bbox::BoundingBox;
+use crate::boolean::{boolean, Operation};
 use crate::line::LineEquation;
+use crate::offset;
 use crate::point::Point;
+use crate::predicates::orient2d;
+use crate::segment::{self, Intersection, Segment};
+use crate::simplify;
 use crate::utils::cmp_floats;
 
 /// A simple closed Polygon primitive.
@@ -119,6 +125,186 @@ where
     }
 }
 
+impl Polygon<f64> {
+    /// Return the signed area of the polygon computed with the [shoelace
+    /// formula](https://en.wikipedia.org/wiki/Shoelace_formula). Like
+    /// `Triangle::signed_area` it's negative if the vertices are in clockwise
+    /// order and positive otherwise.
+    pub fn signed_area(&self) -> f64 {
+        self.edges()
+            .map(|(a, b)| a.x * b.y - b.x * a.y)
+            .sum::<f64>()
+            / 2.0
+    }
+
+    /// Return the area of the polygon.
+    pub fn area(&self) -> f64 {
+        self.signed_area().abs()
+    }
+
+    /// Return the length of the boundary of the polygon.
+    pub fn perimeter(&self) -> f64 {
+        self.edges().map(|(a, b)| a.dist::<f64>(b)).sum()
+    }
+
+    /// Return the centroid of the area of the polygon, None if it has no
+    /// area.
+    pub fn centroid(&self) -> Option<Point<f64>> {
+        let (mut area, mut cx, mut cy) = (0.0, 0.0, 0.0);
+
+        for (a, b) in self.edges() {
+            let cross = a.x * b.y - b.x * a.y;
+
+            area += cross;
+            cx += (a.x + b.x) * cross;
+            cy += (a.y + b.y) * cross;
+        }
+
+        if area == 0.0 {
+            return None;
+        }
+
+        Some(Point::new(cx / (3.0 * area), cy / (3.0 * area)))
+    }
+
+    /// Return the winding order of the vertices, `Colinear` if the polygon has
+    /// no area.
+    pub fn orientation(&self) -> AngleOrientation {
+        let area = self.signed_area();
+
+        if area > 0.0 {
+            AngleOrientation::CounterClockwise
+        } else if area < 0.0 {
+            AngleOrientation::Clockwise
+        } else {
+            AngleOrientation::Colinear
+        }
+    }
+
+    /// Return a copy of the polygon with the vertices in the opposite order.
+    pub fn reversed(&self) -> Self {
+        Polygon {
+            points: self.points.iter().rev().copied().collect(),
+            bbox: self.bbox.clone(),
+        }
+    }
+
+    /// Return whether the polygon is convex. Collinear vertices are allowed,
+    /// but the boundary must turn around exactly once.
+    pub fn is_convex(&self) -> bool {
+        // the last point is the same as the first one
+        let n = self.points.len() - 1;
+        let vertex = |i: usize| &self.points[i % n];
+
+        let mut sign = 0.0;
+        let mut turning = 0.0;
+
+        for i in 0..n {
+            let (a, b, c) = (vertex(i), vertex(i + 1), vertex(i + 2));
+
+            let o = orient2d(a, b, c);
+            if o != 0.0 {
+                if o * sign < 0.0 {
+                    return false;
+                }
+                sign = o;
+            }
+
+            let (dx0, dy0) = (b.x - a.x, b.y - a.y);
+            let (dx1, dy1) = (c.x - b.x, c.y - b.y);
+            turning += (dx0 * dy1 - dy0 * dx1).atan2(dx0 * dx1 + dy0 * dy1);
+        }
+
+        sign != 0.0 && (turning.abs() - 2.0 * std::f64::consts::PI).abs() < 1e-6
+    }
+
+    /// Return the polygons obtained by moving the boundary of this polygon by
+    /// the given distance, outward if positive and inward if negative. See
+    /// `offset::offset` for the details.
+    pub fn offset(&self, distance: f64) -> Vec<Self> {
+        offset::offset(self, distance)
+    }
+
+    /// Simplify the boundary of the polygon with the Ramer–Douglas–Peucker
+    /// algorithm, None if the simplified polygon degenerates to a line.
+    pub fn simplify_rdp(&self, epsilon: f64) -> Option<Self> {
+        // split the ring at the vertex farthest from the first one so that both
+        // halves are open polylines with fixed endpoints
+        let (_, first) = self.points.split_last()?;
+        let far = (0..first.len())
+            .max_by(|i, j| {
+                let di = first[0].squared_dist::<f64>(&first[*i]);
+                let dj = first[0].squared_dist::<f64>(&first[*j]);
+                di.total_cmp(&dj)
+            })
+            .unwrap_or(0);
+
+        let mut simplified = simplify::rdp(&self.points[..=far], epsilon);
+        simplified.pop();
+        simplified.extend(simplify::rdp(&self.points[far..], epsilon));
+
+        // a triangle is 3 vertices plus the closing one
+        if simplified.len() < 4 {
+            return None;
+        }
+
+        Polygon::new(simplified)
+    }
+
+    /// Simplify the boundary of the polygon with the Visvalingam–Whyatt
+    /// algorithm, None if the simplified polygon degenerates to a line.
+    pub fn simplify_visvalingam(&self, min_area: f64) -> Option<Self> {
+        Polygon::new(simplify::visvalingam_with(&self.points, min_area, true))
+    }
+
+    /// Return the edges of the polygon as segments.
+    pub fn segments(&self) -> impl Iterator<Item = Segment> + '_ {
+        self.edges().map(|(p0, p1)| Segment::new(*p0, *p1))
+    }
+
+    /// Return whether the polygon is simple, that is no edge intersects another
+    /// one apart from consecutive edges sharing their common vertex.
+    pub fn is_simple(&self) -> bool {
+        let edges = self.segments().collect::<Vec<_>>();
+        let n = edges.len();
+
+        segment::intersections(&edges)
+            .into_iter()
+            .all(|(i, j, int)| {
+                let shared = if j == i + 1 {
+                    edges[i].end
+                } else if i == 0 && j == n - 1 {
+                    edges[i].start
+                } else {
+                    return false;
+                };
+
+                int == Intersection::Point(shared)
+            })
+    }
+
+    /// Return the union of this polygon with another one.
+    pub fn union(&self, other: &Self) -> Vec<Self> {
+        boolean(self, other, Operation::Union)
+    }
+
+    /// Return the intersection of this polygon with another one, that is this
+    /// polygon clipped by the other.
+    pub fn intersection(&self, other: &Self) -> Vec<Self> {
+        boolean(self, other, Operation::Intersection)
+    }
+
+    /// Return the parts of this polygon that are not covered by the other.
+    pub fn difference(&self, other: &Self) -> Vec<Self> {
+        boolean(self, other, Operation::Difference)
+    }
+
+    /// Return the parts of the polygons that are covered by only one of them.
+    pub fn xor(&self, other: &Self) -> Vec<Self> {
+        boolean(self, other, Operation::Xor)
+    }
+}
+
 fn in_range<T: PartialOrd>(a: &T, b: &T, v: &T) -> bool {
     let (min, max) = if a < b { (a, b) } else { (b, a) };
 
@@ -129,7 +315,7 @@ fn in_range<T: PartialOrd>(a: &T, b: &T, v: &T) -> bool {
 mod tests {
     use super::Polygon;
 
-    use geo::{BoundingBox, PointF64, PointU32};
+    use crate::{AngleOrientation, BoundingBox, PointF64, PointU32};
 
     #[test]
     fn test_polygon_new() {
@@ -309,4 +495,120 @@ mod tests {
 
         assert!(poly.contains(&PointF64::new(1.0, 247.0)));
     }
+
+    #[test]
+    fn test_polygon_is_simple() {
+        let square = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(4.0, 4.0),
+            PointF64::new(0.0, 4.0),
+        ])
+        .unwrap();
+        assert!(square.is_simple());
+
+        let bowtie = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(4.0, 4.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(0.0, 4.0),
+        ])
+        .unwrap();
+        assert!(!bowtie.is_simple());
+
+        // the last vertex touches the first edge
+        let touching = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(4.0, 4.0),
+            PointF64::new(2.0, 0.0),
+            PointF64::new(0.0, 4.0),
+        ])
+        .unwrap();
+        assert!(!touching.is_simple());
+    }
+
+    #[test]
+    fn test_polygon_measurements() {
+        let square = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(4.0, 4.0),
+            PointF64::new(0.0, 4.0),
+        ])
+        .unwrap();
+
+        assert_eq!(square.signed_area(), 16.0);
+        assert_eq!(square.reversed().signed_area(), -16.0);
+        assert_eq!(square.reversed().area(), 16.0);
+        assert_eq!(square.perimeter(), 16.0);
+        assert_eq!(square.centroid(), Some(PointF64::new(2.0, 2.0)));
+        assert_eq!(square.reversed().centroid(), Some(PointF64::new(2.0, 2.0)));
+        assert_eq!(square.orientation(), AngleOrientation::CounterClockwise);
+        assert_eq!(square.reversed().orientation(), AngleOrientation::Clockwise);
+
+        let line = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(1.0, 1.0),
+            PointF64::new(2.0, 2.0),
+        ])
+        .unwrap();
+        assert_eq!(line.centroid(), None);
+        assert_eq!(line.orientation(), AngleOrientation::Colinear);
+        assert!(!line.is_convex());
+
+        // L shaped
+        let concave = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(4.0, 2.0),
+            PointF64::new(2.0, 2.0),
+            PointF64::new(2.0, 4.0),
+            PointF64::new(0.0, 4.0),
+        ])
+        .unwrap();
+        assert_eq!(concave.area(), 12.0);
+        assert!(square.is_convex());
+        assert!(square.reversed().is_convex());
+        assert!(!concave.is_convex());
+
+        // a pentagram turns around twice with consistent orientation
+        let star = Polygon::new((0..5).map(|i| {
+            let a = f64::from(i) * 4.0 * std::f64::consts::PI / 5.0;
+            PointF64::new(a.cos(), a.sin())
+        }))
+        .unwrap();
+        assert!(!star.is_convex());
+    }
+
+    #[test]
+    fn test_polygon_simplify() {
+        let noisy = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(2.0, 0.1),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(4.1, 2.0),
+            PointF64::new(4.0, 4.0),
+            PointF64::new(2.0, 3.9),
+            PointF64::new(0.0, 4.0),
+            PointF64::new(-0.1, 2.0),
+        ])
+        .unwrap();
+
+        let square = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(4.0, 4.0),
+            PointF64::new(0.0, 4.0),
+        ])
+        .unwrap();
+
+        assert_eq!(noisy.simplify_rdp(0.5), Some(square.clone()));
+        assert_eq!(noisy.simplify_rdp(0.0), Some(noisy.clone()));
+        assert_eq!(noisy.simplify_rdp(100.0), None);
+
+        assert_eq!(noisy.simplify_visvalingam(1.0), Some(square));
+        assert_eq!(noisy.simplify_visvalingam(0.0), Some(noisy.clone()));
+        assert_eq!(noisy.simplify_visvalingam(100.0).unwrap().points().len(), 4);
+    }
 }
@@ -0,0 +1,336 @@
+//! Robust geometric predicates. The predicates are first evaluated with plain
+//! floating point arithmetic and, only if the result is too close to zero to be
+//! trusted, they're evaluated again exactly using the floating point expansions
+//! described in [Adaptive Precision Floating-Point Arithmetic and Fast Robust
+//! Geometric Predicates](https://www.cs.cmu.edu/~quake/robust.html).
+
+use crate::point::PointF64;
+
+const EPSILON: f64 = f64::EPSILON / 2.0;
+const ORIENT_ERROR_BOUND: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+const IN_CIRCLE_ERROR_BOUND: f64 = (10.0 + 96.0 * EPSILON) * EPSILON;
+
+/// Return a positive value if `a`, `b` and `c` are in counter clockwise order,
+/// a negative value if they're in clockwise order and zero if they're
+/// collinear. The sign is always exact, but the magnitude is only an
+/// approximation of twice the signed area of the triangle.
+///
+/// The y axis points up, in image coordinates where it points down the sign is
+/// reversed.
+pub fn orient2d(a: &PointF64, b: &PointF64, c: &PointF64) -> f64 {
+    let (acx, bcy) = (a.x - c.x, b.y - c.y);
+    let (acy, bcx) = (a.y - c.y, b.x - c.x);
+    let left = acx * bcy;
+    let right = acy * bcx;
+    let det = left - right;
+
+    let bound = ORIENT_ERROR_BOUND * (left.abs() + right.abs());
+    if det > bound || -det > bound {
+        return det;
+    }
+
+    // the difference of two floats is zero only if they're equal, in that
+    // case the products are exactly zero too. This is common for points that
+    // share a coordinate and it's worth to skip the slow exact evaluation.
+    if (acx == 0.0 || bcy == 0.0) && (acy == 0.0 || bcx == 0.0) {
+        return 0.0;
+    }
+
+    let terms = [
+        (1.0, vec![a.x, b.y]),
+        (-1.0, vec![a.x, c.y]),
+        (-1.0, vec![c.x, b.y]),
+        (-1.0, vec![a.y, b.x]),
+        (1.0, vec![a.y, c.x]),
+        (1.0, vec![c.y, b.x]),
+    ];
+
+    exact_sum(&terms)
+}
+
+/// Return a positive value if `d` lies inside the circle passing through `a`,
+/// `b` and `c`, a negative value if it lies outside and zero if it lies on
+/// the circle. `a`, `b` and `c` must be in counter clockwise order according to
+/// `orient2d`, otherwise the sign is reversed. Like `orient2d` the sign is
+/// exact, but the magnitude is only an approximation.
+pub fn in_circle(a: &PointF64, b: &PointF64, c: &PointF64, d: &PointF64) -> f64 {
+    let (adx, ady) = (a.x - d.x, a.y - d.y);
+    let (bdx, bdy) = (b.x - d.x, b.y - d.y);
+    let (cdx, cdy) = (c.x - d.x, c.y - d.y);
+
+    let alift = adx * adx + ady * ady;
+    let blift = bdx * bdx + bdy * bdy;
+    let clift = cdx * cdx + cdy * cdy;
+
+    let bc = bdx * cdy - cdx * bdy;
+    let ca = cdx * ady - adx * cdy;
+    let ab = adx * bdy - bdx * ady;
+
+    let det = alift * bc + blift * ca + clift * ab;
+
+    let permanent = alift * ((bdx * cdy).abs() + (cdx * bdy).abs())
+        + blift * ((cdx * ady).abs() + (adx * cdy).abs())
+        + clift * ((adx * bdy).abs() + (bdx * ady).abs());
+
+    let bound = IN_CIRCLE_ERROR_BOUND * permanent;
+    if det > bound || -det > bound {
+        return det;
+    }
+
+    exact_sum(&in_circle_terms(a, b, c, d))
+}
+
+/// Expand the determinant
+///
+/// | ax ay ax²+ay² 1 |
+/// | bx by bx²+by² 1 |
+/// | cx cy cx²+cy² 1 |
+/// | dx dy dx²+dy² 1 |
+///
+/// that has the same sign of `in_circle` into a sum of monomials of the
+/// coordinates so that it can be evaluated exactly.
+fn in_circle_terms(a: &PointF64, b: &PointF64, c: &PointF64, d: &PointF64) -> Vec<(f64, Vec<f64>)> {
+    let rows = [a, b, c, d];
+    let mut terms = vec![];
+
+    for perm in permutations(&[0, 1, 2, 3]) {
+        let sign = permutation_sign(&perm);
+
+        // the row that goes in the column of the lifted coordinate is
+        // expanded into two monomials
+        let mut factors = vec![];
+        let mut lifted = None;
+
+        for (row, col) in perm.iter().enumerate() {
+            let p = rows[row];
+
+            match col {
+                0 => factors.push(p.x),
+                1 => factors.push(p.y),
+                2 => lifted = Some(p),
+                _ => {}
+            }
+        }
+
+        let lifted = lifted.unwrap();
+
+        let mut fx = factors.clone();
+        fx.extend_from_slice(&[lifted.x, lifted.x]);
+        terms.push((sign, fx));
+
+        factors.extend_from_slice(&[lifted.y, lifted.y]);
+        terms.push((sign, factors));
+    }
+
+    terms
+}
+
+fn permutations(items: &[usize]) -> Vec<Vec<usize>> {
+    if items.len() <= 1 {
+        return vec![items.to_vec()];
+    }
+
+    let mut out = vec![];
+    for i in 0..items.len() {
+        let mut rest = items.to_vec();
+        let first = rest.remove(i);
+
+        for mut perm in permutations(&rest) {
+            perm.insert(0, first);
+            out.push(perm);
+        }
+    }
+
+    out
+}
+
+fn permutation_sign(perm: &[usize]) -> f64 {
+    let mut inversions = 0;
+    for i in 0..perm.len() {
+        for j in i + 1..perm.len() {
+            if perm[i] > perm[j] {
+                inversions += 1;
+            }
+        }
+    }
+
+    if inversions % 2 == 0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+/// Sum exactly the given signed products and return the most significant
+/// component of the result which has the same sign of the exact sum.
+fn exact_sum(terms: &[(f64, Vec<f64>)]) -> f64 {
+    let mut sum = vec![];
+
+    for (sign, factors) in terms {
+        let mut product = vec![sign * factors[0]];
+        for f in &factors[1..] {
+            product = scale_expansion(&product, *f);
+        }
+
+        for component in product {
+            sum = grow_expansion(&sum, component);
+        }
+    }
+
+    sum.into_iter().rev().find(|c| *c != 0.0).unwrap_or(0.0)
+}
+
+/// Return `a + b` as the rounded sum and the rounding error.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let bv = x - a;
+    let av = x - bv;
+
+    (x, (a - av) + (b - bv))
+}
+
+/// Return `a * b` as the rounded product and the rounding error.
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+
+    (x, a.mul_add(b, -x))
+}
+
+/// Add `b` to the nonoverlapping expansion `e` sorted by increasing magnitude.
+/// The result has the same properties, zero components are removed.
+fn grow_expansion(e: &[f64], b: f64) -> Vec<f64> {
+    let mut out = Vec::with_capacity(e.len() + 1);
+    let mut q = b;
+
+    for c in e {
+        let (sum, err) = two_sum(q, *c);
+        if err != 0.0 {
+            out.push(err);
+        }
+        q = sum;
+    }
+
+    if q != 0.0 {
+        out.push(q);
+    }
+
+    out
+}
+
+/// Multiply the expansion `e` by `b` exactly.
+fn scale_expansion(e: &[f64], b: f64) -> Vec<f64> {
+    let mut out = vec![];
+
+    for c in e {
+        let (product, err) = two_product(*c, b);
+
+        out = grow_expansion(&out, err);
+        out = grow_expansion(&out, product);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn exact_orient(a: (i64, i64), b: (i64, i64), c: (i64, i64)) -> i128 {
+        let (ax, ay) = (i128::from(a.0), i128::from(a.1));
+        let (bx, by) = (i128::from(b.0), i128::from(b.1));
+        let (cx, cy) = (i128::from(c.0), i128::from(c.1));
+
+        (ax - cx) * (by - cy) - (ay - cy) * (bx - cx)
+    }
+
+    fn exact_in_circle(a: (i64, i64), b: (i64, i64), c: (i64, i64), d: (i64, i64)) -> i128 {
+        let rel = |p: (i64, i64)| (i128::from(p.0 - d.0), i128::from(p.1 - d.1));
+        let ((adx, ady), (bdx, bdy), (cdx, cdy)) = (rel(a), rel(b), rel(c));
+
+        (adx * adx + ady * ady) * (bdx * cdy - cdx * bdy)
+            + (bdx * bdx + bdy * bdy) * (cdx * ady - adx * cdy)
+            + (cdx * cdx + cdy * cdy) * (adx * bdy - bdx * ady)
+    }
+
+    fn sign(x: f64) -> i128 {
+        if x > 0.0 {
+            1
+        } else if x < 0.0 {
+            -1
+        } else {
+            0
+        }
+    }
+
+    fn pt(p: (i64, i64)) -> PointF64 {
+        PointF64::new(p.0 as f64, p.1 as f64)
+    }
+
+    #[test]
+    fn test_orient2d() {
+        let a = PointF64::new(0.0, 0.0);
+        let b = PointF64::new(1.0, 0.0);
+
+        assert!(orient2d(&a, &b, &PointF64::new(0.0, 1.0)) > 0.0);
+        assert!(orient2d(&a, &b, &PointF64::new(0.0, -1.0)) < 0.0);
+        assert_eq!(orient2d(&a, &b, &PointF64::new(7.0, 0.0)), 0.0);
+
+        // the naive formula gets these wrong
+        let a = PointF64::new(0.5, 0.5);
+        let b = PointF64::new(12.0, 12.0);
+        let c = PointF64::new(24.0, 24.0);
+        assert_eq!(orient2d(&a, &b, &c), 0.0);
+
+        let c = PointF64::new(24.0, 24.000_000_000_000_004);
+        assert!(orient2d(&a, &b, &c) > 0.0);
+    }
+
+    #[test]
+    fn test_in_circle() {
+        let a = PointF64::new(0.0, 0.0);
+        let b = PointF64::new(2.0, 0.0);
+        let c = PointF64::new(0.0, 2.0);
+
+        assert!(in_circle(&a, &b, &c, &PointF64::new(1.0, 1.0)) > 0.0);
+        assert!(in_circle(&a, &b, &c, &PointF64::new(3.0, 3.0)) < 0.0);
+        assert_eq!(in_circle(&a, &b, &c, &PointF64::new(2.0, 2.0)), 0.0);
+        assert!(in_circle(&a, &c, &b, &PointF64::new(1.0, 1.0)) < 0.0);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_orient2d_is_exact(
+            a in (-(1_i64 << 40)..(1 << 40), -(1_i64 << 40)..(1 << 40)),
+            b in (-(1_i64 << 40)..(1 << 40), -(1_i64 << 40)..(1 << 40)),
+            t in -4_i64..4,
+            eps in -2_i64..3,
+        ) {
+            // points almost on the line between a and b are the hard ones
+            let c = (a.0 + t * (b.0 - a.0) / 4 + eps, a.1 + t * (b.1 - a.1) / 4);
+
+            let got = orient2d(&pt(a), &pt(b), &pt(c));
+
+            prop_assert_eq!(sign(got), exact_orient(a, b, c).signum());
+        }
+
+        #[test]
+        fn prop_in_circle_is_exact(
+            cx in -(1_i64 << 20)..(1 << 20),
+            cy in -(1_i64 << 20)..(1 << 20),
+            r in 1_i64..(1 << 20),
+            eps in -2_i64..3,
+        ) {
+            // pythagorean triples put all the points exactly on the circle
+            let a = (cx + 5 * r, cy);
+            let b = (cx + 3 * r, cy + 4 * r);
+            let c = (cx - 4 * r, cy + 3 * r);
+            let d = (cx, cy - 5 * r + eps);
+
+            let got = in_circle(&pt(a), &pt(b), &pt(c), &pt(d));
+
+            prop_assert_eq!(sign(got), exact_in_circle(a, b, c, d).signum());
+        }
+    }
+}
@@ -0,0 +1,532 @@
+//! A point [Quadtree](https://en.wikipedia.org/wiki/Quadtree) that covers a
+//! bounding box and recursively splits it in 4 quadrants whenever one of them
+//! holds too many points. The leaves are therefore smaller where the points
+//! are denser.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::bbox::BoundingBox;
+use crate::kdtree::{Axis, Range};
+use crate::point::{Point, PointF64};
+use crate::utils::{OrdF64, OrdWrapper};
+
+/// The maximum depth of the tree, it stops many points that are very close
+/// from splitting the leaves forever.
+const MAX_DEPTH: usize = 32;
+
+/// A leaf of the Quadtree: its bounding box and the points inside it.
+pub type Leaf<'a, T, V> = (BoundingBox<f64>, &'a [(Point<T>, V)]);
+
+/// A point Quadtree.
+#[derive(Debug, Clone)]
+pub struct Quadtree<T, V> {
+    bbox: BoundingBox<f64>,
+    capacity: usize,
+    root: QuadNode<T, V>,
+    length: usize,
+}
+
+#[derive(Debug, Clone)]
+enum QuadNode<T, V> {
+    Leaf(Vec<(Point<T>, V)>),
+
+    // the children are in the same order of `BoundingBox::split_at`
+    Inner(Box<[QuadNode<T, V>; 4]>),
+}
+
+impl<T, V> Quadtree<T, V>
+where
+    T: Copy + PartialEq + num::ToPrimitive,
+{
+    /// Create a new empty Quadtree covering the given bounding box whose leaves
+    /// are split once they hold more than `capacity` points.
+    pub fn new(bbox: BoundingBox<f64>, capacity: usize) -> Self {
+        Quadtree {
+            bbox,
+            capacity: capacity.max(1),
+            root: QuadNode::Leaf(vec![]),
+            length: 0,
+        }
+    }
+
+    /// Return the bounding box covered by this Quadtree.
+    pub fn bbox(&self) -> &BoundingBox<f64> {
+        &self.bbox
+    }
+
+    /// Check if this Quadtree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the number of points in this Quadtree.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Add a point to this Quadtree, return the old value if the point was
+    /// already there. Panics if the point is outside the bounding box of the
+    /// tree.
+    pub fn add(&mut self, point: Point<T>, value: V) -> Option<V> {
+        assert!(
+            self.bbox.contains(&to_f64(&point)),
+            "the point is outside the quadtree"
+        );
+
+        let old_value = self
+            .root
+            .add(self.bbox.clone(), self.capacity, 0, point, value);
+
+        if old_value.is_none() {
+            self.length += 1;
+        }
+
+        old_value
+    }
+
+    /// Remove the given point from this Quadtree and return its value if it
+    /// was present. The quadrants that end up with few points are merged.
+    pub fn remove(&mut self, point: &Point<T>) -> Option<V> {
+        if !self.bbox.contains(&to_f64(point)) {
+            return None;
+        }
+
+        let removed = self.root.remove(self.bbox.clone(), self.capacity, point);
+        if removed.is_some() {
+            self.length -= 1;
+        }
+
+        removed
+    }
+
+    /// Return the value of the given point if it's in this Quadtree.
+    pub fn get(&self, point: &Point<T>) -> Option<&V> {
+        let (mut node, mut bbox) = (&self.root, self.bbox.clone());
+
+        loop {
+            match node {
+                QuadNode::Leaf(points) => {
+                    return points.iter().find(|(p, _)| p == point).map(|(_, v)| v)
+                }
+                QuadNode::Inner(children) => {
+                    let (q, child_bbox) = quadrant(&bbox, point);
+                    node = &children[q];
+                    bbox = child_bbox;
+                }
+            }
+        }
+    }
+
+    /// Return a mutable reference to the value of the given point if it's in
+    /// this Quadtree.
+    pub fn get_mut(&mut self, point: &Point<T>) -> Option<&mut V> {
+        let (mut node, mut bbox) = (&mut self.root, self.bbox.clone());
+
+        loop {
+            match node {
+                QuadNode::Leaf(points) => {
+                    return points.iter_mut().find(|(p, _)| p == point).map(|(_, v)| v)
+                }
+                QuadNode::Inner(children) => {
+                    let (q, child_bbox) = quadrant(&bbox, point);
+                    node = &mut children[q];
+                    bbox = child_bbox;
+                }
+            }
+        }
+    }
+
+    /// Return the bounding box of every leaf along with its points.
+    pub fn leaves(&self) -> Vec<Leaf<'_, T, V>> {
+        let mut leaves = vec![];
+        let mut nodes = vec![(&self.root, self.bbox.clone())];
+
+        while let Some((node, bbox)) = nodes.pop() {
+            match node {
+                QuadNode::Leaf(points) => leaves.push((bbox, &points[..])),
+                QuadNode::Inner(children) => nodes.extend(children.iter().zip(split(&bbox))),
+            }
+        }
+
+        leaves
+    }
+
+    /// Return an iterator over all the points of the tree.
+    pub fn iter(&self) -> impl Iterator<Item = (&Point<T>, &V)> {
+        self.leaves()
+            .into_iter()
+            .flat_map(|(_, points)| points.iter().map(|(p, v)| (p, v)))
+    }
+
+    /// Return all the points that are in the given range.
+    pub fn in_range_iter<'s, 'r, R>(
+        &'s self,
+        range: &'r R,
+    ) -> impl Iterator<Item = (&'s Point<T>, &'s V)> + 'r
+    where
+        R: Range<Point<T>, AxisValue = T>,
+        's: 'r,
+    {
+        let (x0, x1) = range.axis_value_range(Axis::X);
+        let (y0, y1) = range.axis_value_range(Axis::Y);
+        let min = to_f64(&Point { x: x0, y: y0 });
+        let max = to_f64(&Point { x: x1, y: y1 });
+
+        self.visit(move |bbox| {
+            bbox.min().x <= max.x
+                && bbox.max().x >= min.x
+                && bbox.min().y <= max.y
+                && bbox.max().y >= min.y
+        })
+        .filter(move |(p, _)| range.contains(p))
+    }
+
+    /// Return the nearest neighbor to the given point.
+    pub fn nearest_neighbor(&self, point: Point<T>) -> Option<(&Point<T>, &V)> {
+        self.nearest_neighbors(point, 1).into_iter().next()
+    }
+
+    /// Return, at most, the k nearest neighbors to the given point, closest
+    /// first.
+    pub fn nearest_neighbors(&self, point: Point<T>, k: usize) -> Vec<(&Point<T>, &V)> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let target = to_f64(&point);
+
+        // visit the nodes closest first so that the search can stop as soon
+        // as the closest node left is farther than all the neighbors found
+        let mut nodes = BinaryHeap::new();
+        nodes.push(Reverse(OrdWrapper::new(
+            (&self.root, self.bbox.clone()),
+            OrdF64(squared_dist_to_bbox(&target, &self.bbox)),
+        )));
+
+        let mut neighbors = BinaryHeap::with_capacity(k + 1);
+
+        while let Some(Reverse(ow)) = nodes.pop() {
+            let OrdF64(node_dist) = *ow.key();
+            let (node, bbox) = ow.data();
+
+            if neighbors.len() == k {
+                let worst: &OrdWrapper<_, OrdF64> = neighbors.peek().unwrap();
+                if node_dist >= worst.key().0 {
+                    break;
+                }
+            }
+
+            match node {
+                QuadNode::Leaf(points) => {
+                    for (p, v) in points {
+                        let d = to_f64(p).squared_dist::<f64>(&target);
+
+                        neighbors.push(OrdWrapper::new((p, v), OrdF64(d)));
+                        if neighbors.len() > k {
+                            neighbors.pop();
+                        }
+                    }
+                }
+                QuadNode::Inner(children) => {
+                    for (child, child_bbox) in children.iter().zip(split(bbox)) {
+                        let d = squared_dist_to_bbox(&target, &child_bbox);
+                        nodes.push(Reverse(OrdWrapper::new((child, child_bbox), OrdF64(d))));
+                    }
+                }
+            }
+        }
+
+        neighbors
+            .into_sorted_vec()
+            .into_iter()
+            .map(|ow| *ow.data())
+            .collect()
+    }
+
+    /// Return all the points whose distance from the given point is at most
+    /// `radius` along with their distances, in no particular order.
+    pub fn radius_search(&self, point: Point<T>, radius: f64) -> Vec<(&Point<T>, &V, f64)> {
+        let target = to_f64(&point);
+
+        self.visit(|bbox| squared_dist_to_bbox(&target, bbox) <= radius * radius)
+            .filter_map(|(p, v)| {
+                let d = to_f64(p).dist::<f64>(&target);
+                if d <= radius {
+                    Some((p, v, d))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Return the points of the leaves reachable through the nodes whose
+    /// bounding box satisfies `visit`.
+    fn visit(
+        &self,
+        visit: impl Fn(&BoundingBox<f64>) -> bool,
+    ) -> impl Iterator<Item = (&Point<T>, &V)> {
+        let mut points = vec![];
+        let mut nodes = vec![(&self.root, self.bbox.clone())];
+
+        while let Some((node, bbox)) = nodes.pop() {
+            if !visit(&bbox) {
+                continue;
+            }
+
+            match node {
+                QuadNode::Leaf(leaf_points) => points.push(leaf_points),
+                QuadNode::Inner(children) => nodes.extend(children.iter().zip(split(&bbox))),
+            }
+        }
+
+        points.into_iter().flatten().map(|(p, v)| (p, v))
+    }
+}
+
+impl<T, V> QuadNode<T, V>
+where
+    T: Copy + PartialEq + num::ToPrimitive,
+{
+    fn add(
+        &mut self,
+        bbox: BoundingBox<f64>,
+        capacity: usize,
+        depth: usize,
+        point: Point<T>,
+        value: V,
+    ) -> Option<V> {
+        match self {
+            QuadNode::Inner(children) => {
+                let (q, child_bbox) = quadrant(&bbox, &point);
+                children[q].add(child_bbox, capacity, depth + 1, point, value)
+            }
+            QuadNode::Leaf(points) => {
+                if let Some((_, v)) = points.iter_mut().find(|(p, _)| *p == point) {
+                    return Some(std::mem::replace(v, value));
+                }
+
+                points.push((point, value));
+
+                if points.len() > capacity && depth < MAX_DEPTH {
+                    let points = std::mem::take(points);
+
+                    *self = QuadNode::Inner(Box::new([
+                        QuadNode::Leaf(vec![]),
+                        QuadNode::Leaf(vec![]),
+                        QuadNode::Leaf(vec![]),
+                        QuadNode::Leaf(vec![]),
+                    ]));
+
+                    for (p, v) in points {
+                        self.add(bbox.clone(), capacity, depth, p, v);
+                    }
+                }
+
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, bbox: BoundingBox<f64>, capacity: usize, point: &Point<T>) -> Option<V> {
+        let children = match self {
+            QuadNode::Leaf(points) => {
+                let i = points.iter().position(|(p, _)| p == point)?;
+                return Some(points.swap_remove(i).1);
+            }
+            QuadNode::Inner(children) => children,
+        };
+
+        let (q, child_bbox) = quadrant(&bbox, point);
+        let removed = children[q].remove(child_bbox, capacity, point)?;
+
+        // merge the children back if they fit in a single leaf
+        let mut len = 0;
+        for child in children.iter() {
+            match child {
+                QuadNode::Leaf(points) => len += points.len(),
+                QuadNode::Inner(_) => return Some(removed),
+            }
+        }
+
+        if len <= capacity {
+            let mut merged = Vec::with_capacity(len);
+            for child in children.iter_mut() {
+                if let QuadNode::Leaf(points) = child {
+                    merged.append(points);
+                }
+            }
+
+            *self = QuadNode::Leaf(merged);
+        }
+
+        Some(removed)
+    }
+}
+
+/// Return the index and the bounding box of the quadrant of the box that
+/// contains the point.
+fn quadrant<T: num::ToPrimitive>(
+    bbox: &BoundingBox<f64>,
+    point: &Point<T>,
+) -> (usize, BoundingBox<f64>) {
+    let center = bbox.center();
+    let p = to_f64(point);
+
+    let q = usize::from(p.x >= center.x) + 2 * usize::from(p.y >= center.y);
+    let child_bbox = split(bbox).nth(q).unwrap();
+
+    (q, child_bbox)
+}
+
+/// Return the 4 quadrants of the bounding box.
+fn split(bbox: &BoundingBox<f64>) -> impl Iterator<Item = BoundingBox<f64>> {
+    let (q0, q1, q2, q3) = bbox.split_at(&bbox.center()).unwrap();
+
+    vec![q0, q1, q2, q3].into_iter()
+}
+
+fn squared_dist_to_bbox(p: &PointF64, bbox: &BoundingBox<f64>) -> f64 {
+    let dx = (bbox.min().x - p.x).max(p.x - bbox.max().x).max(0.0);
+    let dy = (bbox.min().y - p.y).max(p.y - bbox.max().y).max(0.0);
+
+    dx * dx + dy * dy
+}
+
+fn to_f64<T: num::ToPrimitive>(point: &Point<T>) -> PointF64 {
+    Point::new(point.x.to_f64().unwrap(), point.y.to_f64().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    use crate::PointU32;
+
+    #[test]
+    fn test_split_and_merge() {
+        let mut tree = Quadtree::new(BoundingBox::from_dimensions(8.0, 8.0), 2);
+
+        tree.add(PointU32::new(1, 1), 'a');
+        tree.add(PointU32::new(6, 6), 'b');
+        assert_eq!(tree.leaves().len(), 1);
+
+        // the third point splits the root
+        tree.add(PointU32::new(1, 2), 'c');
+        assert_eq!(tree.leaves().len(), 4);
+        assert_eq!(tree.len(), 3);
+
+        assert_eq!(tree.add(PointU32::new(1, 2), 'd'), Some('c'));
+        *tree.get_mut(&PointU32::new(6, 6)).unwrap() = 'e';
+        assert_eq!(tree.get(&PointU32::new(6, 6)), Some(&'e'));
+
+        let mut leaves = tree
+            .leaves()
+            .into_iter()
+            .map(|(bbox, points)| (bbox.min().x, bbox.min().y, points.len()))
+            .collect::<Vec<_>>();
+        leaves.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            leaves,
+            vec![(0.0, 0.0, 2), (0.0, 4.0, 0), (4.0, 0.0, 0), (4.0, 4.0, 1)]
+        );
+
+        assert_eq!(tree.remove(&PointU32::new(1, 1)), Some('a'));
+        assert_eq!(tree.remove(&PointU32::new(1, 1)), None);
+        assert_eq!(tree.remove(&PointU32::new(100, 1)), None);
+        assert_eq!(tree.leaves().len(), 1);
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    fn test_duplicates_close_points() {
+        let mut tree = Quadtree::new(BoundingBox::from_dimensions(1.0, 1.0), 1);
+
+        // these points would split the tree forever without a maximum depth
+        tree.add(PointF64::new(0.5, 0.5), ());
+        tree.add(PointF64::new(0.5, 0.5 + 1e-15), ());
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.radius_search(PointF64::new(0.5, 0.5), 0.1).len(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_add_outside() {
+        let mut tree = Quadtree::new(BoundingBox::from_dimensions(1.0, 1.0), 1);
+        tree.add(PointF64::new(2.0, 0.5), ());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(200))]
+        #[test]
+        fn prop_quadtree_same_as_loop(
+            points in prop::collection::hash_set((0_u32..255, 0_u32..255), 0..100),
+            removed in 0_usize..50,
+            to_search in (0_u32..300, 0_u32..300),
+            k in 0_usize..6,
+            radius in 0.0..100.0,
+            rect in (0_u32..255, 0_u32..255, 0_u32..255, 0_u32..255),
+            capacity in 1_usize..8,
+        ) {
+            let mut points = points
+                .into_iter()
+                .map(|(x, y)| PointU32::new(x, y))
+                .collect::<Vec<_>>();
+            let mut tree = Quadtree::new(BoundingBox::from_dimensions(255.0, 255.0), capacity);
+            for p in &points {
+                tree.add(*p, ());
+            }
+            for p in points.drain(..removed.min(points.len())) {
+                prop_assert_eq!(tree.remove(&p), Some(()));
+            }
+            prop_assert_eq!(tree.len(), points.len());
+            prop_assert_eq!(tree.iter().count(), points.len());
+
+            let to_search = PointU32::new(to_search.0, to_search.1);
+
+            let mut distances = points
+                .iter()
+                .map(|p| p.squared_dist::<i64>(&to_search))
+                .collect::<Vec<_>>();
+            distances.sort();
+            distances.truncate(k);
+
+            let found = tree
+                .nearest_neighbors(to_search, k)
+                .into_iter()
+                .map(|(p, _)| p.squared_dist::<i64>(&to_search))
+                .collect::<Vec<_>>();
+            prop_assert_eq!(found, distances);
+
+            let mut found = tree
+                .radius_search(to_search, radius)
+                .into_iter()
+                .map(|(p, _, _)| *p)
+                .collect::<Vec<_>>();
+            let mut brute_force = points
+                .iter()
+                .filter(|p| p.dist::<f64>(&to_search) <= radius)
+                .cloned()
+                .collect::<Vec<_>>();
+            found.sort_by_key(|p| (p.x, p.y));
+            brute_force.sort_by_key(|p| (p.x, p.y));
+            prop_assert_eq!(found, brute_force);
+
+            let range =
+                BoundingBox::from_dimensions_and_origin(&PointU32::new(rect.0, rect.1), rect.2, rect.3);
+            let mut found = tree.in_range_iter(&range).map(|(p, _)| *p).collect::<Vec<_>>();
+            let mut brute_force = points
+                .iter()
+                .filter(|p| range.contains(p))
+                .cloned()
+                .collect::<Vec<_>>();
+            found.sort_by_key(|p| (p.x, p.y));
+            brute_force.sort_by_key(|p| (p.x, p.y));
+            prop_assert_eq!(found, brute_force);
+        }
+    }
+}
@@ -0,0 +1,732 @@
+//! Line segments and robust intersection tests between them.
+//!
+//! The intersection tests rely on the exact `orient2d` predicate therefore
+//! touching and collinear segments are always detected correctly, only the
+//! coordinates of a proper crossing are subject to rounding.
+//!
+//! The module also implements the [Bentley–Ottmann
+//! algorithm](https://en.wikipedia.org/wiki/Bentley%E2%80%93Ottmann_algorithm)
+//! to find all the intersecting pairs in a set of segments without testing
+//! every pair.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::bbox::BoundingBox;
+use crate::point::PointF64;
+use crate::predicates::orient2d;
+use crate::utils::OrdF64;
+
+/// A line segment between two points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Segment {
+    /// The first endpoint of the segment.
+    pub start: PointF64,
+
+    /// The second endpoint of the segment.
+    pub end: PointF64,
+}
+
+/// The intersection between two segments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Intersection {
+    /// The segments cross or touch in a single point.
+    Point(PointF64),
+
+    /// The segments are collinear and share the given segment.
+    Overlap(Segment),
+}
+
+impl Segment {
+    /// Create a new `Segment` between the given points.
+    pub fn new(start: PointF64, end: PointF64) -> Self {
+        Segment { start, end }
+    }
+
+    /// Return whether the segment degenerates to a single point.
+    pub fn is_degenerate(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Return the length of the segment.
+    pub fn length(&self) -> f64 {
+        self.start.dist(&self.end)
+    }
+
+    /// Return the squared length of the segment.
+    pub fn squared_length(&self) -> f64 {
+        self.start.squared_dist(&self.end)
+    }
+
+    /// Return the minimum bounding box of the segment.
+    pub fn bounding_box(&self) -> BoundingBox<f64> {
+        BoundingBox::from_points(&[self.start, self.end])
+    }
+
+    /// Return the point at the given parameter along the segment, 0 is `start`
+    /// and 1 is `end`.
+    pub fn point_at(&self, t: f64) -> PointF64 {
+        PointF64::new(
+            self.start.x + t * (self.end.x - self.start.x),
+            self.start.y + t * (self.end.y - self.start.y),
+        )
+    }
+
+    /// Return the parameter of the projection of the point on the line that
+    /// passes through the segment. The projection lies on the segment only if
+    /// the parameter is between 0 and 1. A degenerate segment always returns 0.
+    pub fn project(&self, pt: &PointF64) -> f64 {
+        let len = self.squared_length();
+        if len == 0.0 {
+            return 0.0;
+        }
+
+        let dx = self.end.x - self.start.x;
+        let dy = self.end.y - self.start.y;
+
+        ((pt.x - self.start.x) * dx + (pt.y - self.start.y) * dy) / len
+    }
+
+    /// Return the point of the segment that is closest to the given point.
+    pub fn closest_point(&self, pt: &PointF64) -> PointF64 {
+        match self.project(pt) {
+            t if t <= 0.0 => self.start,
+            t if t >= 1.0 => self.end,
+            t => self.point_at(t),
+        }
+    }
+
+    /// Return the squared distance between the point and the segment.
+    pub fn squared_dist(&self, pt: &PointF64) -> f64 {
+        self.closest_point(pt).squared_dist(pt)
+    }
+
+    /// Return the distance between the point and the segment.
+    pub fn dist(&self, pt: &PointF64) -> f64 {
+        self.closest_point(pt).dist(pt)
+    }
+
+    /// Return whether the point lies exactly on the segment.
+    pub fn contains(&self, pt: &PointF64) -> bool {
+        orient2d(&self.start, &self.end, pt) == 0.0 && in_box(&self.start, &self.end, pt)
+    }
+
+    /// Return the intersection between this segment and another one, if any.
+    pub fn intersection(&self, other: &Segment) -> Option<Intersection> {
+        let (p0, p1) = (self.start, self.end);
+        let (q0, q1) = (other.start, other.end);
+
+        let o1 = orient2d(&p0, &p1, &q0);
+        let o2 = orient2d(&p0, &p1, &q1);
+        let o3 = orient2d(&q0, &q1, &p0);
+        let o4 = orient2d(&q0, &q1, &p1);
+
+        if (o1 > 0.0 && o2 > 0.0)
+            || (o1 < 0.0 && o2 < 0.0)
+            || (o3 > 0.0 && o4 > 0.0)
+            || (o3 < 0.0 && o4 < 0.0)
+        {
+            return None;
+        }
+
+        if o1 == 0.0 && o2 == 0.0 && o3 == 0.0 && o4 == 0.0 {
+            return self.collinear_intersection(other);
+        }
+
+        // if an endpoint lies on the other segment then it's the exact
+        // intersection point.
+        let pt = if o1 == 0.0 {
+            q0
+        } else if o2 == 0.0 {
+            q1
+        } else if o3 == 0.0 {
+            p0
+        } else if o4 == 0.0 {
+            p1
+        } else {
+            let t = o3 / (o3 - o4);
+            self.point_at(t.clamp(0.0, 1.0))
+        };
+
+        Some(Intersection::Point(pt))
+    }
+
+    /// Return whether the two segments cross in a single point that is not an
+    /// endpoint of either of them.
+    pub fn crosses(&self, other: &Segment) -> bool {
+        let o1 = orient2d(&self.start, &self.end, &other.start);
+        let o2 = orient2d(&self.start, &self.end, &other.end);
+        let o3 = orient2d(&other.start, &other.end, &self.start);
+        let o4 = orient2d(&other.start, &other.end, &self.end);
+
+        o1 * o2 < 0.0 && o3 * o4 < 0.0
+    }
+
+    fn collinear_intersection(&self, other: &Segment) -> Option<Intersection> {
+        if self.is_degenerate() && other.is_degenerate() {
+            return if self.start == other.start {
+                Some(Intersection::Point(self.start))
+            } else {
+                None
+            };
+        }
+
+        // all the points lie on the same line, project them on the axis where
+        // the line is longer.
+        let dir = if self.is_degenerate() { other } else { self };
+        let use_x = (dir.end.x - dir.start.x).abs() >= (dir.end.y - dir.start.y).abs();
+        let key = |p: &PointF64| if use_x { p.x } else { p.y };
+
+        let sorted = |s: &Segment| {
+            if key(&s.start) <= key(&s.end) {
+                (s.start, s.end)
+            } else {
+                (s.end, s.start)
+            }
+        };
+        let (p0, p1) = sorted(self);
+        let (q0, q1) = sorted(other);
+
+        let start = if key(&p0) >= key(&q0) { p0 } else { q0 };
+        let end = if key(&p1) <= key(&q1) { p1 } else { q1 };
+
+        match key(&start).partial_cmp(&key(&end)) {
+            Some(Ordering::Less) => Some(Intersection::Overlap(Segment::new(start, end))),
+            Some(Ordering::Equal) => Some(Intersection::Point(start)),
+            _ => None,
+        }
+    }
+}
+
+/// Find all the pairs of intersecting segments using the Bentley–Ottmann
+/// algorithm. The pairs are returned as the indices of the segments in
+/// ascending order along with their intersection.
+///
+/// The sweep line status is kept in a sorted vector which is fast in practice
+/// even though insertions and removals are linear in the number of segments
+/// crossing the sweep line.
+pub fn intersections(segments: &[Segment]) -> Vec<(usize, usize, Intersection)> {
+    Sweep::new(segments).run()
+}
+
+/// Return whether the point is inside the box spanned by the two points.
+fn in_box(a: &PointF64, b: &PointF64, pt: &PointF64) -> bool {
+    a.x.min(b.x) <= pt.x && pt.x <= a.x.max(b.x) && a.y.min(b.y) <= pt.y && pt.y <= a.y.max(b.y)
+}
+
+/// Compare points from left to right and then from bottom to top, the order
+/// in which the sweep line meets them.
+fn cmp_sweep(a: &PointF64, b: &PointF64) -> Ordering {
+    // adding 0 turns -0 into 0 which would otherwise be ordered before it
+    (a.x + 0.0)
+        .total_cmp(&(b.x + 0.0))
+        .then((a.y + 0.0).total_cmp(&(b.y + 0.0)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EventKind {
+    End,
+    Cross(usize, usize),
+    Start,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Event {
+    x: OrdF64,
+    y: OrdF64,
+    kind: EventKind,
+    segment: usize,
+}
+
+impl Event {
+    fn new(pt: PointF64, kind: EventKind, segment: usize) -> Self {
+        // adding 0 turns -0 into 0 so that the events at the same point are
+        // grouped together
+        Event {
+            x: OrdF64(pt.x + 0.0),
+            y: OrdF64(pt.y + 0.0),
+            kind,
+            segment,
+        }
+    }
+
+    fn point(&self) -> PointF64 {
+        PointF64::new(self.x.0, self.y.0)
+    }
+}
+
+struct Sweep<'s> {
+    input: &'s [Segment],
+
+    // the segments with the endpoints sorted in sweep order
+    segments: Vec<Segment>,
+    events: BinaryHeap<Reverse<Event>>,
+    sweep: PointF64,
+
+    // the non vertical segments crossing the sweep line from bottom to top
+    status: Vec<usize>,
+
+    // vertical segments don't have an order in the status, they're kept aside
+    // while the sweep line is on them
+    verticals: Vec<usize>,
+
+    checked: HashSet<(usize, usize)>,
+    found: Vec<(usize, usize, Intersection)>,
+}
+
+impl<'s> Sweep<'s> {
+    fn new(input: &'s [Segment]) -> Self {
+        let segments = input
+            .iter()
+            .map(|s| {
+                if cmp_sweep(&s.start, &s.end) == Ordering::Greater {
+                    Segment::new(s.end, s.start)
+                } else {
+                    *s
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut events = BinaryHeap::with_capacity(segments.len() * 2);
+        for (i, s) in segments.iter().enumerate() {
+            events.push(Reverse(Event::new(s.start, EventKind::Start, i)));
+            events.push(Reverse(Event::new(s.end, EventKind::End, i)));
+        }
+
+        Sweep {
+            input,
+            segments,
+            events,
+            sweep: PointF64::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+            status: vec![],
+            verticals: vec![],
+            checked: HashSet::new(),
+            found: vec![],
+        }
+    }
+
+    fn run(mut self) -> Vec<(usize, usize, Intersection)> {
+        while let Some(Reverse(event)) = self.events.pop() {
+            let pt = event.point();
+
+            let mut group = vec![event];
+            while let Some(Reverse(e)) = self.events.peek() {
+                if cmp_sweep(&e.point(), &pt) != Ordering::Equal {
+                    break;
+                }
+                group.push(*e);
+                self.events.pop();
+            }
+
+            self.sweep = pt;
+            self.handle_point(&pt, &group);
+        }
+
+        self.found.sort_by_key(|(i, j, _)| (*i, *j));
+        self.found
+    }
+
+    fn is_vertical(&self, s: usize) -> bool {
+        let s = &self.segments[s];
+        s.start.x == s.end.x
+    }
+
+    fn handle_point(&mut self, pt: &PointF64, group: &[Event]) {
+        let starting = group
+            .iter()
+            .filter(|e| e.kind == EventKind::Start)
+            .map(|e| e.segment)
+            .collect::<Vec<_>>();
+
+        let segments = &self.segments;
+        self.verticals
+            .retain(|v| cmp_sweep(&segments[*v].end, pt) != Ordering::Less);
+
+        // the crossing segments are swapped first so that the status is
+        // ordered as right after the point, a crossing might have been
+        // clamped to the end of one of them.
+        for e in group {
+            if let EventKind::Cross(a, b) = e.kind {
+                self.swap(a, b);
+            }
+        }
+
+        // all the segments that touch the point must be checked against each
+        // other because they're not necessarily neighbors in the status.
+        let (lo, hi) = self.equal_range(pt);
+        let mut touching = self.status[lo..hi].to_vec();
+        touching.extend(&starting);
+        touching.extend(
+            self.verticals
+                .iter()
+                .filter(|v| self.segments[**v].contains(pt)),
+        );
+        for (i, a) in touching.iter().enumerate() {
+            for b in &touching[i + 1..] {
+                self.check(*a, *b);
+            }
+        }
+
+        // the segments through the point are sorted again by where they go
+        // after it, which takes care of the ones ending, starting or crossing
+        // there.
+        let segments = &self.segments;
+        let mut block = self
+            .status
+            .drain(lo..hi)
+            .filter(|s| cmp_sweep(&segments[*s].end, pt) == Ordering::Greater)
+            .collect::<Vec<_>>();
+        for s in &starting {
+            if self.is_vertical(*s) {
+                self.add_vertical(*s);
+            } else {
+                block.push(*s);
+            }
+        }
+        block.sort_by(|s, t| self.cmp_after(*s, *t));
+
+        let len = block.len();
+        self.status.splice(lo..lo, block);
+        if lo > 0 && lo < self.status.len() {
+            self.check(self.status[lo - 1], self.status[lo]);
+        }
+        if len > 0 && lo + len < self.status.len() {
+            self.check(self.status[lo + len - 1], self.status[lo + len]);
+        }
+    }
+
+    /// Check the vertical segment against all the segments of the status that
+    /// cross its column.
+    fn add_vertical(&mut self, v: usize) {
+        let seg = self.segments[v];
+
+        let lo = self
+            .status
+            .partition_point(|s| self.cmp_point(&seg.start, *s) == Ordering::Greater);
+        let hi = self
+            .status
+            .partition_point(|s| self.cmp_point(&seg.end, *s) != Ordering::Less);
+
+        for i in lo..hi.max(lo) {
+            self.check(v, self.status[i]);
+        }
+
+        self.verticals.push(v);
+    }
+
+    /// Return the range of the status with the segments that contain the
+    /// point.
+    fn equal_range(&self, pt: &PointF64) -> (usize, usize) {
+        let lo = self
+            .status
+            .partition_point(|s| self.cmp_point(pt, *s) == Ordering::Greater);
+
+        let hi = lo
+            + self.status[lo..]
+                .iter()
+                .take_while(|s| self.cmp_point(pt, **s) == Ordering::Equal)
+                .count();
+
+        (lo, hi)
+    }
+
+    /// Compare the point with the segment, a point is greater if it's above
+    /// the segment.
+    fn cmp_point(&self, pt: &PointF64, s: usize) -> Ordering {
+        let s = &self.segments[s];
+
+        match orient2d(&s.start, &s.end, pt).partial_cmp(&0.0) {
+            Some(Ordering::Greater) => Ordering::Greater,
+            Some(Ordering::Less) => Ordering::Less,
+            _ => {
+                if cmp_sweep(pt, &s.end) == Ordering::Greater {
+                    Ordering::Greater
+                } else if cmp_sweep(pt, &s.start) == Ordering::Less {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            }
+        }
+    }
+
+    /// Compare two segments that pass through the same point by their order
+    /// right after it.
+    fn cmp_after(&self, s: usize, t: usize) -> Ordering {
+        let (ss, ts) = (&self.segments[s], &self.segments[t]);
+
+        match orient2d(&ts.start, &ts.end, &ss.end).partial_cmp(&0.0) {
+            Some(Ordering::Less) => Ordering::Less,
+            Some(Ordering::Greater) => Ordering::Greater,
+            _ => s.cmp(&t),
+        }
+    }
+
+    /// Reorder the segments between the two crossing ones. All of them pass
+    /// through the crossing point and after it they're sorted by slope. Like
+    /// the segments touching an event point they might never be neighbors,
+    /// hence they're all checked against each other.
+    fn swap(&mut self, a: usize, b: usize) {
+        let (pa, pb) = match (self.position(a), self.position(b)) {
+            (Some(pa), Some(pb)) => (pa, pb),
+            _ => return,
+        };
+
+        let (lo, hi) = if pa < pb { (pa, pb) } else { (pb, pa) };
+
+        let mut span = self.status[lo..=hi].to_vec();
+        span.sort_by(|s, t| self.cmp_slope(*s, *t));
+        self.status.splice(lo..=hi, span);
+
+        for i in lo..=hi {
+            for j in i + 1..=hi {
+                self.check(self.status[i], self.status[j]);
+            }
+        }
+        if lo > 0 {
+            self.check(self.status[lo - 1], self.status[lo]);
+        }
+        if hi + 1 < self.status.len() {
+            self.check(self.status[hi], self.status[hi + 1]);
+        }
+    }
+
+    fn cmp_slope(&self, s: usize, t: usize) -> Ordering {
+        let (ss, ts) = (&self.segments[s], &self.segments[t]);
+
+        let origin = PointF64::new(0.0, 0.0);
+        let ds = PointF64::new(ss.end.x - ss.start.x, ss.end.y - ss.start.y);
+        let dt = PointF64::new(ts.end.x - ts.start.x, ts.end.y - ts.start.y);
+
+        match orient2d(&origin, &ds, &dt).partial_cmp(&0.0) {
+            Some(Ordering::Greater) => Ordering::Less,
+            Some(Ordering::Less) => Ordering::Greater,
+            _ => s.cmp(&t),
+        }
+    }
+
+    fn position(&self, s: usize) -> Option<usize> {
+        self.status.iter().position(|t| *t == s)
+    }
+
+    fn check(&mut self, a: usize, b: usize) {
+        let key = (a.min(b), a.max(b));
+        if a == b || !self.checked.insert(key) {
+            return;
+        }
+
+        // report the intersection of the original segments to keep the
+        // direction of the overlaps
+        let int = match self.input[key.0].intersection(&self.input[key.1]) {
+            None => return,
+            Some(int) => int,
+        };
+        self.found.push((key.0, key.1, int));
+
+        let (sa, sb) = (&self.segments[a], &self.segments[b]);
+        if !sa.crosses(sb) {
+            return;
+        }
+
+        if let Intersection::Point(pt) = int {
+            // rounding might put the crossing behind the sweep line or after
+            // the end of one of the segments
+            let first_end = if cmp_sweep(&sa.end, &sb.end) == Ordering::Less {
+                sa.end
+            } else {
+                sb.end
+            };
+            let pt = if cmp_sweep(&pt, &self.sweep) == Ordering::Less {
+                self.sweep
+            } else if cmp_sweep(&pt, &first_end) == Ordering::Greater {
+                first_end
+            } else {
+                pt
+            };
+
+            self.events.push(Reverse(Event::new(
+                pt,
+                EventKind::Cross(key.0, key.1),
+                key.0,
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn seg(x0: f64, y0: f64, x1: f64, y1: f64) -> Segment {
+        Segment::new(PointF64::new(x0, y0), PointF64::new(x1, y1))
+    }
+
+    #[test]
+    fn test_intersection() {
+        let s = seg(0.0, 0.0, 4.0, 4.0);
+
+        assert_eq!(
+            s.intersection(&seg(0.0, 4.0, 4.0, 0.0)),
+            Some(Intersection::Point(PointF64::new(2.0, 2.0)))
+        );
+        assert_eq!(s.intersection(&seg(1.0, 0.0, 5.0, 4.0)), None);
+        assert_eq!(s.intersection(&seg(3.0, 0.0, 4.0, 1.0)), None);
+
+        // touching
+        assert_eq!(
+            s.intersection(&seg(4.0, 4.0, 8.0, 0.0)),
+            Some(Intersection::Point(PointF64::new(4.0, 4.0)))
+        );
+        assert_eq!(
+            s.intersection(&seg(1.0, 1.0, 3.0, 0.0)),
+            Some(Intersection::Point(PointF64::new(1.0, 1.0)))
+        );
+
+        // collinear
+        assert_eq!(
+            s.intersection(&seg(6.0, 6.0, 2.0, 2.0)),
+            Some(Intersection::Overlap(seg(2.0, 2.0, 4.0, 4.0)))
+        );
+        assert_eq!(
+            s.intersection(&seg(4.0, 4.0, 6.0, 6.0)),
+            Some(Intersection::Point(PointF64::new(4.0, 4.0)))
+        );
+        assert_eq!(s.intersection(&seg(5.0, 5.0, 6.0, 6.0)), None);
+
+        // vertical and degenerate
+        assert_eq!(
+            seg(1.0, 0.0, 1.0, 5.0).intersection(&seg(1.0, 2.0, 1.0, 2.0)),
+            Some(Intersection::Point(PointF64::new(1.0, 2.0)))
+        );
+        assert_eq!(
+            seg(1.0, 0.0, 1.0, 5.0).intersection(&seg(1.0, 6.0, 1.0, 8.0)),
+            None
+        );
+        assert_eq!(s.intersection(&seg(2.0, 1.0, 2.0, 1.0)), None);
+    }
+
+    #[test]
+    fn test_closest_point() {
+        let s = seg(0.0, 0.0, 4.0, 0.0);
+
+        assert_eq!(s.project(&PointF64::new(1.0, 3.0)), 0.25);
+        assert_eq!(s.project(&PointF64::new(-4.0, 3.0)), -1.0);
+        assert_eq!(
+            s.closest_point(&PointF64::new(1.0, 3.0)),
+            PointF64::new(1.0, 0.0)
+        );
+        assert_eq!(
+            s.closest_point(&PointF64::new(-1.0, 3.0)),
+            PointF64::new(0.0, 0.0)
+        );
+        assert_eq!(
+            s.closest_point(&PointF64::new(7.0, 4.0)),
+            PointF64::new(4.0, 0.0)
+        );
+        assert_eq!(s.dist(&PointF64::new(7.0, 4.0)), 5.0);
+        assert_eq!(s.squared_dist(&PointF64::new(2.0, -2.0)), 4.0);
+
+        let p = seg(1.0, 1.0, 1.0, 1.0);
+        assert_eq!(p.project(&PointF64::new(7.0, 4.0)), 0.0);
+        assert_eq!(p.dist(&PointF64::new(4.0, 5.0)), 5.0);
+
+        assert!(s.contains(&PointF64::new(3.0, 0.0)));
+        assert!(!s.contains(&PointF64::new(5.0, 0.0)));
+    }
+
+    #[test]
+    fn test_intersections() {
+        let segments = [
+            seg(0.0, 0.0, 4.0, 4.0),
+            seg(0.0, 4.0, 4.0, 0.0),
+            seg(2.0, 0.0, 2.0, 4.0),
+            seg(5.0, 0.0, 6.0, 0.0),
+            seg(3.0, 3.0, 6.0, 6.0),
+        ];
+
+        let pairs = intersections(&segments)
+            .into_iter()
+            .map(|(i, j, _)| (i, j))
+            .collect::<Vec<_>>();
+
+        assert_eq!(pairs, vec![(0, 1), (0, 2), (0, 4), (1, 2)]);
+    }
+
+    #[test]
+    fn test_intersections_rounding() {
+        // -0 and 0 are the same point
+        let segments = [seg(0.0, -3.0, -0.0, -1.0), seg(-2.0, -2.0, 2.0, -3.0)];
+        assert_eq!(intersections(&segments), brute_force(&segments));
+
+        // the crossing of the almost vertical segment is rounded after its end
+        let x = -1.911379476325839;
+        let segments = [
+            seg(
+                -5.758313561530384,
+                4.494457625646411,
+                -1.7583135615303842,
+                -1.5055423743535896,
+            ),
+            seg(-1.9113794763258392, -1.0, x, -4.264471478082332),
+            seg(
+                x,
+                -4.264471478082332,
+                2.2882034957921156,
+                -2.864610487376347,
+            ),
+            seg(2.0, -2.9113794763258394, 4.0, -2.9113794763258394),
+        ];
+        assert_eq!(intersections(&segments), brute_force(&segments));
+    }
+
+    fn brute_force(segments: &[Segment]) -> Vec<(usize, usize, Intersection)> {
+        let mut found = vec![];
+        for (i, a) in segments.iter().enumerate() {
+            for (j, b) in segments.iter().enumerate().skip(i + 1) {
+                if let Some(int) = a.intersection(b) {
+                    found.push((i, j, int));
+                }
+            }
+        }
+
+        found
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(500))]
+        #[test]
+        fn prop_intersections_grid(
+            coords in prop::collection::vec((0_u8..8, 0_u8..8, 0_u8..8, 0_u8..8), 0..25)
+        ) {
+            // a small grid generates lots of collinear, touching and vertical
+            // segments
+            let segments = coords
+                .into_iter()
+                .map(|(x0, y0, x1, y1)| {
+                    seg(f64::from(x0), f64::from(y0), f64::from(x1), f64::from(y1))
+                })
+                .collect::<Vec<_>>();
+
+            prop_assert_eq!(intersections(&segments), brute_force(&segments));
+        }
+
+        #[test]
+        fn prop_intersections_random(
+            coords in prop::collection::vec((0.0..100.0, 0.0..100.0, 0.0..100.0, 0.0..100.0), 0..50)
+        ) {
+            let segments = coords
+                .into_iter()
+                .map(|(x0, y0, x1, y1)| seg(x0, y0, x1, y1))
+                .collect::<Vec<_>>();
+
+            let pairs = |v: Vec<(usize, usize, Intersection)>| {
+                v.into_iter().map(|(i, j, _)| (i, j)).collect::<Vec<_>>()
+            };
+
+            prop_assert_eq!(pairs(intersections(&segments)), pairs(brute_force(&segments)));
+        }
+    }
+}
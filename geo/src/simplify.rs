@@ -0,0 +1,203 @@
+//! Line simplification algorithms that remove the vertices that contribute the
+//! least to the shape of a polyline, e.g. to reduce the number of strokes of a
+//! plotter or to smooth noisy outlines.
+//!
+//! - [Ramer–Douglas–Peucker](https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm)
+//!   keeps the vertices that are farther than a distance from the simplified
+//!   line.
+//! - [Visvalingam–Whyatt](https://en.wikipedia.org/wiki/Visvalingam%E2%80%93Whyatt_algorithm)
+//!   removes the vertices that form the smallest triangles with their
+//!   neighbors until all of them are above an area.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::point::PointF64;
+use crate::segment::Segment;
+use crate::utils::OrdF64;
+
+/// Simplify the open polyline with the Ramer–Douglas–Peucker algorithm. The
+/// endpoints are always kept and every removed vertex is at most `epsilon`
+/// away from the simplified polyline.
+pub fn rdp(points: &[PointF64], epsilon: f64) -> Vec<PointF64> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let segment = Segment::new(points[start], points[end]);
+
+        let farthest = (start + 1..end)
+            .map(|i| (i, segment.dist(&points[i])))
+            .max_by(|(_, d1), (_, d2)| d1.total_cmp(d2));
+
+        if let Some((i, d)) = farthest {
+            if d > epsilon {
+                keep[i] = true;
+                ranges.push((start, i));
+                ranges.push((i, end));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(p, _)| *p)
+        .collect()
+}
+
+/// Simplify the open polyline with the Visvalingam–Whyatt algorithm. The
+/// endpoints are always kept while the other vertices are removed as long as
+/// the triangle they form with their neighbors has an area smaller than
+/// `min_area`.
+pub fn visvalingam(points: &[PointF64], min_area: f64) -> Vec<PointF64> {
+    visvalingam_with(points, min_area, false)
+}
+
+/// Implementation of `visvalingam` that can also simplify closed rings whose
+/// first and last points are the same, in that case every vertex can be
+/// removed, but at least 3 of them are kept.
+pub(crate) fn visvalingam_with(points: &[PointF64], min_area: f64, closed: bool) -> Vec<PointF64> {
+    let n = if closed {
+        points.len() - 1
+    } else {
+        points.len()
+    };
+    let min_len = if closed { 3 } else { 2 };
+    if n <= min_len {
+        return points.to_vec();
+    }
+
+    let mut prev = (0..n).map(|i| (i + n - 1) % n).collect::<Vec<_>>();
+    let mut next = (0..n).map(|i| (i + 1) % n).collect::<Vec<_>>();
+    let mut removed = vec![false; n];
+
+    let removable = |i: usize| closed || (i != 0 && i != n - 1);
+    let area = |prev: &[usize], next: &[usize], i: usize| {
+        let (a, b, c) = (points[prev[i]], points[i], points[next[i]]);
+        ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)).abs() / 2.0
+    };
+
+    // the heap might contain stale areas of vertices whose neighbors changed,
+    // they're skipped by checking the area again
+    let mut heap = (0..n)
+        .filter(|i| removable(*i))
+        .map(|i| Reverse((OrdF64(area(&prev, &next, i)), i)))
+        .collect::<BinaryHeap<_>>();
+
+    let mut len = n;
+    while let Some(Reverse((OrdF64(a), i))) = heap.pop() {
+        if len <= min_len || a >= min_area {
+            break;
+        }
+
+        if removed[i] || area(&prev, &next, i) != a {
+            continue;
+        }
+
+        removed[i] = true;
+        len -= 1;
+
+        let (p, q) = (prev[i], next[i]);
+        next[p] = q;
+        prev[q] = p;
+
+        for j in [p, q].iter().copied().filter(|j| removable(*j)) {
+            heap.push(Reverse((OrdF64(area(&prev, &next, j)), j)));
+        }
+    }
+
+    let mut simplified = points[..n]
+        .iter()
+        .zip(removed)
+        .filter(|(_, removed)| !*removed)
+        .map(|(p, _)| *p)
+        .collect::<Vec<_>>();
+
+    if closed {
+        simplified.push(simplified[0]);
+    }
+
+    simplified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn pts(coords: &[(f64, f64)]) -> Vec<PointF64> {
+        coords.iter().map(|(x, y)| PointF64::new(*x, *y)).collect()
+    }
+
+    #[test]
+    fn test_rdp() {
+        let line = pts(&[
+            (0.0, 0.0),
+            (1.0, 0.1),
+            (2.0, -0.1),
+            (3.0, 5.0),
+            (4.0, 6.0),
+            (5.0, 7.0),
+        ]);
+
+        assert_eq!(
+            rdp(&line, 1.0),
+            pts(&[(0.0, 0.0), (2.0, -0.1), (3.0, 5.0), (5.0, 7.0)])
+        );
+        assert_eq!(rdp(&line, 0.0), [&line[..4], &line[5..]].concat());
+        assert_eq!(rdp(&line, 100.0), pts(&[(0.0, 0.0), (5.0, 7.0)]));
+        assert_eq!(rdp(&line[..2], 1.0), line[..2].to_vec());
+    }
+
+    #[test]
+    fn test_visvalingam() {
+        let line = pts(&[(0.0, 0.0), (1.0, 0.1), (2.0, 0.0), (3.0, 3.0), (4.0, 0.0)]);
+
+        assert_eq!(
+            visvalingam(&line, 1.0),
+            pts(&[(0.0, 0.0), (2.0, 0.0), (3.0, 3.0), (4.0, 0.0)])
+        );
+        assert_eq!(visvalingam(&line, 0.0), line);
+        assert_eq!(visvalingam(&line, 100.0), pts(&[(0.0, 0.0), (4.0, 0.0)]));
+
+        // closed rings keep at least a triangle
+        let ring = pts(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (0.0, 0.0)]);
+        assert_eq!(visvalingam_with(&ring, 100.0, true).len(), 4);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_rdp_within_epsilon(
+            coords in prop::collection::vec((0.0..100.0, 0.0..100.0), 2..50),
+            epsilon in 0.0..20.0
+        ) {
+            let line = coords.into_iter().map(|(x, y)| PointF64::new(x, y)).collect::<Vec<_>>();
+            let simplified = rdp(&line, epsilon);
+
+            prop_assert_eq!(simplified[0], line[0]);
+            prop_assert_eq!(simplified[simplified.len() - 1], line[line.len() - 1]);
+
+            // every vertex is close to the segment of the simplified line that
+            // replaced it
+            let mut j = 0;
+            for p in &line {
+                if *p == simplified[j] {
+                    j = (j + 1).min(simplified.len() - 1);
+                    continue;
+                }
+
+                let s = Segment::new(simplified[j - 1], simplified[j]);
+                prop_assert!(s.dist(p) <= epsilon);
+            }
+        }
+    }
+}
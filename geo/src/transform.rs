@@ -0,0 +1,325 @@
+//! 2-D [affine transformations](https://en.wikipedia.org/wiki/Affine_transformation)
+//! like translations, rotations, scalings and skews and the shapes they can be
+//! applied to.
+
+use crate::bbox::BoundingBox;
+use crate::point::{Point, PointF64};
+use crate::polygon::Polygon;
+use crate::triangle::Triangle;
+
+/// An affine transformation stored as the matrix
+///
+/// ```text
+/// | a c e |
+/// | b d f |
+/// | 0 0 1 |
+/// ```
+///
+/// that maps the point (x, y) to (a x + c y + e, b x + d y + f), the same
+/// convention used by SVG and the canvas APIs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transform {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    /// Create a new transform from the coefficients of its matrix.
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        Transform { a, b, c, d, e, f }
+    }
+
+    /// Return the transform that leaves the points untouched.
+    pub fn identity() -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    /// Return the transform that moves the points by the given offsets.
+    pub fn translation(dx: f64, dy: f64) -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, dx, dy)
+    }
+
+    /// Return the transform that rotates the points around the origin by the
+    /// given angle in radians. The rotation goes from the x axis towards the y
+    /// axis, that is clockwise on an image where the y axis points down.
+    pub fn rotation(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Transform::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// Return the transform that scales the points from the origin by the
+    /// given factors.
+    pub fn scaling(sx: f64, sy: f64) -> Self {
+        Transform::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+    }
+
+    /// Return the transform that skews the points by the given angles in
+    /// radians along the x and y axis respectively.
+    pub fn skewing(ax: f64, ay: f64) -> Self {
+        Transform::new(1.0, ay.tan(), ax.tan(), 1.0, 0.0, 0.0)
+    }
+
+    /// Return the transform that applies `other` first and then this one.
+    pub fn compose(&self, other: &Transform) -> Self {
+        Transform::new(
+            self.a * other.a + self.c * other.b,
+            self.b * other.a + self.d * other.b,
+            self.a * other.c + self.c * other.d,
+            self.b * other.c + self.d * other.d,
+            self.a * other.e + self.c * other.f + self.e,
+            self.b * other.e + self.d * other.f + self.f,
+        )
+    }
+
+    /// Return the transform that applies this one and then translates the
+    /// points.
+    pub fn translate(&self, dx: f64, dy: f64) -> Self {
+        Transform::translation(dx, dy).compose(self)
+    }
+
+    /// Return the transform that applies this one and then rotates the points
+    /// around the origin.
+    pub fn rotate(&self, angle: f64) -> Self {
+        Transform::rotation(angle).compose(self)
+    }
+
+    /// Return the transform that applies this one and then rotates the points
+    /// around the given center.
+    pub fn rotate_around(&self, center: &PointF64, angle: f64) -> Self {
+        self.translate(-center.x, -center.y)
+            .rotate(angle)
+            .translate(center.x, center.y)
+    }
+
+    /// Return the transform that applies this one and then scales the points
+    /// from the origin.
+    pub fn scale(&self, sx: f64, sy: f64) -> Self {
+        Transform::scaling(sx, sy).compose(self)
+    }
+
+    /// Return the transform that applies this one and then skews the points.
+    pub fn skew(&self, ax: f64, ay: f64) -> Self {
+        Transform::skewing(ax, ay).compose(self)
+    }
+
+    /// Return the determinant of the linear part of the transform, that is the
+    /// factor areas are scaled by. It's negative if the transform mirrors the
+    /// shapes.
+    pub fn determinant(&self) -> f64 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Return whether this is the identity transform.
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::identity()
+    }
+
+    /// Return the transform that undoes this one, None if the transform
+    /// collapses the plane on a line or a point.
+    pub fn invert(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        Some(Transform::new(
+            self.d / det,
+            -self.b / det,
+            -self.c / det,
+            self.a / det,
+            (self.c * self.f - self.d * self.e) / det,
+            (self.b * self.e - self.a * self.f) / det,
+        ))
+    }
+
+    /// Apply the transform to the given point.
+    pub fn apply(&self, pt: &PointF64) -> PointF64 {
+        Point::new(
+            self.a * pt.x + self.c * pt.y + self.e,
+            self.b * pt.x + self.d * pt.y + self.f,
+        )
+    }
+}
+
+/// A shape that can be moved by a `Transform`.
+pub trait Transformable {
+    /// Return a copy of the shape with the transform applied.
+    fn transformed(&self, transform: &Transform) -> Self;
+}
+
+impl Transformable for Point<f64> {
+    fn transformed(&self, transform: &Transform) -> Self {
+        transform.apply(self)
+    }
+}
+
+impl Transformable for Triangle<f64> {
+    fn transformed(&self, transform: &Transform) -> Self {
+        let [p0, p1, p2] = &self.points;
+
+        Triangle::new(
+            transform.apply(p0),
+            transform.apply(p1),
+            transform.apply(p2),
+        )
+    }
+}
+
+impl Transformable for Polygon<f64> {
+    /// Note that the order of the vertices is kept, therefore transforms with
+    /// a negative determinant change the orientation of the polygon.
+    fn transformed(&self, transform: &Transform) -> Self {
+        Polygon::new(self.points().iter().map(|p| transform.apply(p))).unwrap()
+    }
+}
+
+impl Transformable for BoundingBox<f64> {
+    /// Return the bounding box of the transformed corners, rotations and skews
+    /// make it bigger than the box itself.
+    fn transformed(&self, transform: &Transform) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+
+        let corners = self
+            .points()
+            .iter()
+            .map(|p| transform.apply(p))
+            .collect::<Vec<_>>();
+
+        BoundingBox::from_points(&corners)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f64::consts::FRAC_PI_2;
+
+    fn assert_close(a: PointF64, b: PointF64) {
+        assert!(a.dist::<f64>(&b) < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_transform_point() {
+        let pt = PointF64::new(2.0, 1.0);
+
+        assert_eq!(Transform::identity().apply(&pt), pt);
+        assert_eq!(
+            Transform::translation(1.0, -1.0).apply(&pt),
+            PointF64::new(3.0, 0.0)
+        );
+        assert_eq!(
+            Transform::scaling(2.0, 3.0).apply(&pt),
+            PointF64::new(4.0, 3.0)
+        );
+        assert_close(
+            Transform::rotation(FRAC_PI_2).apply(&pt),
+            PointF64::new(-1.0, 2.0),
+        );
+        assert_close(
+            Transform::skewing(FRAC_PI_2 / 2.0, 0.0).apply(&pt),
+            PointF64::new(3.0, 1.0),
+        );
+        assert_close(
+            Transform::identity()
+                .rotate_around(&PointF64::new(1.0, 1.0), FRAC_PI_2)
+                .apply(&pt),
+            PointF64::new(1.0, 2.0),
+        );
+    }
+
+    #[test]
+    fn test_transform_compose() {
+        let pt = PointF64::new(2.0, 1.0);
+
+        // scale first and then translate
+        let t = Transform::scaling(2.0, 2.0).translate(1.0, 0.0);
+        assert_eq!(t.apply(&pt), PointF64::new(5.0, 2.0));
+        assert_eq!(
+            t,
+            Transform::translation(1.0, 0.0).compose(&Transform::scaling(2.0, 2.0))
+        );
+
+        // translate first and then scale
+        let t = Transform::translation(1.0, 0.0).scale(2.0, 2.0);
+        assert_eq!(t.apply(&pt), PointF64::new(6.0, 2.0));
+
+        assert_eq!(t.compose(&Transform::identity()), t);
+        assert_eq!(Transform::identity().compose(&t), t);
+        assert!(Transform::default().is_identity());
+        assert!(!t.is_identity());
+    }
+
+    #[test]
+    fn test_transform_invert() {
+        let t = Transform::rotation(0.3)
+            .scale(2.0, -0.5)
+            .skew(0.2, 0.1)
+            .translate(10.0, -3.0);
+        let inv = t.invert().unwrap();
+
+        let pt = PointF64::new(-4.0, 7.5);
+        assert_close(inv.apply(&t.apply(&pt)), pt);
+        assert_close(t.apply(&inv.apply(&pt)), pt);
+        assert!(inv.compose(&t).apply(&pt).dist::<f64>(&pt) < 1e-9);
+
+        assert_eq!(Transform::scaling(0.0, 1.0).invert(), None);
+        assert_eq!(Transform::scaling(2.0, 3.0).determinant(), 6.0);
+    }
+
+    #[test]
+    fn test_transform_shapes() {
+        let t = Transform::scaling(2.0, 2.0).translate(1.0, 1.0);
+
+        let triangle = Triangle::new(
+            PointF64::new(0.0, 0.0),
+            PointF64::new(1.0, 0.0),
+            PointF64::new(0.0, 1.0),
+        );
+        assert_eq!(
+            triangle.transformed(&t),
+            Triangle::new(
+                PointF64::new(1.0, 1.0),
+                PointF64::new(3.0, 1.0),
+                PointF64::new(1.0, 3.0),
+            )
+        );
+
+        let square =
+            Polygon::new(BoundingBox::from_dimensions(1.0, 1.0).points().to_vec()).unwrap();
+        let moved = square.transformed(&t);
+        assert_eq!(moved.points().len(), square.points().len());
+        assert_eq!(moved.area(), 4.0);
+        assert_eq!(
+            square
+                .transformed(&Transform::scaling(-1.0, 1.0))
+                .signed_area(),
+            -square.signed_area()
+        );
+
+        let bbox = BoundingBox::from_dimensions(2.0, 2.0);
+        assert_eq!(
+            bbox.transformed(&t),
+            BoundingBox::from_dimensions_and_origin(&PointF64::new(1.0, 1.0), 4.0, 4.0)
+        );
+
+        let rotated = bbox.transformed(&Transform::rotation(FRAC_PI_2 / 2.0));
+        assert!((rotated.width().unwrap() - 8.0_f64.sqrt()).abs() < 1e-9);
+
+        assert!(BoundingBox::<f64>::new().transformed(&t).is_empty());
+    }
+}
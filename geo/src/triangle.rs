@@ -5,6 +5,7 @@ use crate::point::Point;
 
 /// Simple Triangle shape.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Triangle<P> {
     /// The points of the triangle
     pub points: [Point<P>; 3],
@@ -122,7 +123,7 @@ where
 #[cfg(test)]
 mod test {
     use super::Triangle;
-    use geo::PointI32;
+    use crate::PointI32;
 
     #[test]
     fn test_triangle_circumcircle() {
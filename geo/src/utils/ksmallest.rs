@@ -79,8 +79,8 @@ mod test {
             let mut sorted = v.clone();
             sorted.sort();
 
-            for k in 0..v.len() {
-                assert_eq!(ksmallest(&mut v, k), Some(&sorted[k]));
+            for (k, expected) in sorted.iter().enumerate() {
+                assert_eq!(ksmallest(&mut v, k), Some(expected));
             }
 
             assert_eq!(sorted, v);
@@ -95,9 +95,9 @@ mod test {
             sorted.sort();
             sorted.reverse();
 
-            for k in 0..v.len() {
+            for (k, expected) in sorted.iter().enumerate() {
                 let v = ksmallest_by(&mut v, k, |l, r| r.cmp(l));
-                assert_eq!(v, Some(&sorted[k]));
+                assert_eq!(v, Some(expected));
             }
 
             assert_eq!(sorted, v);
@@ -63,7 +63,7 @@ pub mod ksmallest;
 pub mod ordwrapper;
 
 pub use self::ksmallest::{ksmallest, ksmallest_by, ksmallest_by_key};
-pub use self::ordwrapper::OrdWrapper;
+pub use self::ordwrapper::{OrdF64, OrdWrapper};
 
 #[cfg(test)]
 mod test {
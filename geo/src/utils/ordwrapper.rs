@@ -10,9 +10,14 @@ pub struct OrdWrapper<T, K> {
     key: K,
 }
 
-impl<T, K> Into<(T, K)> for OrdWrapper<T, K> {
-    fn into(self) -> (T, K) {
-        (self.data, self.key)
+/// A f64 with a total order so that it can be used as the key of an
+/// `OrdWrapper`, e.g. to keep the closest points in a heap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrdF64(pub f64);
+
+impl<T, K> From<OrdWrapper<T, K>> for (T, K) {
+    fn from(ow: OrdWrapper<T, K>) -> (T, K) {
+        (ow.data, ow.key)
     }
 }
 
@@ -52,3 +57,17 @@ impl<T, K: Ord> Ord for OrdWrapper<T, K> {
         self.key.cmp(&other.key)
     }
 }
+
+impl Eq for OrdF64 {}
+
+impl PartialOrd for OrdF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrdF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
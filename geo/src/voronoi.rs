@@ -0,0 +1,244 @@
+//! [Voronoi diagram](https://en.wikipedia.org/wiki/Voronoi_diagram) of a set
+//! of sites clipped to a bounding box.
+//!
+//! The diagram is the dual of the Delaunay triangulation: the cell of a site is
+//! the region closer to it than to any of its Delaunay neighbors. Therefore
+//! every cell is computed by clipping the bounding box with the half planes
+//! delimited by the bisectors between the site and its neighbors.
+
+use std::collections::HashMap;
+
+use crate::bbox::BoundingBox;
+use crate::delaunay::Triangulation;
+use crate::point::{Point, PointF64};
+use crate::polygon::Polygon;
+
+/// The Voronoi diagram of some sites.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Voronoi {
+    sites: Vec<PointF64>,
+    cells: Vec<Option<Polygon<f64>>>,
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl Voronoi {
+    /// Compute the Voronoi diagram of the given sites. The cells are clipped
+    /// to the bounding box. Duplicated sites share the same cell.
+    pub fn new(sites: &[PointF64], bbox: &BoundingBox<f64>) -> Self {
+        let neighbors = delaunay_neighbors(sites);
+
+        let cells = sites
+            .iter()
+            .enumerate()
+            .map(|(i, site)| {
+                let cell = neighbors[i].iter().fold(bbox.points().to_vec(), |cell, n| {
+                    clip(&cell, site, &sites[*n])
+                });
+
+                Polygon::new(cell)
+            })
+            .collect();
+
+        Voronoi {
+            sites: sites.to_vec(),
+            cells,
+            neighbors,
+        }
+    }
+
+    /// Return the sites of the diagram.
+    pub fn sites(&self) -> &[PointF64] {
+        &self.sites
+    }
+
+    /// Return the cell of every site in the same order of the sites. A cell is
+    /// None if it lies completely outside the bounding box.
+    pub fn cells(&self) -> &[Option<Polygon<f64>>] {
+        &self.cells
+    }
+
+    /// Return the cell of the i-th site.
+    pub fn cell(&self, i: usize) -> Option<&Polygon<f64>> {
+        self.cells[i].as_ref()
+    }
+
+    /// Return the indices of the sites whose cells share a border with the one
+    /// of the i-th site, before clipping to the bounding box. When four or
+    /// more sites lie on the same circle some of them might share just a
+    /// corner.
+    pub fn neighbors(&self, i: usize) -> &[usize] {
+        &self.neighbors[i]
+    }
+}
+
+/// Return the Delaunay neighbors of every site. Duplicated sites have the same
+/// neighbors of the first one.
+fn delaunay_neighbors(sites: &[PointF64]) -> Vec<Vec<usize>> {
+    let mut first = HashMap::new();
+    let unique = sites
+        .iter()
+        .enumerate()
+        .map(|(i, p)| *first.entry((p.x.to_bits(), p.y.to_bits())).or_insert(i))
+        .collect::<Vec<_>>();
+
+    let mut neighbors = vec![vec![]; sites.len()];
+
+    let triangulation = Triangulation::new(sites);
+    if triangulation.triangle_indices().is_empty() {
+        // all the sites are collinear and the cells are stripes, each one
+        // bordering the previous and the next site along the line
+        let mut sorted = (0..sites.len())
+            .filter(|i| unique[*i] == *i)
+            .collect::<Vec<_>>();
+        sorted.sort_by(|a, b| {
+            let (a, b) = (&sites[*a], &sites[*b]);
+            (a.x, a.y).partial_cmp(&(b.x, b.y)).unwrap()
+        });
+
+        for w in sorted.windows(2) {
+            neighbors[w[0]].push(w[1]);
+            neighbors[w[1]].push(w[0]);
+        }
+    } else {
+        // the triangulation might have kept any of the duplicates
+        for (a, b) in triangulation.edges() {
+            let (a, b) = (unique[a], unique[b]);
+
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+    }
+
+    for i in 0..sites.len() {
+        if unique[i] != i {
+            neighbors[i] = neighbors[unique[i]].clone();
+        }
+    }
+
+    neighbors
+}
+
+/// Clip the convex polygon with the half plane of the points that are closer
+/// to `site` than to `other`.
+fn clip(polygon: &[PointF64], site: &PointF64, other: &PointF64) -> Vec<PointF64> {
+    let mid = site.midpoint(other);
+    let (dx, dy) = (other.x - site.x, other.y - site.y);
+
+    // positive on the side of `other`
+    let side = |p: &PointF64| (p.x - mid.x) * dx + (p.y - mid.y) * dy;
+
+    let mut out = Vec::with_capacity(polygon.len() + 1);
+    for (i, cur) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let (sc, sn) = (side(cur), side(next));
+
+        if sc <= 0.0 {
+            out.push(*cur);
+        }
+
+        if (sc < 0.0 && sn > 0.0) || (sc > 0.0 && sn < 0.0) {
+            let t = sc / (sc - sn);
+            out.push(Point::new(
+                cur.x + (next.x - cur.x) * t,
+                cur.y + (next.y - cur.y) * t,
+            ));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_voronoi_quadrants() {
+        let bbox = BoundingBox::from_dimensions(4.0, 4.0);
+        let sites = [
+            PointF64::new(1.0, 1.0),
+            PointF64::new(3.0, 1.0),
+            PointF64::new(3.0, 3.0),
+            PointF64::new(1.0, 3.0),
+        ];
+
+        let voronoi = Voronoi::new(&sites, &bbox);
+
+        for (i, site) in sites.iter().enumerate() {
+            let cell = voronoi.cell(i).unwrap();
+
+            assert_eq!(cell.area(), 4.0);
+            assert!(cell.contains(site));
+        }
+
+        // the site in the opposite corner may or may not be a neighbor
+        assert!(voronoi.neighbors(0).contains(&1));
+        assert!(voronoi.neighbors(0).contains(&3));
+    }
+
+    #[test]
+    fn test_voronoi_degenerate() {
+        let bbox = BoundingBox::from_dimensions(6.0, 2.0);
+
+        let single = Voronoi::new(&[PointF64::new(1.0, 1.0)], &bbox);
+        assert_eq!(single.cell(0).unwrap().area(), 12.0);
+
+        // collinear and duplicated sites
+        let sites = [
+            PointF64::new(1.0, 1.0),
+            PointF64::new(3.0, 1.0),
+            PointF64::new(5.0, 1.0),
+            PointF64::new(3.0, 1.0),
+        ];
+        let voronoi = Voronoi::new(&sites, &bbox);
+
+        assert_eq!(
+            voronoi
+                .cells()
+                .iter()
+                .map(|c| c.as_ref().unwrap().area())
+                .collect::<Vec<_>>(),
+            vec![4.0, 4.0, 4.0, 4.0]
+        );
+
+        let outside = Voronoi::new(&[PointF64::new(1.0, 1.0), PointF64::new(1.0, 10.0)], &bbox);
+        assert!(outside.cell(1).is_none());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_voronoi_cells_partition_the_box(
+            sites in prop::collection::vec((0.0..100.0, 0.0..100.0), 1..60),
+            queries in prop::collection::vec((0.0..100.0, 0.0..100.0), 10),
+        ) {
+            let bbox = BoundingBox::from_dimensions(100.0, 100.0);
+            let sites = sites
+                .into_iter()
+                .map(|(x, y)| PointF64::new(x, y))
+                .collect::<Vec<_>>();
+
+            let voronoi = Voronoi::new(&sites, &bbox);
+
+            let total = voronoi.cells().iter().flatten().map(Polygon::area).sum::<f64>();
+            prop_assert!((total - 100.0 * 100.0).abs() < 1e-6);
+
+            // every point belongs to the cell of the closest site
+            for (x, y) in queries {
+                let q = PointF64::new(x, y);
+                let closest = (0..sites.len())
+                    .min_by(|a, b| {
+                        let da: f64 = sites[*a].squared_dist(&q);
+                        let db: f64 = sites[*b].squared_dist(&q);
+                        da.partial_cmp(&db).unwrap()
+                    })
+                    .unwrap();
+
+                prop_assert!(voronoi.cell(closest).unwrap().contains(&q));
+            }
+        }
+    }
+}
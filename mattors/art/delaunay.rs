@@ -1,26 +1,57 @@
 //! Generate some triangly art using Delaunay triangulation.
 
-use geo::{delaunay, BoundingBox, PointF64, PointU32};
+use std::str::FromStr;
+
+use geo::predicates::orient2d;
+use geo::{delaunay, BoundingBox, PointF64, PointU32, Triangle};
 use rand::Rng;
 
-use crate::color::{random_color, RandomColorConfig};
+use crate::art::edges;
+use crate::art::lloyd;
+use crate::art::poisson::{self, PointDistribution};
 use crate::drawing;
 
-/// Generate a random triangulation and draws it onto the given image. The
-/// points are generated randomly but the image is divided into a grid and each
-/// point is contained in a cell.
-pub fn random_triangulation<R: Rng>(
+/// How the triangles of a low poly image are colored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriangleColor {
+    /// The mean color of the source pixels covered by the triangle.
+    Mean,
+
+    /// The color of the source pixel under the centroid of the triangle.
+    Centroid,
+}
+
+/// Generate a random triangulation and draws it onto the given image. With
+/// the uniform distribution the points are generated randomly but the image is
+/// divided into a grid and each point is contained in a cell, while with the
+/// Poisson one about `grid_size * grid_size` evenly spaced points are used.
+/// The points are then spread by `relax_iterations` steps of Lloyd's
+/// relaxation. Each triangle is filled with the pixel returned by
+/// `random_pixel`.
+pub fn random_triangulation(
     img: &mut image::RgbaImage,
-    color_config: &mut RandomColorConfig<R>,
     grid_size: u32,
-    alpha: u8,
+    distribution: PointDistribution,
+    relax_iterations: usize,
+    mut random_pixel: impl FnMut() -> image::Rgba<u8>,
 ) {
-    let points = random_points_in_grid(img.width(), img.height(), grid_size);
+    let bbox = BoundingBox::from_dimensions(f64::from(img.width()), f64::from(img.height()));
 
-    let triangles = delaunay::triangulate(
-        &BoundingBox::from_dimensions(f64::from(img.width()), f64::from(img.height())),
-        points,
-    );
+    let mut points = match distribution {
+        PointDistribution::Uniform => random_points_in_grid(img.width(), img.height(), grid_size),
+        PointDistribution::Poisson => poisson::sample(
+            &mut rand::thread_rng(),
+            distribution,
+            grid_size as usize * grid_size as usize,
+            &bbox,
+        ),
+    };
+
+    if relax_iterations > 0 {
+        lloyd::relax(&mut points, &bbox, relax_iterations);
+    }
+
+    let triangles = delaunay::triangulate(&bbox, points);
 
     {
         let mut drawer = drawing::Drawer::new_with_no_blending(img);
@@ -32,11 +63,111 @@ pub fn random_triangulation<R: Rng>(
             let p2 = PointU32::new(p2.x.ceil() as u32, p2.y.ceil() as u32);
             let p3 = PointU32::new(p3.x.ceil() as u32, p3.y.ceil() as u32);
 
-            let pix = image::Rgba(random_color(color_config).to_rgba(alpha));
+            drawer.triangle(p1, p2, p3, &random_pixel());
+        }
+    }
+}
+
+/// Turn the given image into a low poly one made of about `npoints`
+/// triangles' vertices. The `edge_ratio` fraction of the points is placed on
+/// the edges of the image so that the triangles follow its details, the rest
+/// is scattered randomly.
+pub fn low_poly(
+    img: &image::RgbImage,
+    npoints: usize,
+    edge_ratio: f64,
+    color: TriangleColor,
+) -> image::RgbImage {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return image::RgbImage::new(width, height);
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let nedges = (npoints as f64 * edge_ratio.clamp(0.0, 1.0)).round() as usize;
 
-            drawer.triangle(p1, p2, p3, &pix);
+    let edge_map = edges::sobel(&image::imageops::grayscale(img));
+    let mut points = edges::sample_edge_points(&mut rng, &edge_map, nedges);
+
+    let nrandom = npoints - points.len();
+    points.extend((0..nrandom).map(|_| {
+        PointF64::new(
+            rng.gen::<f64>() * f64::from(width),
+            rng.gen::<f64>() * f64::from(height),
+        )
+    }));
+
+    let bbox = BoundingBox::from_dimensions(f64::from(width), f64::from(height));
+    let mut out = image::RgbImage::new(width, height);
+
+    for triangle in delaunay::triangulate(&bbox, points) {
+        let pixels = triangle_pixels(&triangle, width, height);
+
+        let centroid = || {
+            let [a, b, c] = triangle.points;
+            let x = ((a.x + b.x + c.x) / 3.0).clamp(0.0, f64::from(width - 1));
+            let y = ((a.y + b.y + c.y) / 3.0).clamp(0.0, f64::from(height - 1));
+
+            *img.get_pixel(x as u32, y as u32)
+        };
+
+        let pix = match color {
+            TriangleColor::Centroid => centroid(),
+            TriangleColor::Mean if pixels.is_empty() => centroid(),
+            TriangleColor::Mean => {
+                let mut sum = [0_u64; 3];
+                for (x, y) in &pixels {
+                    let p = img.get_pixel(*x, *y);
+                    for (s, c) in sum.iter_mut().zip(p.0.iter()) {
+                        *s += u64::from(*c);
+                    }
+                }
+
+                let n = pixels.len() as u64;
+                image::Rgb([(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8])
+            }
+        };
+
+        for (x, y) in pixels {
+            out.put_pixel(x, y, pix);
         }
     }
+
+    out
+}
+
+/// Return the pixels whose center lies inside or on the border of the
+/// triangle. Since the test is exact, triangles that share an edge never leave
+/// a gap between them.
+fn triangle_pixels(triangle: &Triangle<f64>, width: u32, height: u32) -> Vec<(u32, u32)> {
+    let [mut a, b, mut c] = triangle.points;
+    if orient2d(&a, &b, &c) < 0.0 {
+        std::mem::swap(&mut a, &mut c);
+    }
+
+    let bbox = BoundingBox::from_points(&triangle.points);
+    let xmin = (bbox.min().x - 0.5).ceil().max(0.0) as u32;
+    let ymin = (bbox.min().y - 0.5).ceil().max(0.0) as u32;
+    let xmax = (bbox.max().x - 0.5).floor().min(f64::from(width) - 1.0);
+    let ymax = (bbox.max().y - 0.5).floor().min(f64::from(height) - 1.0);
+
+    let mut pixels = vec![];
+
+    for y in (ymin..).take_while(|y| f64::from(*y) <= ymax) {
+        for x in (xmin..).take_while(|x| f64::from(*x) <= xmax) {
+            let p = PointF64::new(f64::from(x) + 0.5, f64::from(y) + 0.5);
+
+            if orient2d(&a, &b, &p) >= 0.0
+                && orient2d(&b, &c, &p) >= 0.0
+                && orient2d(&c, &a, &p) >= 0.0
+            {
+                pixels.push((x, y));
+            }
+        }
+    }
+
+    pixels
 }
 
 fn random_points_in_grid(width: u32, height: u32, grid_size: u32) -> Vec<PointF64> {
@@ -67,3 +198,83 @@ fn random_points_in_grid(width: u32, height: u32, grid_size: u32) -> Vec<PointF6
 
     out
 }
+
+impl FromStr for TriangleColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mean" => Ok(TriangleColor::Mean),
+            "centroid" => Ok(TriangleColor::Centroid),
+            _ => Err(format!(
+                "unknown triangle color {}, please use mean or centroid",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triangle_pixels() {
+        let upper = Triangle::new(
+            PointF64::new(0.0, 0.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(0.0, 4.0),
+        );
+        let lower = Triangle::new(
+            PointF64::new(4.0, 4.0),
+            PointF64::new(4.0, 0.0),
+            PointF64::new(0.0, 4.0),
+        );
+
+        let mut covered = triangle_pixels(&upper, 4, 4);
+        assert_eq!(covered.len(), 10);
+        assert!(covered.contains(&(0, 0)) && covered.contains(&(3, 0)));
+
+        // the diagonal pixels are shared and nothing is left out
+        covered.extend(triangle_pixels(&lower, 4, 4));
+        covered.sort();
+        covered.dedup();
+        assert_eq!(covered.len(), 16);
+
+        // pixels outside the image are skipped
+        assert_eq!(triangle_pixels(&upper, 3, 3).len(), 8);
+    }
+
+    #[test]
+    fn test_low_poly() {
+        let img = image::RgbImage::from_fn(20, 10, |x, _| {
+            if x < 10 {
+                image::Rgb([200, 0, 0])
+            } else {
+                image::Rgb([0, 0, 200])
+            }
+        });
+
+        for color in [TriangleColor::Mean, TriangleColor::Centroid].iter() {
+            let out = low_poly(&img, 30, 0.5, *color);
+
+            assert_eq!(out.dimensions(), img.dimensions());
+
+            // the flat areas far from the edge keep, mostly, their colors
+            let left = out.get_pixel(0, 5);
+            let right = out.get_pixel(19, 5);
+            assert!(left[0] > left[2] && left[1] == 0);
+            assert!(right[2] > right[0] && right[1] == 0);
+        }
+    }
+
+    #[test]
+    fn test_low_poly_empty_image() {
+        for &(w, h) in &[(0, 0), (0, 10), (10, 0)] {
+            let img = image::RgbImage::new(w, h);
+            let out = low_poly(&img, 50, 0.5, TriangleColor::Centroid);
+
+            assert_eq!(out.dimensions(), (w, h));
+        }
+    }
+}
@@ -6,7 +6,7 @@ use num::traits::{AsPrimitive, Bounded};
 /// Perform [Floyd–Steinberg_dithering][0] over a binary image.
 ///
 /// 0: https://en.wikipedia.org/wiki/Floyd%E2%80%93Steinberg_dithering
-pub fn dither<I: GenericImageView>(
+pub fn dither<I>(
     img: &I,
     mut closest: impl FnMut(&I::Pixel) -> I::Pixel,
 ) -> ImageBuffer<I::Pixel, Vec<<I::Pixel as Pixel>::Subpixel>>
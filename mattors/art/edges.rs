@@ -0,0 +1,110 @@
+//! Simple edge detection. The edges are used to decide where the details of an
+//! image are so that the art modules can spend more effort there.
+
+use rand::Rng;
+
+use geo::PointF64;
+
+/// Return the magnitude of the gradient of the image computed with the
+/// [Sobel operator](https://en.wikipedia.org/wiki/Sobel_operator). The pixels
+/// outside the image are assumed to be equal to the closest border pixel and
+/// the magnitudes bigger than 255 are clamped.
+pub fn sobel(img: &image::GrayImage) -> image::GrayImage {
+    let (width, height) = img.dimensions();
+
+    let at = |x: i64, y: i64| {
+        let x = x.max(0).min(i64::from(width) - 1) as u32;
+        let y = y.max(0).min(i64::from(height) - 1) as u32;
+
+        f64::from(img.get_pixel(x, y)[0])
+    };
+
+    image::GrayImage::from_fn(width, height, |x, y| {
+        let (x, y) = (i64::from(x), i64::from(y));
+
+        let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+            - at(x - 1, y - 1)
+            - 2.0 * at(x - 1, y)
+            - at(x - 1, y + 1);
+        let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+            - at(x - 1, y - 1)
+            - 2.0 * at(x, y - 1)
+            - at(x + 1, y - 1);
+
+        image::Luma([gx.hypot(gy).round().min(255.0) as u8])
+    })
+}
+
+/// Return `n` random points where the probability of picking a pixel is
+/// proportional to its value in the given edge map. The points are spread
+/// uniformly inside the picked pixels. If the map is completely black there
+/// are no edges and no points are returned.
+pub fn sample_edge_points<R: Rng>(
+    rng: &mut R,
+    edges: &image::GrayImage,
+    n: usize,
+) -> Vec<PointF64> {
+    let cumulative = edges
+        .pixels()
+        .scan(0_u64, |sum, p| {
+            *sum += u64::from(p[0]);
+            Some(*sum)
+        })
+        .collect::<Vec<_>>();
+
+    let total = cumulative.last().copied().unwrap_or(0);
+    if total == 0 {
+        return vec![];
+    }
+
+    (0..n)
+        .map(|_| {
+            let target = rng.gen_range(0, total);
+            let i = cumulative.partition_point(|c| *c <= target) as u32;
+
+            PointF64::new(
+                f64::from(i % edges.width()) + rng.gen::<f64>(),
+                f64::from(i / edges.width()) + rng.gen::<f64>(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sobel() {
+        let flat = image::GrayImage::from_pixel(4, 4, image::Luma([100]));
+        assert!(sobel(&flat).pixels().all(|p| p[0] == 0));
+
+        // a vertical edge between the 2nd and the 3rd column
+        let img = image::GrayImage::from_fn(4, 3, |x, _| image::Luma([if x < 2 { 0 } else { 50 }]));
+        let edges = sobel(&img);
+
+        for y in 0..3 {
+            let row = (0..4).map(|x| edges.get_pixel(x, y)[0]).collect::<Vec<_>>();
+            assert_eq!(row, vec![0, 200, 200, 0]);
+        }
+    }
+
+    #[test]
+    fn test_sample_edge_points() {
+        let mut rng = rand::thread_rng();
+
+        let flat = image::GrayImage::from_pixel(4, 4, image::Luma([0]));
+        assert!(sample_edge_points(&mut rng, &flat, 10).is_empty());
+
+        // only the pixel at (2, 1) is an edge
+        let edges = image::GrayImage::from_fn(4, 3, |x, y| {
+            image::Luma([if (x, y) == (2, 1) { 9 } else { 0 }])
+        });
+        let points = sample_edge_points(&mut rng, &edges, 20);
+
+        assert_eq!(points.len(), 20);
+        assert!(points
+            .iter()
+            .all(|p| p.x >= 2.0 && p.x < 3.0 && p.y >= 1.0 && p.y < 2.0));
+    }
+}
@@ -3,7 +3,7 @@
 use std::f64;
 use std::fmt::Debug;
 
-use geo::PointU32;
+use geo::{PointU32, Transform};
 
 use crate::drawing;
 
@@ -27,43 +27,60 @@ pub fn fractal_tree<I>(
     I::Pixel: Debug,
     f64: From<<I::Pixel as image::Pixel>::Subpixel>,
 {
-    if nbranches == 0 {
-        return;
-    }
-
-    let breakpoint = {
-        let x =
-            (<f64 as From<u32>>::from(pt.x) + branching_angle.cos() * branch_len).max(0.0) as u32;
-        let y =
-            (<f64 as From<u32>>::from(pt.y) + branching_angle.sin() * branch_len).max(0.0) as u32;
+    let mut drawer = drawing::Drawer::new_with_no_blending(img);
 
-        PointU32::new(x, y)
-    };
+    // every branch is drawn along the x axis of its own coordinate system
+    // that starts at the tip of its parent
+    drawer.push_transform(&Transform::rotation(branching_angle).translate(
+        <f64 as From<u32>>::from(pt.x),
+        <f64 as From<u32>>::from(pt.y),
+    ));
 
-    {
-        let mut drawer = drawing::Drawer::new_with_no_blending(img);
-        drawer.antialiased_line(pt, breakpoint, pix);
-    }
-
-    fractal_tree(
-        img,
-        nbranches - 1,
-        breakpoint,
-        branching_angle + branching_angle_step,
+    branch(
+        &mut drawer,
+        nbranches,
         branching_angle_step,
-        branch_len * branch_len_factor,
+        branch_len,
         branch_len_factor,
         pix,
     );
+}
 
-    fractal_tree(
-        img,
-        nbranches - 1,
-        breakpoint,
-        branching_angle - branching_angle_step,
-        branching_angle_step,
-        branch_len * branch_len_factor,
-        branch_len_factor,
-        pix,
-    );
+fn branch<I, B>(
+    drawer: &mut drawing::Drawer<I, B>,
+    nbranches: u32,
+    branching_angle_step: f64,
+    branch_len: f64,
+    branch_len_factor: f64,
+    pix: &I::Pixel,
+) where
+    I: image::GenericImage,
+    I::Pixel: Debug,
+    B: drawing::Blender<I::Pixel>,
+    f64: From<<I::Pixel as image::Pixel>::Subpixel>,
+{
+    if nbranches == 0 {
+        return;
+    }
+
+    // only the drawn line is rounded, the children start at the exact tip so
+    // that the rounding errors do not add up and short branches still move
+    // their children.
+    let len = branch_len.round() as u32;
+    drawer.antialiased_line(PointU32::new(0, 0), PointU32::new(len, 0), pix);
+
+    for angle in &[branching_angle_step, -branching_angle_step] {
+        drawer.push_transform(&Transform::rotation(*angle).translate(branch_len, 0.0));
+
+        branch(
+            drawer,
+            nbranches - 1,
+            branching_angle_step,
+            branch_len * branch_len_factor,
+            branch_len_factor,
+            pix,
+        );
+
+        drawer.pop_transform();
+    }
 }
@@ -8,6 +8,8 @@ use num::complex::Complex64;
 
 use geo::PointF64;
 
+use crate::color::Theme;
+
 /// This struct is mainly used to pass some data used when converting to raw
 /// pixels.
 #[derive(Debug)]
@@ -68,6 +70,16 @@ impl FractalPoint {
             ]
         }
     }
+
+    fn to_themed_pixels(&self, theme: &Theme, max_iterations: u32) -> Vec<u8> {
+        let pix = if self.is_inside {
+            theme.background()
+        } else {
+            theme.gradient(f64::from(self.iterations) / f64::from(max_iterations.max(1)))
+        };
+
+        pix.0.to_vec()
+    }
 }
 
 /// Iterator that returns all the `FractalPoint`
@@ -122,6 +134,26 @@ impl<F: Fn(Complex64, u32) -> FractalPoint> JuliaGenIter<F> {
 
         image::ImageBuffer::from_raw(width, height, self.flat_map(|pt| pt.to_pixels()).collect())
     }
+
+    /// Consume the `JuliaGenIter` and return an image of the Julia set colored
+    /// with the given `Theme`: the points inside the set are painted with the
+    /// background while the others follow the palette gradient according to
+    /// how fast they escape.
+    pub fn into_themed_image(
+        self,
+        theme: &Theme,
+    ) -> Option<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>> {
+        let width = self.xcount;
+        let height = self.ycount;
+        let iterations = self.iterations;
+
+        image::ImageBuffer::from_raw(
+            width,
+            height,
+            self.flat_map(|pt| pt.to_themed_pixels(theme, iterations))
+                .collect(),
+        )
+    }
 }
 
 impl<F: Fn(Complex64, u32) -> FractalPoint> Iterator for JuliaGenIter<F> {
@@ -153,17 +185,36 @@ mod tests {
 
     #[test]
     fn sanity() {
-        assert_eq!(
-            FractalPoint::mandelbrot(Complex64::new(0.0, 0.0), 128).is_inside,
-            true
-        );
-        assert_eq!(
-            FractalPoint::mandelbrot(Complex64::new(-1.0, 0.0), 64).is_inside,
-            true
-        );
-        assert_eq!(
-            FractalPoint::mandelbrot(Complex64::new(1.0, 0.0), 12).is_inside,
-            false
-        );
+        assert!(FractalPoint::mandelbrot(Complex64::new(0.0, 0.0), 128).is_inside);
+        assert!(FractalPoint::mandelbrot(Complex64::new(-1.0, 0.0), 64).is_inside);
+        assert!(!FractalPoint::mandelbrot(Complex64::new(1.0, 0.0), 12).is_inside);
+    }
+
+    #[test]
+    fn test_themed_image() {
+        let theme = Theme::new(
+            image::Rgb([1, 2, 3]),
+            vec![image::Rgb([0, 0, 0]), image::Rgb([255, 255, 255])],
+            None,
+        )
+        .unwrap();
+
+        let img = JuliaGenIter::new(
+            PointF64::new(-2.0, -1.0),
+            3,
+            2,
+            1.0,
+            1.0,
+            64,
+            FractalPoint::mandelbrot,
+        )
+        .into_themed_image(&theme)
+        .unwrap();
+
+        assert_eq!(img.dimensions(), (3, 2));
+
+        // (0, 0) is inside the set while (1, 0) escapes right away.
+        assert_eq!(*img.get_pixel(2, 1), image::Rgb([1, 2, 3]));
+        assert_eq!(*img.get_pixel(1, 0), theme.gradient(1.0 / 64.0));
     }
 }
@@ -0,0 +1,72 @@
+//! [Lloyd's relaxation](https://en.wikipedia.org/wiki/Lloyd%27s_algorithm)
+//! that repeatedly moves every site to the centroid of its Voronoi cell. Random
+//! sites become evenly spaced while still looking organic.
+
+use geo::voronoi::Voronoi;
+use geo::{BoundingBox, PointF64, Polygon};
+
+/// Stop relaxing when no site moved more than this distance.
+const MIN_MOVEMENT: f64 = 0.1;
+
+/// Relax the sites in the bounding box for the given number of iterations or
+/// until they stop moving. Return the Voronoi diagram of the relaxed sites.
+pub fn relax(sites: &mut [PointF64], bbox: &BoundingBox<f64>, iterations: usize) -> Voronoi {
+    relax_with(sites, bbox, iterations, Polygon::centroid)
+}
+
+/// Like `relax`, but the centroid of every cell is computed by the given
+/// function which allows to weight the cells by some density. The sites whose
+/// centroid is None are not moved.
+pub fn relax_with(
+    sites: &mut [PointF64],
+    bbox: &BoundingBox<f64>,
+    iterations: usize,
+    centroid: impl Fn(&Polygon<f64>) -> Option<PointF64>,
+) -> Voronoi {
+    let mut voronoi = Voronoi::new(sites, bbox);
+
+    for _ in 0..iterations {
+        let mut moved = 0.0_f64;
+
+        for (site, cell) in sites.iter_mut().zip(voronoi.cells()) {
+            if let Some(c) = cell.as_ref().and_then(&centroid) {
+                moved = moved.max(site.squared_dist(&c));
+                *site = c;
+            }
+        }
+
+        voronoi = Voronoi::new(sites, bbox);
+
+        if moved < MIN_MOVEMENT * MIN_MOVEMENT {
+            break;
+        }
+    }
+
+    voronoi
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relax() {
+        let bbox = BoundingBox::from_dimensions(4.0, 1.0);
+
+        // two clumped sites end up in the middle of the two halves
+        let mut sites = vec![PointF64::new(0.1, 0.5), PointF64::new(0.2, 0.5)];
+        relax(&mut sites, &bbox, 100);
+
+        assert!(sites[0].dist::<f64>(&PointF64::new(1.0, 0.5)) < MIN_MOVEMENT * 2.0);
+        assert!(sites[1].dist::<f64>(&PointF64::new(3.0, 0.5)) < MIN_MOVEMENT * 2.0);
+
+        // no iterations, no changes
+        let mut sites = vec![PointF64::new(0.1, 0.5), PointF64::new(0.2, 0.5)];
+        let voronoi = relax(&mut sites, &bbox, 0);
+        assert_eq!(
+            sites,
+            vec![PointF64::new(0.1, 0.5), PointF64::new(0.2, 0.5)]
+        );
+        assert_eq!(voronoi.sites(), &sites[..]);
+    }
+}
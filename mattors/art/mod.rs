@@ -1,6 +1,7 @@
 //! This module contains the code to generate the images
 
 use std::collections::HashSet;
+use std::convert::TryFrom;
 
 use rand::Rng;
 
@@ -51,7 +52,8 @@ pub fn random_bbox_subdivisions<R: Rng>(
     boxes.into_iter().chain(small_bboxes)
 }
 
-/// Generate n distinct random points in bbox.
+/// Generate n distinct random points in bbox. If the box has less than n
+/// points then all of them are returned.
 pub fn generate_distinct_random_points<R: Rng>(
     rng: &mut R,
     n: usize,
@@ -59,7 +61,13 @@ pub fn generate_distinct_random_points<R: Rng>(
 ) -> HashSet<PointU32> {
     let mut points = HashSet::new();
 
-    // TODO: if n > number of points in bbox panic!
+    let available = if bbox.is_empty() {
+        0
+    } else {
+        u64::from(bbox.max().x - bbox.min().x) * u64::from(bbox.max().y - bbox.min().y)
+    };
+    let n = n.min(usize::try_from(available).unwrap_or(usize::MAX));
+
     // TODO: if n is high it's probably faster to generate all the points and
     // shuffle the array.
     while points.len() < n {
@@ -80,14 +88,37 @@ pub fn random_point_in_bbox<R: Rng>(rng: &mut R, bbox: &BoundingBox<u32>) -> Poi
 pub mod delaunay;
 pub mod dithering;
 pub mod dragon;
+pub mod edges;
 pub mod fractree;
 pub mod julia;
+pub mod lloyd;
 pub mod mondrian;
+pub mod palette;
 pub mod patchwork;
+pub mod poisson;
 pub mod primi;
+pub mod quadtree;
 pub mod quantize;
 pub mod runes;
 pub mod sierpinski;
 pub mod stippling;
 pub mod tangled_web;
 pub mod voronoi;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_distinct_random_points() {
+        let mut rng = rand::thread_rng();
+        let bbox = BoundingBox::from_dimensions(3, 2);
+
+        assert_eq!(generate_distinct_random_points(&mut rng, 4, &bbox).len(), 4);
+
+        // there are only 6 points in the box
+        let all = generate_distinct_random_points(&mut rng, 100, &bbox);
+        assert_eq!(all.len(), 6);
+        assert!(all.iter().all(|p| p.x < 3 && p.y < 2));
+    }
+}
@@ -15,6 +15,7 @@ pub fn generate(
     minimum_area: u32,
     white: image::Rgb<u8>,
     fill_palette: &[image::Rgb<u8>],
+    border: image::Rgb<u8>,
     border_thickness: u32,
 ) {
     let mut rng = rand::thread_rng();
@@ -33,7 +34,7 @@ pub fn generate(
 
     let mut draw_rect = |rect, pix| {
         drawer.rect(rect, &pix);
-        draw_borders(&mut drawer, rect, border_thickness);
+        draw_borders(&mut drawer, rect, border, border_thickness);
     };
 
     for rect in &rects {
@@ -55,6 +56,7 @@ pub fn generate(
 fn draw_borders(
     drawer: &mut Drawer<image::RgbImage, NoopBlender>,
     rect: &BoundingBox<u32>,
+    border: image::Rgb<u8>,
     border_thickness: u32,
 ) {
     let (width, height) = drawer.dimensions();
@@ -103,7 +105,7 @@ fn draw_borders(
         ),
     ];
 
-    for border in &borders {
-        drawer.rect(border, &image::Rgb([0, 0, 0]));
+    for border_rect in &borders {
+        drawer.rect(border_rect, &border);
     }
 }
@@ -0,0 +1,211 @@
+//! Extract a palette of dominant colors from an image. The colors are found by
+//! quantizing the image with [Median Cut](https://en.wikipedia.org/wiki/Median_cut)
+//! and by keeping the quantized colors that cover most pixels.
+
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use geo::kdtree::KdTree;
+use geo::utils::build_hashmap_counter;
+
+use crate::art::quantize::{quantize, PixelFreq};
+use crate::color::{luminance, Hsv};
+
+/// The order in which the colors of a palette are sorted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteSort {
+    /// The most frequent colors come first.
+    Frequency,
+
+    /// Sort by increasing hue.
+    Hue,
+
+    /// Sort from the darkest color to the lightest.
+    Luminance,
+}
+
+/// Extract at most `ncolors` dominant colors from the given pixels. Each color
+/// is returned along with the number of pixels it represents. The palette might
+/// contain less colors than requested if the pixels aren't colorful enough.
+pub fn extract_palette<I>(
+    pixels: I,
+    ncolors: usize,
+    sort: PaletteSort,
+) -> Vec<PixelFreq<image::Rgb<u8>>>
+where
+    I: Iterator<Item = image::Rgb<u8>>,
+{
+    if ncolors == 0 {
+        return vec![];
+    }
+
+    let pixels = pixels.collect::<Vec<_>>();
+
+    // Median Cut always returns a power of two colors, find the smallest
+    // power that is at least as big as the number of requested colors and
+    // then keep only the most frequent ones.
+    let divide_steps = (ncolors as f64).log2().ceil() as u32;
+    let quantized = quantize(pixels.iter().cloned(), divide_steps);
+
+    let mut palette = build_hashmap_counter(pixels.iter().map(|p| quantized.quantized_pixels[p]))
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    sort_palette(&mut palette, PaletteSort::Frequency);
+    palette.truncate(ncolors);
+    sort_palette(&mut palette, sort);
+
+    palette
+}
+
+/// Sort the given palette according to `sort`. Ties are broken by looking at
+/// the channels so that the order is always deterministic.
+pub fn sort_palette(palette: &mut [PixelFreq<image::Rgb<u8>>], sort: PaletteSort) {
+    palette.sort_by(|(c1, f1), (c2, f2)| {
+        let ord = match sort {
+            PaletteSort::Frequency => f2.cmp(f1),
+            PaletteSort::Hue => Hsv::from_rgb(c1.0).hue().cmp(&Hsv::from_rgb(c2.0).hue()),
+            PaletteSort::Luminance => luminance(c1.0)
+                .partial_cmp(&luminance(c2.0))
+                .unwrap_or(Ordering::Equal),
+        };
+
+        ord.then_with(|| c1.0.cmp(&c2.0))
+    });
+}
+
+/// Create an image made of a row of squares of side `swatch_size`, one for
+/// each color in the palette. Fail if the image would be empty, that is if the
+/// palette is empty or `swatch_size` is 0.
+pub fn swatch(palette: &[image::Rgb<u8>], swatch_size: u32) -> Result<image::RgbImage, String> {
+    if palette.is_empty() || swatch_size == 0 {
+        return Err("cannot create an empty swatch".to_string());
+    }
+
+    let width = swatch_size * palette.len() as u32;
+
+    Ok(image::RgbImage::from_fn(width, swatch_size, |x, _| {
+        palette[(x / swatch_size) as usize]
+    }))
+}
+
+/// Index of the colors of a palette to quickly find the closest one to any
+/// other color.
+#[derive(Debug)]
+pub struct PaletteMatcher {
+    colors: KdTree<[u8; 3], ()>,
+}
+
+impl PaletteMatcher {
+    /// Create a new matcher for the given palette.
+    pub fn new(palette: &[image::Rgb<u8>]) -> Self {
+        PaletteMatcher {
+            colors: palette.iter().map(|c| (c.0, ())).collect(),
+        }
+    }
+
+    /// Return the color of the palette closest to the given one, None if the
+    /// palette is empty.
+    pub fn closest(&self, color: &image::Rgb<u8>) -> Option<image::Rgb<u8>> {
+        self.colors
+            .nearest_neighbor(color.0)
+            .map(|(c, _)| image::Rgb(*c))
+    }
+}
+
+impl FromStr for PaletteSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "frequency" => Ok(PaletteSort::Frequency),
+            "hue" => Ok(PaletteSort::Hue),
+            "luminance" => Ok(PaletteSort::Luminance),
+            _ => Err(format!(
+                "unknown sort {}, please use one of frequency, hue or luminance",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::iter;
+
+    const RED: image::Rgb<u8> = image::Rgb([0xff, 0, 0]);
+    const GREEN: image::Rgb<u8> = image::Rgb([0, 0xff, 0]);
+    const BLUE: image::Rgb<u8> = image::Rgb([0, 0, 0xff]);
+    const WHITE: image::Rgb<u8> = image::Rgb([0xff, 0xff, 0xff]);
+
+    fn pixels() -> impl Iterator<Item = image::Rgb<u8>> {
+        iter::repeat_n(BLUE, 5)
+            .chain(iter::repeat_n(RED, 2))
+            .chain(iter::repeat_n(WHITE, 4))
+            .chain(iter::repeat_n(GREEN, 1))
+    }
+
+    #[test]
+    fn test_extract_palette_by_frequency() {
+        assert_eq!(
+            extract_palette(pixels(), 4, PaletteSort::Frequency),
+            vec![(BLUE, 5), (WHITE, 4), (RED, 2), (GREEN, 1)]
+        );
+
+        assert_eq!(
+            extract_palette(pixels(), 3, PaletteSort::Frequency),
+            vec![(BLUE, 5), (WHITE, 4), (RED, 2)]
+        );
+
+        assert_eq!(extract_palette(pixels(), 0, PaletteSort::Frequency), vec![]);
+    }
+
+    #[test]
+    fn test_extract_palette_less_colors_than_wanted() {
+        assert_eq!(
+            extract_palette(iter::repeat_n(RED, 3), 8, PaletteSort::Frequency),
+            vec![(RED, 3)]
+        );
+    }
+
+    #[test]
+    fn test_extract_palette_sorted() {
+        assert_eq!(
+            extract_palette(pixels(), 3, PaletteSort::Hue),
+            vec![(RED, 2), (WHITE, 4), (BLUE, 5)]
+        );
+
+        assert_eq!(
+            extract_palette(pixels(), 4, PaletteSort::Luminance),
+            vec![(BLUE, 5), (RED, 2), (GREEN, 1), (WHITE, 4)]
+        );
+    }
+
+    #[test]
+    fn test_swatch() {
+        let img = swatch(&[RED, BLUE], 2).unwrap();
+
+        assert_eq!(img.dimensions(), (4, 2));
+        assert_eq!(
+            img.pixels().cloned().collect::<Vec<_>>(),
+            vec![RED, RED, BLUE, BLUE, RED, RED, BLUE, BLUE]
+        );
+
+        assert!(swatch(&[], 2).is_err());
+        assert!(swatch(&[RED], 0).is_err());
+    }
+
+    #[test]
+    fn test_palette_matcher() {
+        let matcher = PaletteMatcher::new(&[RED, GREEN, BLUE, WHITE]);
+
+        assert_eq!(matcher.closest(&image::Rgb([200, 10, 40])), Some(RED));
+        assert_eq!(matcher.closest(&image::Rgb([0, 10, 140])), Some(BLUE));
+        assert_eq!(matcher.closest(&image::Rgb([220, 210, 240])), Some(WHITE));
+        assert_eq!(matcher.closest(&GREEN), Some(GREEN));
+
+        assert_eq!(PaletteMatcher::new(&[]).closest(&RED), None);
+    }
+}
@@ -5,19 +5,32 @@ use std::collections::HashSet;
 
 use geo::{convex_hull, kmeans, BoundingBox, Point, Polygon};
 
+use crate::art::poisson::{self, PointDistribution};
 use crate::art::random_point_in_bbox;
+use crate::color::Theme;
 use crate::drawing::{Blender, Drawer};
 
-const WHITE_EGG: image::Rgb<u8> = image::Rgb([0xFD, 0xFD, 0xFF]);
-const BLACK_MATTERHORN: image::Rgb<u8> = image::Rgb([0x52, 0x4B, 0x4B]);
-
-/// Generate random shapes according to the PatchWork algorithm.
+/// Generate random shapes according to the PatchWork algorithm. The polygons
+/// boundaries are drawn with the stroke color of the theme while the polygons
+/// of the last generation are filled with random colors from its palette.
+///
+/// When `grow` isn't 1 the hulls are scaled by it around their center and
+/// clipped to their parent polygon, so that growing them makes the siblings
+/// overlap without leaking out of the parent.
+///
+/// When `inset` is positive the filled polygons are shrunk by that many pixels
+/// leaving a gap of the background color between them.
+#[allow(clippy::too_many_arguments)]
 pub fn random_patchwork(
     img: &mut image::RgbImage,
+    theme: &Theme,
     npoints: usize,
     k: usize,
     iterations: usize,
     fill_polygons: bool,
+    distribution: PointDistribution,
+    grow: f64,
+    inset: f64,
 ) {
     let mut generations = vec![vec![Polygon::new(vec![
         Point::new(0.0, 0.0),
@@ -27,9 +40,12 @@ pub fn random_patchwork(
     ])
     .unwrap()]];
 
+    let mut rng = rand::thread_rng();
+    let stroke = theme.stroke_or_fill();
+
     let mut drawer = Drawer::new_with_no_blending(img);
 
-    drawer.fill(&WHITE_EGG);
+    drawer.fill(&theme.background());
 
     let mut i = 0;
 
@@ -37,10 +53,20 @@ pub fn random_patchwork(
         if i >= iterations {
             if fill_polygons {
                 for poly in polygons {
-                    let poly =
-                        Polygon::new(poly.points().iter().map(|p| p.try_cast().unwrap())).unwrap();
-
-                    drawer.polygon(&poly, &BLACK_MATTERHORN);
+                    let insets = if inset > 0.0 {
+                        poly.offset(-inset)
+                    } else {
+                        vec![poly]
+                    };
+
+                    let fill = theme.random_fill(&mut rng);
+                    for poly in insets {
+                        let poly =
+                            Polygon::new(poly.points().iter().map(|p| p.try_cast().unwrap()))
+                                .unwrap();
+
+                        drawer.polygon(&poly, &fill);
+                    }
                 }
             }
 
@@ -51,7 +77,18 @@ pub fn random_patchwork(
 
         let new_polygons = polygons
             .iter()
-            .flat_map(|poly| patchwork_step(&mut drawer, &poly, npoints, k, !fill_polygons))
+            .flat_map(|poly| {
+                patchwork_step(
+                    &mut drawer,
+                    poly,
+                    npoints,
+                    k,
+                    stroke,
+                    !fill_polygons,
+                    distribution,
+                    grow,
+                )
+            })
             .collect::<Vec<_>>();
 
         if !new_polygons.is_empty() {
@@ -60,23 +97,35 @@ pub fn random_patchwork(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn patchwork_step<B: Blender<image::Rgb<u8>>>(
     drawer: &mut Drawer<image::RgbImage, B>,
     polygon: &Polygon<f64>,
     npoints: usize,
     k: usize,
+    stroke: image::Rgb<u8>,
     draw_polygons_boundary: bool,
+    distribution: PointDistribution,
+    grow: f64,
 ) -> Vec<Polygon<f64>> {
     let mut rng = rand::thread_rng();
 
-    let polygon_bbox = BoundingBox::from_points(&[
-        polygon.bounding_box().min().try_cast().unwrap(),
-        polygon.bounding_box().max().try_cast().unwrap(),
-    ]);
+    let mut points = match distribution {
+        PointDistribution::Uniform => {
+            let polygon_bbox = BoundingBox::from_points(&[
+                polygon.bounding_box().min().try_cast().unwrap(),
+                polygon.bounding_box().max().try_cast().unwrap(),
+            ]);
 
-    let mut points = (0..npoints)
-        .map(|_| random_point_in_bbox(&mut rng, &polygon_bbox))
-        .collect::<HashSet<_>>();
+            (0..npoints)
+                .map(|_| random_point_in_bbox(&mut rng, &polygon_bbox))
+                .collect::<HashSet<_>>()
+        }
+        PointDistribution::Poisson => poisson::sample(&mut rng, distribution, npoints, polygon)
+            .into_iter()
+            .map(|pt| Point::new(pt.x as u32, pt.y as u32))
+            .collect::<HashSet<_>>(),
+    };
 
     points.retain(|pt| polygon.contains(&pt.cast()));
 
@@ -105,11 +154,25 @@ fn patchwork_step<B: Blender<image::Rgb<u8>>>(
                     points.remove(&pt.try_cast().unwrap());
                 }
 
+                if (grow - 1.0).abs() > f64::EPSILON {
+                    for new_poly in
+                        grow_hull(&hull, grow).map_or(vec![], |h| h.intersection(polygon))
+                    {
+                        if draw_polygons_boundary {
+                            drawer.closed_path(
+                                new_poly.points().iter().map(|p| p.try_cast().unwrap()),
+                                &stroke,
+                            );
+                        }
+
+                        polygons.push(new_poly);
+                    }
+
+                    continue;
+                }
+
                 if draw_polygons_boundary {
-                    drawer.closed_path(
-                        hull.iter().map(|p| p.try_cast().unwrap()),
-                        &BLACK_MATTERHORN,
-                    );
+                    drawer.closed_path(hull.iter().map(|p| p.try_cast().unwrap()), &stroke);
                 }
 
                 if let Some(new_poly) = Polygon::new(hull) {
@@ -121,3 +184,15 @@ fn patchwork_step<B: Blender<image::Rgb<u8>>>(
 
     polygons
 }
+
+/// Scale the hull around the mean of its vertices.
+fn grow_hull(hull: &[Point<f64>], factor: f64) -> Option<Polygon<f64>> {
+    let n = hull.len() as f64;
+    let cx = hull.iter().map(|p| p.x).sum::<f64>() / n;
+    let cy = hull.iter().map(|p| p.y).sum::<f64>() / n;
+
+    Polygon::new(
+        hull.iter()
+            .map(|p| Point::new(cx + (p.x - cx) * factor, cy + (p.y - cy) * factor)),
+    )
+}
@@ -0,0 +1,339 @@
+//! Blue noise sampling with [Bridson's Poisson-disc
+//! algorithm](https://www.cs.ubc.ca/~rbridson/docs/bridson-siggraph07-poissondisk.pdf).
+//! Unlike uniformly random points, the points never clump together because
+//! every point keeps a minimum distance from all the others, but they still
+//! look random.
+
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use rand::Rng;
+
+use geo::{BoundingBox, PointF64, Polygon};
+
+/// How many candidates are tried around a point before giving up on it.
+const ATTEMPTS: usize = 30;
+
+/// The fraction of the area a Poisson-disc sample of radius `r` covers with
+/// discs of radius `r / 2`, used to estimate the radius needed for a given
+/// number of points.
+const PACKING_DENSITY: f64 = 0.54;
+
+/// How random points are scattered over a region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointDistribution {
+    /// Independent uniformly random points, they tend to clump.
+    Uniform,
+
+    /// Poisson-disc points that are evenly spaced, but still random.
+    Poisson,
+}
+
+/// A region of the plane where points can be sampled.
+pub trait Region {
+    /// Return the bounding box of the region.
+    fn bbox(&self) -> BoundingBox<f64>;
+
+    /// Return whether the point is inside the region.
+    fn contains_point(&self, pt: &PointF64) -> bool;
+
+    /// Return the area of the region, it's fine to overestimate it.
+    fn approximate_area(&self) -> f64 {
+        self.bbox().area().unwrap_or(0.0)
+    }
+}
+
+impl Region for BoundingBox<f64> {
+    fn bbox(&self) -> BoundingBox<f64> {
+        self.clone()
+    }
+
+    fn contains_point(&self, pt: &PointF64) -> bool {
+        self.contains(pt)
+    }
+}
+
+impl Region for Polygon<f64> {
+    fn bbox(&self) -> BoundingBox<f64> {
+        self.bounding_box().clone()
+    }
+
+    fn contains_point(&self, pt: &PointF64) -> bool {
+        self.contains(pt)
+    }
+
+    fn approximate_area(&self) -> f64 {
+        self.area()
+    }
+}
+
+/// Return about `n` points in the region scattered according to the given
+/// distribution. The uniform distribution always returns exactly `n` points
+/// unless the region is empty.
+pub fn sample<R: Rng>(
+    rng: &mut R,
+    distribution: PointDistribution,
+    n: usize,
+    region: &impl Region,
+) -> Vec<PointF64> {
+    if n == 0 {
+        return vec![];
+    }
+
+    match distribution {
+        PointDistribution::Uniform => {
+            let bbox = region.bbox();
+            if bbox.is_empty() {
+                return vec![];
+            }
+
+            let mut points = Vec::with_capacity(n);
+
+            // give up eventually if the region has no area at all
+            for _ in 0..n.saturating_mul(100) {
+                if points.len() >= n {
+                    break;
+                }
+
+                let pt = random_point(rng, &bbox);
+                if region.contains_point(&pt) {
+                    points.push(pt);
+                }
+            }
+
+            points
+        }
+        PointDistribution::Poisson => {
+            poisson_disc(rng, region, radius_for(region.approximate_area(), n))
+        }
+    }
+}
+
+/// Return the radius of a Poisson-disc sample of about `n` points over the
+/// given area.
+pub fn radius_for(area: f64, n: usize) -> f64 {
+    (4.0 * PACKING_DENSITY * area / (PI * n as f64)).sqrt()
+}
+
+/// Return evenly spaced points in the region where no two points are closer
+/// than `radius`.
+pub fn poisson_disc<R: Rng>(rng: &mut R, region: &impl Region, radius: f64) -> Vec<PointF64> {
+    variable_poisson_disc(rng, region, (radius, radius), |_| radius)
+}
+
+/// Return points in the region whose spacing varies according to the `radius`
+/// function clamped between `min_radius` and `max_radius`: every point `p` is
+/// placed at least `radius(p)` away from the points placed before it and
+/// therefore two points are never closer than the smaller of their radii. The
+/// smaller the radius, the denser the points.
+pub fn variable_poisson_disc<R: Rng>(
+    rng: &mut R,
+    region: &impl Region,
+    (min_radius, max_radius): (f64, f64),
+    radius: impl Fn(&PointF64) -> f64,
+) -> Vec<PointF64> {
+    let bbox = region.bbox();
+    if bbox.is_empty() || min_radius <= 0.0 || max_radius < min_radius {
+        return vec![];
+    }
+
+    let radius = |p: &PointF64| radius(p).max(min_radius).min(max_radius);
+
+    // each cell is small enough to contain at most one point
+    let cell_size = min_radius / 2.0_f64.sqrt();
+    let (width, height) = bbox.dimensions().unwrap();
+    let cols = (width / cell_size).floor() as usize + 1;
+    let rows = (height / cell_size).floor() as usize + 1;
+
+    let cell_of = |p: &PointF64| {
+        (
+            ((p.x - bbox.min().x) / cell_size) as usize,
+            ((p.y - bbox.min().y) / cell_size) as usize,
+        )
+    };
+
+    let mut grid = vec![None; cols * rows];
+    let mut points = vec![];
+    let mut active = vec![];
+
+    let is_far_enough = |grid: &[Option<usize>], points: &[PointF64], p: &PointF64| {
+        let r = radius(p);
+        let reach = (r / cell_size).ceil() as usize;
+        let (cx, cy) = cell_of(p);
+
+        for y in cy.saturating_sub(reach)..(cy + reach + 1).min(rows) {
+            for x in cx.saturating_sub(reach)..(cx + reach + 1).min(cols) {
+                if let Some(i) = grid[y * cols + x] {
+                    let other: &PointF64 = &points[i];
+                    let d: f64 = other.squared_dist(p);
+
+                    if d < r * r {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    };
+
+    let insert = |grid: &mut Vec<Option<usize>>,
+                  points: &mut Vec<PointF64>,
+                  active: &mut Vec<usize>,
+                  p: PointF64| {
+        let (cx, cy) = cell_of(&p);
+
+        grid[cy * cols + cx] = Some(points.len());
+        active.push(points.len());
+        points.push(p);
+    };
+
+    let seed = (0..ATTEMPTS)
+        .map(|_| random_point(rng, &bbox))
+        .find(|p| region.contains_point(p));
+
+    match seed {
+        Some(seed) => insert(&mut grid, &mut points, &mut active, seed),
+        None => return vec![],
+    }
+
+    while !active.is_empty() {
+        let i = rng.gen_range(0, active.len());
+        let p = points[active[i]];
+        let r = radius(&p);
+
+        let candidate = (0..ATTEMPTS)
+            .map(|_| {
+                let angle = rng.gen_range(0.0, 2.0 * PI);
+                let dist = rng.gen_range(r, 2.0 * r);
+
+                PointF64::new(p.x + dist * angle.cos(), p.y + dist * angle.sin())
+            })
+            .find(|c| {
+                bbox.contains(c) && region.contains_point(c) && is_far_enough(&grid, &points, c)
+            });
+
+        match candidate {
+            Some(c) => insert(&mut grid, &mut points, &mut active, c),
+            None => {
+                active.swap_remove(i);
+            }
+        }
+    }
+
+    points
+}
+
+fn random_point<R: Rng>(rng: &mut R, bbox: &BoundingBox<f64>) -> PointF64 {
+    let (width, height) = bbox.dimensions().unwrap();
+
+    PointF64::new(
+        bbox.min().x + rng.gen::<f64>() * width,
+        bbox.min().y + rng.gen::<f64>() * height,
+    )
+}
+
+impl FromStr for PointDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(PointDistribution::Uniform),
+            "poisson" => Ok(PointDistribution::Poisson),
+            _ => Err(format!(
+                "unknown point distribution {}, please use uniform or poisson",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn min_distance(points: &[PointF64]) -> f64 {
+        let mut min = f64::INFINITY;
+
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                min = min.min(a.dist(b));
+            }
+        }
+
+        min
+    }
+
+    #[test]
+    fn test_poisson_disc() {
+        let mut rng = rand::thread_rng();
+        let bbox = BoundingBox::from_dimensions(100.0, 50.0);
+
+        let points = poisson_disc(&mut rng, &bbox, 5.0);
+
+        assert!(points.iter().all(|p| bbox.contains(p)));
+        assert!(min_distance(&points) >= 5.0);
+
+        // the box should be almost full, a 5x5 square fits only one point
+        assert!(points.len() > 100 && points.len() < 200);
+    }
+
+    #[test]
+    fn test_poisson_disc_in_polygon() {
+        let mut rng = rand::thread_rng();
+        let triangle = Polygon::new(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(100.0, 0.0),
+            PointF64::new(0.0, 100.0),
+        ])
+        .unwrap();
+
+        let points = poisson_disc(&mut rng, &triangle, 3.0);
+
+        assert!(!points.is_empty());
+        assert!(points.iter().all(|p| triangle.contains(p)));
+        assert!(min_distance(&points) >= 3.0);
+    }
+
+    #[test]
+    fn test_variable_poisson_disc() {
+        let mut rng = rand::thread_rng();
+        let bbox = BoundingBox::from_dimensions(100.0, 100.0);
+
+        // denser on the left
+        let points =
+            variable_poisson_disc(&mut rng, &bbox, (2.0, 8.0), |p| 2.0 + p.x / 100.0 * 6.0);
+
+        let left = points.iter().filter(|p| p.x < 50.0).count();
+        assert!(left > 2 * (points.len() - left));
+
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                assert!(a.dist::<f64>(b) >= 2.0 + a.x.min(b.x) / 100.0 * 6.0 - 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut rng = rand::thread_rng();
+        let bbox = BoundingBox::from_dimensions(200.0, 100.0);
+
+        let uniform = sample(&mut rng, PointDistribution::Uniform, 500, &bbox);
+        assert_eq!(uniform.len(), 500);
+
+        let poisson = sample(&mut rng, PointDistribution::Poisson, 500, &bbox);
+        assert!(poisson.len() > 400 && poisson.len() < 600);
+
+        let empty = BoundingBox::new();
+        assert!(sample(&mut rng, PointDistribution::Poisson, 10, &empty).is_empty());
+        assert!(sample(&mut rng, PointDistribution::Uniform, 10, &empty).is_empty());
+    }
+
+    #[test]
+    fn test_distribution_from_str() {
+        assert_eq!("poisson".parse(), Ok(PointDistribution::Poisson));
+        assert_eq!("uniform".parse(), Ok(PointDistribution::Uniform));
+        assert!("blue".parse::<PointDistribution>().is_err());
+    }
+}
@@ -0,0 +1,286 @@
+//! How `primify` measures the difference between the target image and the
+//! image made of shapes. The difference of every pixel can be computed on the
+//! raw channels or in a perceptual color space and it can be weighted by an
+//! importance map so that some regions, like faces or details, get more
+//! shapes.
+
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::art::edges;
+use crate::art::primi::PrimifyImage;
+
+static SRGB_TO_LINEAR: OnceLock<[f64; 256]> = OnceLock::new();
+
+/// How the difference between two pixels is measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorMetric {
+    /// Sum of the squared differences of the raw channels.
+    Rgb,
+
+    /// Squared euclidean distance in the
+    /// [CIELAB](https://en.wikipedia.org/wiki/CIELAB_color_space) color space
+    /// that is closer to how humans perceive color differences. The alpha
+    /// channel is ignored and it's slower than `Rgb`.
+    Lab,
+}
+
+/// The importance of every pixel of an image. The weights are normalized so
+/// that their mean is 1 and therefore the error has the same magnitude of the
+/// unweighted one. The map can have any size, it's stretched to cover the whole
+/// image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightMap {
+    width: u32,
+    height: u32,
+    weights: Vec<f64>,
+}
+
+impl WeightMap {
+    /// Create a `WeightMap` from a grayscale mask where white pixels are the
+    /// most important ones and black pixels do not matter at all. A completely
+    /// black mask weights all the pixels equally.
+    pub fn from_mask(mask: &image::GrayImage) -> Self {
+        let weights = mask.pixels().map(|p| f64::from(p[0]) / 255.0).collect();
+
+        WeightMap::new(mask.width(), mask.height(), weights)
+    }
+
+    /// Create a `WeightMap` that gives more importance to the pixels close to
+    /// the edges of the image, but where every pixel still matters a bit.
+    pub fn from_edges(img: &image::GrayImage) -> Self {
+        let edges = image::imageops::blur(&edges::sobel(img), 2.0);
+        let max = edges.pixels().map(|p| p[0]).max().unwrap_or(0).max(1);
+
+        let weights = edges
+            .pixels()
+            .map(|p| 0.1 + f64::from(p[0]) / f64::from(max))
+            .collect();
+
+        WeightMap::new(img.width(), img.height(), weights)
+    }
+
+    fn new(width: u32, height: u32, mut weights: Vec<f64>) -> Self {
+        let sum = weights.iter().sum::<f64>();
+
+        if sum > 0.0 {
+            let mean = sum / weights.len() as f64;
+            for w in &mut weights {
+                *w /= mean;
+            }
+        } else {
+            weights.iter_mut().for_each(|w| *w = 1.0);
+        }
+
+        WeightMap {
+            width,
+            height,
+            weights,
+        }
+    }
+
+    /// Return the weight of the pixel at `(x, y)` of a `width` x `height`
+    /// image.
+    pub fn weight(&self, x: u32, y: u32, width: u32, height: u32) -> f64 {
+        if self.weights.is_empty() {
+            return 1.0;
+        }
+
+        let wx = u64::from(x) * u64::from(self.width) / u64::from(width);
+        let wy = u64::from(y) * u64::from(self.height) / u64::from(height);
+
+        self.weights[(wy * u64::from(self.width) + wx) as usize]
+    }
+}
+
+/// Return the squared difference between the two pixels according to the
+/// given metric.
+pub fn pixel_error<P>(metric: ErrorMetric, p1: &P, p2: &P) -> f64
+where
+    P: image::Pixel,
+    f64: From<P::Subpixel>,
+{
+    match metric {
+        ErrorMetric::Rgb => p1
+            .channels()
+            .iter()
+            .zip(p2.channels())
+            .map(|(a, b)| (f64::from(*a) - f64::from(*b)).powi(2))
+            .sum(),
+        ErrorMetric::Lab => {
+            let (l1, l2) = (lab(p1), lab(p2));
+
+            l1.iter().zip(&l2).map(|(a, b)| (a - b).powi(2)).sum()
+        }
+    }
+}
+
+/// Return the total squared error between the two images of the same size.
+pub fn image_error<P>(
+    metric: ErrorMetric,
+    weights: Option<&WeightMap>,
+    img1: &PrimifyImage<P>,
+    img2: &PrimifyImage<P>,
+) -> f64
+where
+    P: 'static + image::Pixel,
+    f64: From<P::Subpixel>,
+{
+    if let (ErrorMetric::Rgb, None) = (metric, weights) {
+        return get_squared_error(img1.iter(), img2.iter());
+    }
+
+    let (width, height) = img1.dimensions();
+
+    img1.enumerate_pixels()
+        .map(|(x, y, p1)| {
+            let w = weights.map_or(1.0, |w| w.weight(x, y, width, height));
+
+            w * pixel_error(metric, p1, img2.get_pixel(x, y))
+        })
+        .sum()
+}
+
+fn get_squared_error<'a, I, D>(it1: I, it2: I) -> f64
+where
+    I: Iterator<Item = &'a D>,
+    D: 'a + Clone,
+    f64: From<D>,
+{
+    // sum of the squared deviations
+    it1.zip(it2)
+        .map(|(x, y)| (f64::from(x.clone()), f64::from(y.clone())))
+        .fold(0.0, |acc, (x, y)| acc + (x - y).powi(2))
+}
+
+/// Convert the given pixel, whose channels are assumed to be sRGB, to CIELAB
+/// under the D65 illuminant.
+fn lab<P>(pix: &P) -> [f64; 3]
+where
+    P: image::Pixel,
+    f64: From<P::Subpixel>,
+{
+    let max = f64::from(<P::Subpixel as num::Bounded>::max_value());
+    let rgb = pix.to_rgb();
+
+    // the conversion is hot, use a table for the common 8 bit channels
+    let linear = |c: P::Subpixel| {
+        let c = f64::from(c);
+
+        if max == 255.0 {
+            SRGB_TO_LINEAR.get_or_init(|| {
+                let mut table = [0.0; 256];
+                for (i, l) in table.iter_mut().enumerate() {
+                    *l = srgb_to_linear(i as f64 / 255.0);
+                }
+                table
+            })[c as usize]
+        } else {
+            srgb_to_linear(c / max)
+        }
+    };
+    let (r, g, b) = (linear(rgb[0]), linear(rgb[1]), linear(rgb[2]));
+
+    let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.088_83;
+
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl FromStr for ErrorMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rgb" => Ok(ErrorMetric::Rgb),
+            "lab" => Ok(ErrorMetric::Lab),
+            _ => Err(format!("unknown metric {}, please use rgb or lab", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error() {
+        assert_eq!(get_squared_error(([] as [u8; 0]).iter(), [].iter()), 0.0);
+        assert_eq!(get_squared_error([0_u8].iter(), [2_u8].iter()), 4.0);
+        assert_eq!(
+            get_squared_error([3_u8, 1, 3].iter(), [3_u8, 4, 7].iter()),
+            25.0
+        );
+        assert_eq!(
+            get_squared_error([3_u8, 1, 3].iter(), [3_u8, 4, 5].iter()),
+            13.0
+        );
+    }
+
+    #[test]
+    fn test_lab() {
+        let white = lab(&image::Rgb([255_u8, 255, 255]));
+        assert!((white[0] - 100.0).abs() < 1e-3);
+        assert!(white[1].abs() < 1e-3 && white[2].abs() < 1e-3);
+
+        assert_eq!(lab(&image::Rgb([0_u8, 0, 0])), [0.0, 0.0, 0.0]);
+
+        let red = lab(&image::Rgb([255_u8, 0, 0]));
+        assert!((red[0] - 53.24).abs() < 0.01);
+        assert!((red[1] - 80.09).abs() < 0.01);
+        assert!((red[2] - 67.20).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_weight_map() {
+        let mask =
+            image::GrayImage::from_fn(2, 1, |x, _| image::Luma([if x == 0 { 0 } else { 255 }]));
+        let weights = WeightMap::from_mask(&mask);
+
+        // the map is stretched over the 4x2 image
+        assert_eq!(weights.weight(0, 0, 4, 2), 0.0);
+        assert_eq!(weights.weight(1, 1, 4, 2), 0.0);
+        assert_eq!(weights.weight(2, 0, 4, 2), 2.0);
+        assert_eq!(weights.weight(3, 1, 4, 2), 2.0);
+
+        let black = WeightMap::from_mask(&image::GrayImage::new(3, 3));
+        assert_eq!(black.weight(1, 1, 3, 3), 1.0);
+    }
+
+    #[test]
+    fn test_image_error() {
+        let img1 = image::RgbImage::from_pixel(2, 1, image::Rgb([10, 10, 10]));
+        let img2 = image::RgbImage::from_pixel(2, 1, image::Rgb([12, 10, 10]));
+
+        assert_eq!(image_error(ErrorMetric::Rgb, None, &img1, &img2), 8.0);
+
+        let mask =
+            image::GrayImage::from_fn(2, 1, |x, _| image::Luma([if x == 0 { 0 } else { 255 }]));
+        let weights = WeightMap::from_mask(&mask);
+        assert_eq!(
+            image_error(ErrorMetric::Rgb, Some(&weights), &img1, &img2),
+            8.0
+        );
+
+        assert!(image_error(ErrorMetric::Lab, None, &img1, &img2) > 0.0);
+        assert_eq!(image_error(ErrorMetric::Lab, None, &img1, &img1), 0.0);
+    }
+}
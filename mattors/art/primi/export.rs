@@ -0,0 +1,190 @@
+//! Export the result of `primify` as SVG or as a JSON list of shapes. Both
+//! can be rendered again at any resolution or edited later.
+
+use serde::{Deserialize, Serialize};
+
+use crate::art::primi::{Primitized, Shape};
+use crate::color::theme::to_hex;
+
+/// A serializable description of a `Primitized` image.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ShapeList<S> {
+    /// The width of the image the shapes were generated for.
+    pub width: u32,
+
+    /// The height of the image the shapes were generated for.
+    pub height: u32,
+
+    /// The background color in the `#rrggbb` format.
+    pub background: String,
+
+    /// The shapes in the order they must be drawn.
+    pub shapes: Vec<ShapeEntry<S>>,
+}
+
+/// A shape along with the color it's drawn with.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ShapeEntry<S> {
+    /// The color of the shape in the `#rrggbb` format.
+    pub color: String,
+
+    /// The opacity of the shape between 0 and 255.
+    pub alpha: u8,
+
+    /// The shape itself.
+    pub shape: S,
+}
+
+impl<S> ShapeList<S> {
+    /// Parse a list of shapes previously serialized with `Primitized::to_json`.
+    pub fn from_json(s: &str) -> Result<Self, String>
+    where
+        S: serde::de::DeserializeOwned,
+    {
+        serde_json::from_str(s).map_err(|e| format!("cannot parse shapes from json, {}", e))
+    }
+}
+
+impl<P, S> Primitized<P, S>
+where
+    P: 'static + image::Pixel<Subpixel = u8>,
+    S: Shape,
+{
+    /// Write this image as an SVG document of the given size. The shapes are
+    /// scaled to fill it.
+    pub fn write_svg(
+        &self,
+        out: &mut impl std::io::Write,
+        (width, height): (u32, u32),
+    ) -> std::io::Result<()> {
+        let (w, h) = self.best_image.dimensions();
+
+        write!(
+            out,
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{width}" height="{height}" viewBox="0 0 {w} {h}">
+<rect x="0" y="0" width="{w}" height="{h}" fill="{background}"/>
+"##,
+            width = width,
+            height = height,
+            w = w,
+            h = h,
+            background = to_hex(self.dominant_color.to_rgb()),
+        )?;
+
+        for (shape, color) in &self.shapes {
+            let (hex, alpha) = split_color(color);
+
+            writeln!(out, "{}", shape.to_svg(&hex, f64::from(alpha) / 255.0))?;
+        }
+
+        write!(out, "</svg>")
+    }
+
+    /// Return the list of shapes that make up this image.
+    pub fn to_shape_list(&self) -> ShapeList<S> {
+        let (width, height) = self.best_image.dimensions();
+
+        ShapeList {
+            width,
+            height,
+            background: to_hex(self.dominant_color.to_rgb()),
+            shapes: self
+                .shapes
+                .iter()
+                .map(|(shape, color)| {
+                    let (color, alpha) = split_color(color);
+
+                    ShapeEntry {
+                        color,
+                        alpha,
+                        shape: shape.clone(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Serialize the list of shapes that make up this image to JSON.
+    pub fn to_json(&self) -> Result<String, String>
+    where
+        S: Serialize,
+    {
+        serde_json::to_string_pretty(&self.to_shape_list())
+            .map_err(|e| format!("cannot serialize shapes to json, {}", e))
+    }
+}
+
+fn split_color<P: image::Pixel<Subpixel = u8>>(color: &P) -> (String, u8) {
+    let rgba = color.to_rgba();
+
+    (to_hex(color.to_rgb()), rgba[3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use geo::PointU32;
+
+    use crate::art::primi::shapes::{AnyShape, Circle};
+
+    fn primitized() -> Primitized<image::Rgba<u8>, AnyShape> {
+        let triangle = geo::Triangle::new(
+            PointU32::new(0, 0),
+            PointU32::new(4, 0),
+            PointU32::new(0, 3),
+        );
+        let circle = Circle {
+            center: PointU32::new(5, 5),
+            radius: 2,
+        };
+
+        Primitized {
+            best_error: 0.0,
+            best_image: image::RgbaImage::new(10, 8),
+            shapes: vec![
+                (AnyShape::Triangle(triangle), image::Rgba([255, 0, 0, 128])),
+                (AnyShape::Circle(circle), image::Rgba([0, 0, 255, 255])),
+            ],
+            dominant_color: image::Rgba([255, 255, 255, 255]),
+        }
+    }
+
+    #[test]
+    fn test_write_svg() {
+        let mut out = vec![];
+        primitized().write_svg(&mut out, (20, 16)).unwrap();
+
+        let svg = String::from_utf8(out).unwrap();
+        let lines = svg.lines().skip(2).collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="20" height="16" viewBox="0 0 10 8">"#,
+                r##"<rect x="0" y="0" width="10" height="8" fill="#ffffff"/>"##,
+                r##"<polygon points="0,0 4,0 0,3" fill="#ff0000" fill-opacity="0.502"/>"##,
+                r##"<circle cx="5" cy="5" r="2" fill="#0000ff" fill-opacity="1.000"/>"##,
+                "</svg>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shape_list_json() {
+        let prim = primitized();
+        let list = prim.to_shape_list();
+
+        assert_eq!((list.width, list.height), (10, 8));
+        assert_eq!(list.background, "#ffffff");
+        assert_eq!(list.shapes[0].color, "#ff0000");
+        assert_eq!(list.shapes[0].alpha, 128);
+
+        let json = prim.to_json().unwrap();
+        let parsed = ShapeList::<AnyShape>::from_json(&json).unwrap();
+
+        assert_eq!(parsed, list);
+    }
+}
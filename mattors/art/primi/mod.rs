@@ -0,0 +1,564 @@
+//! Simple Rust implementation of
+//! [primitive](https://github.com/fogleman/primitive)
+
+pub mod error;
+pub mod export;
+pub mod scanline;
+pub mod search;
+pub mod shapes;
+
+use std;
+use std::clone::Clone;
+use std::convert::From;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::iter::Iterator;
+use std::str::FromStr;
+
+use crate::art::quantize;
+use crate::color::theme::parse_color;
+
+pub use self::error::{ErrorMetric, WeightMap};
+pub use self::export::ShapeList;
+pub use self::scanline::Scanline;
+pub use self::search::Strategy;
+pub use self::shapes::{AnyShape, ShapeKind};
+
+/// A shape that can be used in the `primitify` function.
+pub trait Shape: Clone + Send + Sync {
+    /// Generate a new random `Shape` inside the box.
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self;
+
+    /// Generate the shapes to choose the starting shape of a step from. By
+    /// default it's just a single random shape.
+    fn candidates(width: u32, height: u32, dx: u32, dy: u32) -> Vec<Self> {
+        vec![Self::random(width, height, dx, dy)]
+    }
+
+    /// Create a new version of `Shape` that's slightly changed.
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self;
+
+    /// Return the scanlines covered by the `Shape` in a `width` x `height`
+    /// image.
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline>;
+
+    /// Draw the `Shape` onto `dst` by blending the given pixel.
+    fn draw<P>(&self, pix: &P, dst: &mut PrimifyImage<P>)
+    where
+        P: 'static + image::Pixel + Debug,
+    {
+        let lines = self.rasterize(dst.width(), dst.height());
+        scanline::draw(&lines, pix, dst);
+    }
+
+    /// Return the SVG element that draws this shape with the given color, in
+    /// the `#rrggbb` format, and opacity between 0 and 1.
+    fn to_svg(&self, color: &str, opacity: f64) -> String;
+
+    /// Upscale the shape by the given `factor`.
+    fn upscale(&self, factor: u32) -> Self;
+}
+
+/// The result of `primitify`.
+pub struct Primitized<P: image::Pixel, S: Shape> {
+    /// The smallest error the primitization process was able to achieve.
+    pub best_error: f64,
+
+    /// The best image the algorithm was able to achieve.
+    pub best_image: PrimifyImage<P>,
+
+    /// All the shapes in the image along with the color, alpha included, they
+    /// were drawn with.
+    pub shapes: Vec<(S, P)>,
+
+    /// The dominant color of the original image.
+    pub dominant_color: P,
+}
+
+/// The opacity of the shapes drawn by `primify`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alpha {
+    /// All the shapes are drawn with the given alpha.
+    Fixed(u8),
+
+    /// The alpha of each shape is optimized along with the shape itself.
+    Auto,
+}
+
+/// Just an handy alias for `ImageBuffer`.
+pub type PrimifyImage<P> = image::ImageBuffer<P, Vec<<P as image::Pixel>::Subpixel>>;
+
+/// The configuration of `primify`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrimifyConfig {
+    nshapes: usize,
+    nmutations: u32,
+    mutation_batch: u32,
+    dx: u32,
+    dy: u32,
+    alpha: Alpha,
+    strategy: Strategy,
+    frame_every: Option<usize>,
+    metric: ErrorMetric,
+    weights: Option<WeightMap>,
+}
+
+/// The progress `primify` reports after each step.
+pub struct Progress<'a, P: 'static + image::Pixel, S: Shape> {
+    /// The step that was just completed, starting from 1.
+    pub step: usize,
+
+    /// The total number of steps.
+    pub steps: usize,
+
+    /// How many shapes the image is made of so far, including the ones it
+    /// was resumed from.
+    pub shapes: usize,
+
+    /// The error of the image after this step.
+    pub error: f64,
+
+    /// Whether a shape was added in this step.
+    pub improved: bool,
+
+    /// The image and its shapes after this step.
+    pub current: &'a Primitized<P, S>,
+
+    /// Whether this step should be rendered as a frame of an animation, see
+    /// `PrimifyConfig::frame_every`.
+    pub frame: bool,
+}
+
+impl PrimifyConfig {
+    /// Create a new `PrimifyConfig` with the default settings: 100 shapes, 100
+    /// mutations per shape in batches of 8, shapes of size 16x16, alpha 128 and
+    /// hill climbing starting from the best of 16 random shapes.
+    pub fn new() -> Self {
+        PrimifyConfig {
+            nshapes: 100,
+            nmutations: 100,
+            mutation_batch: 8,
+            dx: 16,
+            dy: 16,
+            alpha: Alpha::Fixed(128),
+            strategy: Strategy::HillClimbing { candidates: 16 },
+            frame_every: None,
+            metric: ErrorMetric::Rgb,
+            weights: None,
+        }
+    }
+
+    /// Set the number of shapes to generate.
+    pub fn shapes(mut self, nshapes: usize) -> Self {
+        self.nshapes = nshapes;
+        self
+    }
+
+    /// Set the number of mutations to perform when searching for a shape.
+    pub fn mutations(mut self, nmutations: u32) -> Self {
+        self.nmutations = nmutations;
+        self
+    }
+
+    /// Set how many mutations hill climbing evaluates in parallel before
+    /// moving to the best one. The result doesn't depend on the number of
+    /// threads, but only on this value.
+    pub fn mutation_batch(mut self, size: u32) -> Self {
+        self.mutation_batch = size.max(1);
+        self
+    }
+
+    /// Set the deltas in x and y that determine how big the shapes will be.
+    pub fn delta(mut self, dx: u32, dy: u32) -> Self {
+        self.dx = dx;
+        self.dy = dy;
+        self
+    }
+
+    /// Set the opacity of the shapes.
+    pub fn alpha(mut self, alpha: Alpha) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Set the strategy used to search for the shapes.
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Mark a step as a frame every time `k` new shapes have been added and at
+    /// the last step. By default no step is a frame.
+    pub fn frame_every(mut self, k: usize) -> Self {
+        self.frame_every = Some(k.max(1));
+        self
+    }
+
+    /// Set how the difference between the pixels of the target image and the
+    /// ones of the shapes is measured. By default it's `ErrorMetric::Rgb`.
+    pub fn metric(mut self, metric: ErrorMetric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Weight the error of every pixel with the given map so that the shapes
+    /// focus on the most important regions. By default all the pixels are
+    /// equally important.
+    pub fn weights(mut self, weights: WeightMap) -> Self {
+        self.weights = Some(weights);
+        self
+    }
+}
+
+impl Default for PrimifyConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Simple rust port of [primitive](https://github.com/fogleman/primitive) and
+/// primipy. The kind of shapes to use is chosen by `S`, see the `shapes`
+/// module for the available ones and the `search` module for how the shapes
+/// are chosen. `on_progress` is called after each step and it can be used to
+/// stream the progress, for example to render an animation.
+pub fn primify<P, S>(
+    img: &PrimifyImage<P>,
+    config: &PrimifyConfig,
+    on_progress: impl FnMut(&Progress<P, S>),
+) -> Option<Primitized<P, S>>
+where
+    P: 'static + Eq + Hash + image::Pixel + Debug + Send + Sync,
+    P::Subpixel: Ord + From<u8> + std::fmt::Debug + Send + Sync,
+    f64: From<P::Subpixel>,
+    u64: From<P::Subpixel>,
+    S: Shape,
+{
+    let dominant = get_dominant_color(img)?;
+
+    let start = Primitized {
+        best_error: 0.0,
+        shapes: Vec::with_capacity(config.nshapes),
+        best_image: image::ImageBuffer::from_pixel(img.width(), img.height(), dominant),
+        dominant_color: dominant,
+    };
+
+    Some(add_shapes(img, start, config, on_progress))
+}
+
+/// Like `primify`, but instead of starting from a blank canvas start from the
+/// image made by the given shapes drawn on their background and keep adding
+/// `config` shapes to it. The shapes must have been generated for an image as
+/// big as `img`.
+pub fn resume<P, S>(
+    img: &PrimifyImage<P>,
+    start: &ShapeList<S>,
+    config: &PrimifyConfig,
+    on_progress: impl FnMut(&Progress<P, S>),
+) -> Result<Primitized<P, S>, String>
+where
+    P: 'static + Eq + Hash + image::Pixel<Subpixel = u8> + Debug + Send + Sync,
+    S: Shape,
+{
+    if img.dimensions() != (start.width, start.height) {
+        return Err(format!(
+            "the shapes are for a {}x{} image, but the image is {}x{}",
+            start.width,
+            start.height,
+            img.width(),
+            img.height()
+        ));
+    }
+
+    let background = parse_color(&start.background)?;
+    let background = P::from_channels(background[0], background[1], background[2], 255);
+
+    let mut res = Primitized {
+        best_error: 0.0,
+        best_image: image::ImageBuffer::from_pixel(img.width(), img.height(), background),
+        shapes: Vec::with_capacity(start.shapes.len() + config.nshapes),
+        dominant_color: background,
+    };
+
+    for entry in &start.shapes {
+        let c = parse_color(&entry.color)?;
+        let color = P::from_channels(c[0], c[1], c[2], entry.alpha);
+
+        entry.shape.draw(&color, &mut res.best_image);
+        res.shapes.push((entry.shape.clone(), color));
+    }
+
+    Ok(add_shapes(img, res, config, on_progress))
+}
+
+fn add_shapes<P, S>(
+    img: &PrimifyImage<P>,
+    mut res: Primitized<P, S>,
+    config: &PrimifyConfig,
+    mut on_progress: impl FnMut(&Progress<P, S>),
+) -> Primitized<P, S>
+where
+    P: 'static + image::Pixel + Debug + Send + Sync,
+    P::Subpixel: From<u8> + Send + Sync,
+    f64: From<P::Subpixel>,
+    S: Shape,
+{
+    let mut squared_error =
+        error::image_error(config.metric, config.weights.as_ref(), img, &res.best_image);
+    res.best_error = squared_error.sqrt();
+
+    let initial_shapes = res.shapes.len();
+
+    for step in 0..config.nshapes {
+        let candidate = search::search::<P, S>(img, &res.best_image, squared_error, config);
+
+        let improved = candidate.squared_error < squared_error;
+        if improved {
+            scanline::draw(&candidate.lines, &candidate.color, &mut res.best_image);
+
+            squared_error = candidate.squared_error;
+            res.best_error = squared_error.sqrt();
+            res.shapes.push((candidate.shape, candidate.color));
+        }
+
+        let last = step + 1 == config.nshapes;
+        let added = res.shapes.len() - initial_shapes;
+        let frame = match config.frame_every {
+            Some(k) => last || (improved && added.is_multiple_of(k)),
+            None => false,
+        };
+
+        on_progress(&Progress {
+            step: step + 1,
+            steps: config.nshapes,
+            shapes: res.shapes.len(),
+            error: res.best_error,
+            improved,
+            current: &res,
+            frame,
+        });
+    }
+
+    res
+}
+
+/// Find the color that, once blended with the given `alpha` onto `current`,
+/// minimizes the squared error, optionally weighted, with `target` over all the
+/// pixels covered by the scanlines. The returned color has the given `alpha`.
+/// Pixels without an alpha channel simply replace the current ones when drawn,
+/// therefore `alpha` is ignored for them. Returns None if the scanlines do not
+/// cover any pixel.
+pub fn optimal_color<P>(
+    target: &PrimifyImage<P>,
+    current: &PrimifyImage<P>,
+    lines: &[Scanline],
+    alpha: u8,
+    weights: Option<&WeightMap>,
+) -> Option<P>
+where
+    P: 'static + image::Pixel,
+    P::Subpixel: From<u8>,
+    f64: From<P::Subpixel>,
+{
+    // blending `color` with alpha `a` onto `c` gives `c + (color - c) * a` and
+    // that's exactly `t` when `color = c + (t - c) / a`. The average of that
+    // over all the pixels minimizes the squared error, the weighted average
+    // minimizes the weighted one.
+    let a = if has_alpha::<P>() {
+        <f64 as From<u8>>::from(alpha) / 255.0
+    } else {
+        1.0
+    };
+    let (width, height) = target.dimensions();
+
+    let mut sums = vec![0.0; usize::from(P::CHANNEL_COUNT)];
+    let mut total_weight = 0.0;
+    let mut count = 0_u64;
+
+    for line in lines {
+        for x in line.x1..=line.x2 {
+            let t = target.get_pixel(x, line.y).channels();
+            let c = current.get_pixel(x, line.y).channels();
+            let w = weights.map_or(1.0, |w| w.weight(x, line.y, width, height));
+
+            for ((sum, t), c) in sums.iter_mut().zip(t).zip(c) {
+                let (t, c) = (f64::from(*t), f64::from(*c));
+                *sum += w * (c + (t - c) / a);
+            }
+
+            total_weight += w;
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        return None;
+    }
+
+    // none of the pixels matter, any color would do
+    if total_weight == 0.0 {
+        return Some(
+            target
+                .get_pixel(lines[0].x1, lines[0].y)
+                .map_with_alpha(|c| c, |_| From::from(alpha)),
+        );
+    }
+
+    let max = f64::from(<P::Subpixel as num::Bounded>::max_value());
+
+    let mut color = *target.get_pixel(lines[0].x1, lines[0].y);
+    for (ch, sum) in color.channels_mut().iter_mut().zip(sums) {
+        // local import because otherwise using convert::From in the rest of
+        // the function will be a pain
+        use num::traits::cast::NumCast;
+
+        let avg = (sum / total_weight).round().max(0.0).min(max);
+        *ch = <P::Subpixel as NumCast>::from(avg).unwrap();
+    }
+
+    Some(color.map_with_alpha(|c| c, |_| From::from(alpha)))
+}
+
+/// Return whether the pixels of type `P` have an alpha channel, that is if
+/// they're blended onto the image instead of replacing its pixels.
+fn has_alpha<P: image::Pixel>() -> bool {
+    matches!(
+        P::COLOR_TYPE,
+        image::ColorType::GrayA(_) | image::ColorType::RGBA(_) | image::ColorType::BGRA(_)
+    )
+}
+
+fn get_dominant_color<I>(img: &I) -> Option<I::Pixel>
+where
+    I: image::GenericImageView,
+    I::Pixel: Eq + Hash,
+    <<I as image::GenericImageView>::Pixel as image::Pixel>::Subpixel: Ord,
+    u64: From<<<I as image::GenericImageView>::Pixel as image::Pixel>::Subpixel>,
+{
+    let pixels_it = img.pixels().map(|(_, _, p)| p);
+
+    quantize::quantize(pixels_it, 0).colors.into_iter().next()
+}
+
+impl FromStr for Alpha {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(Alpha::Auto);
+        }
+
+        match s.parse::<u8>() {
+            Ok(a) if a > 0 => Ok(Alpha::Fixed(a)),
+            _ => Err(format!(
+                "bad alpha {}, please use auto or a number between 1 and 255",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimal_color() {
+        let target = image::RgbImage::from_pixel(4, 4, image::Rgb([110, 200, 0]));
+        let current = image::RgbImage::from_pixel(4, 4, image::Rgb([100, 100, 100]));
+        let lines = [Scanline::new(1, 0, 3), Scanline::new(2, 1, 2)];
+
+        assert_eq!(
+            optimal_color(&target, &current, &lines, 255, None),
+            Some(image::Rgb([110, 200, 0]))
+        );
+
+        // rgb pixels replace the current ones, the alpha doesn't matter
+        assert_eq!(
+            optimal_color(&target, &current, &lines, 51, None),
+            Some(image::Rgb([110, 200, 0]))
+        );
+
+        // with a low alpha the color must be pushed further away from the
+        // current one, but it cannot go out of range.
+        let to_rgba = |img: &image::RgbImage| image::DynamicImage::ImageRgb8(img.clone()).to_rgba();
+        assert_eq!(
+            optimal_color(&to_rgba(&target), &to_rgba(&current), &lines, 51, None),
+            Some(image::Rgba([150, 255, 0, 51]))
+        );
+
+        assert_eq!(optimal_color(&target, &current, &[], 255, None), None);
+    }
+
+    #[test]
+    fn test_optimal_color_alpha() {
+        let target = image::RgbaImage::from_pixel(2, 2, image::Rgba([10, 20, 30, 255]));
+        let lines = [Scanline::new(0, 0, 1)];
+
+        assert_eq!(
+            optimal_color(&target, &target, &lines, 42, None),
+            Some(image::Rgba([10, 20, 30, 42]))
+        );
+    }
+
+    #[test]
+    fn test_progress_frames() {
+        let img = image::RgbImage::from_fn(16, 16, |x, y| {
+            if x < 8 && y < 8 {
+                image::Rgb([255, 0, 0])
+            } else {
+                image::Rgb([0, 0, 255])
+            }
+        });
+        let config = PrimifyConfig::new().shapes(10).mutations(10).frame_every(2);
+
+        let mut frames = vec![];
+        let prim = primify::<_, geo::BoundingBox<u32>>(&img, &config, |progress| {
+            assert_eq!(progress.current.best_image.dimensions(), (16, 16));
+
+            if progress.frame {
+                frames.push((progress.step, progress.shapes));
+            }
+        })
+        .unwrap();
+
+        assert_eq!(frames.last(), Some(&(10, prim.shapes.len())));
+        assert!(frames[..frames.len() - 1]
+            .iter()
+            .all(|(_, shapes)| shapes % 2 == 0));
+    }
+
+    #[test]
+    fn test_resume() {
+        let img = image::RgbaImage::from_fn(16, 16, |x, y| {
+            image::Rgba([x as u8 * 16, y as u8 * 16, 0, 255])
+        });
+        let config = PrimifyConfig::new().shapes(5).mutations(10);
+
+        let prim = primify::<_, geo::BoundingBox<u32>>(&img, &config, |_| {}).unwrap();
+        let list = prim.to_shape_list();
+
+        let resumed = resume(&img, &list, &config.clone().shapes(0), |_| {}).unwrap();
+        assert_eq!(resumed.shapes, prim.shapes);
+        assert_eq!(
+            resumed.best_image.into_raw(),
+            prim.best_image.clone().into_raw()
+        );
+        assert_eq!(resumed.best_error, prim.best_error);
+
+        let resumed = resume(&img, &list, &config, |_| {}).unwrap();
+        assert!(resumed.shapes.len() >= prim.shapes.len());
+        assert!(resumed.best_error <= prim.best_error);
+        assert_eq!(resumed.shapes[..prim.shapes.len()], prim.shapes[..]);
+
+        let small = image::RgbaImage::new(8, 8);
+        assert!(resume(&small, &list, &config, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_alpha_from_str() {
+        assert_eq!("auto".parse(), Ok(Alpha::Auto));
+        assert_eq!("128".parse(), Ok(Alpha::Fixed(128)));
+        assert!("0".parse::<Alpha>().is_err());
+        assert!("256".parse::<Alpha>().is_err());
+    }
+}
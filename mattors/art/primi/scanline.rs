@@ -0,0 +1,234 @@
+//! Scanline rasterization of the shapes used by `primi`. Every shape is turned
+//! into a set of horizontal runs of pixels which makes it easy to draw any
+//! shape in the same way.
+//!
+//! A pixel is covered by a shape when its center is inside it, where the right
+//! and bottom boundaries of the shape are excluded. Therefore a `w` x `h`
+//! rectangle covers exactly `w * h` pixels whether it's rotated or not.
+
+use std::fmt::Debug;
+
+use geo::PointU32;
+
+use crate::art::primi::PrimifyImage;
+use crate::drawing;
+
+/// An horizontal run of pixels on row `y` that goes from `x1` to `x2`, both
+/// inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scanline {
+    /// The row of the scanline.
+    pub y: u32,
+
+    /// The first column of the scanline.
+    pub x1: u32,
+
+    /// The last column of the scanline.
+    pub x2: u32,
+}
+
+impl Scanline {
+    /// Create a new `Scanline`.
+    pub fn new(y: u32, x1: u32, x2: u32) -> Self {
+        Scanline { y, x1, x2 }
+    }
+}
+
+/// Rasterize the polygon with the given vertices using the even-odd rule. The
+/// polygon can be concave and even self intersecting. Only the scanlines inside
+/// a `width` x `height` image are returned.
+pub fn polygon(points: &[(f64, f64)], width: u32, height: u32) -> Vec<Scanline> {
+    if points.len() < 3 || width == 0 || height == 0 {
+        return vec![];
+    }
+
+    let (ymin, ymax) = points.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(ymin, ymax), (_, y)| (ymin.min(*y), ymax.max(*y)),
+    );
+
+    let (ymin, ymax) = pixel_range(ymin, ymax, height);
+
+    let mut lines = vec![];
+    let mut xs = vec![];
+
+    for y in ymin..ymax {
+        let yc = f64::from(y) + 0.5;
+
+        xs.clear();
+        for (i, a) in points.iter().enumerate() {
+            let b = &points[(i + 1) % points.len()];
+
+            if (a.1 <= yc && yc < b.1) || (b.1 <= yc && yc < a.1) {
+                xs.push(a.0 + (yc - a.1) * (b.0 - a.0) / (b.1 - a.1));
+            }
+        }
+
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        for span in xs.chunks(2) {
+            if span.len() < 2 {
+                break;
+            }
+
+            if let Some(line) = clip_span(y, span[0], span[1], width) {
+                lines.push(line);
+            }
+        }
+    }
+
+    merge(lines)
+}
+
+/// Rasterize the axis aligned ellipse centered at `(cx, cy)` with the given
+/// radii.
+pub fn ellipse(cx: f64, cy: f64, rx: f64, ry: f64, width: u32, height: u32) -> Vec<Scanline> {
+    if width == 0 || height == 0 || rx < 0.0 || ry < 0.0 {
+        return vec![];
+    }
+
+    let (ymin, ymax) = pixel_range(cy - ry, cy + ry, height);
+
+    (ymin..ymax)
+        .filter_map(|y| {
+            let dy = f64::from(y) + 0.5 - cy;
+            let w = rx * (1.0 - (dy / ry).powi(2)).max(0.0).sqrt();
+
+            clip_span(y, cx - w, cx + w, width)
+        })
+        .collect()
+}
+
+/// Sort the scanlines and merge the ones that overlap so that no pixel is
+/// covered twice.
+pub fn merge(mut lines: Vec<Scanline>) -> Vec<Scanline> {
+    lines.sort_by_key(|l| (l.y, l.x1));
+
+    let mut merged: Vec<Scanline> = Vec::with_capacity(lines.len());
+    for line in lines {
+        match merged.last_mut() {
+            Some(last) if last.y == line.y && line.x1 <= last.x2 + 1 => {
+                last.x2 = last.x2.max(line.x2);
+            }
+            _ => merged.push(line),
+        }
+    }
+
+    merged
+}
+
+/// Blend the given pixel onto all the pixels covered by the scanlines.
+pub fn draw<P>(lines: &[Scanline], pix: &P, dst: &mut PrimifyImage<P>)
+where
+    P: 'static + image::Pixel + Debug,
+{
+    let mut drawer = drawing::Drawer::new_with_default_blending(dst);
+
+    for line in lines {
+        drawer.line(
+            PointU32::new(line.x1, line.y),
+            PointU32::new(line.x2, line.y),
+            pix,
+        );
+    }
+}
+
+fn clip_span(y: u32, x1: f64, x2: f64, width: u32) -> Option<Scanline> {
+    let (x1, x2) = pixel_range(x1, x2, width);
+
+    if x2 <= x1 {
+        return None;
+    }
+
+    Some(Scanline::new(y, x1, x2 - 1))
+}
+
+/// Return the half open range of the pixels whose centers are in `[start,
+/// end)` clipped to `[0, size)`.
+fn pixel_range(start: f64, end: f64, size: u32) -> (u32, u32) {
+    let clip = |v: f64| (v - 0.5).ceil().max(0.0).min(f64::from(size)) as u32;
+
+    (clip(start), clip(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygon_square() {
+        let square = [(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)];
+
+        assert_eq!(
+            polygon(&square, 10, 10),
+            vec![Scanline::new(1, 1, 2), Scanline::new(2, 1, 2)]
+        );
+    }
+
+    #[test]
+    fn test_polygon_clipped() {
+        let square = [(-5.0, -5.0), (20.0, -5.0), (20.0, 20.0), (-5.0, 20.0)];
+
+        assert_eq!(
+            polygon(&square, 3, 2),
+            vec![Scanline::new(0, 0, 2), Scanline::new(1, 0, 2)]
+        );
+
+        let outside = [(20.0, 20.0), (30.0, 20.0), (30.0, 30.0)];
+        assert_eq!(polygon(&outside, 3, 2), vec![]);
+    }
+
+    #[test]
+    fn test_polygon_concave() {
+        // a "U" shape whose rows at the top are split in two scanlines
+        let u = [
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 2.0),
+            (3.0, 2.0),
+            (3.0, 0.0),
+            (4.0, 0.0),
+            (4.0, 4.0),
+            (0.0, 4.0),
+        ];
+
+        let lines = polygon(&u, 10, 10);
+        assert_eq!(
+            &lines[..2],
+            &[Scanline::new(0, 0, 0), Scanline::new(0, 3, 3)]
+        );
+        assert_eq!(lines[lines.len() - 1], Scanline::new(3, 0, 3));
+    }
+
+    #[test]
+    fn test_ellipse() {
+        assert_eq!(
+            ellipse(2.0, 2.0, 2.0, 1.0, 10, 10),
+            vec![Scanline::new(1, 0, 3), Scanline::new(2, 0, 3)]
+        );
+
+        assert_eq!(ellipse(0.0, 0.0, 1.0, 0.0, 10, 10), vec![]);
+        assert_eq!(
+            ellipse(0.0, 1.0, 1.0, 1.0, 10, 10),
+            vec![Scanline::new(0, 0, 0), Scanline::new(1, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(
+            merge(vec![
+                Scanline::new(1, 4, 6),
+                Scanline::new(0, 0, 2),
+                Scanline::new(1, 0, 3),
+                Scanline::new(0, 5, 6),
+                Scanline::new(0, 1, 3),
+            ]),
+            vec![
+                Scanline::new(0, 0, 3),
+                Scanline::new(0, 5, 6),
+                Scanline::new(1, 0, 6)
+            ]
+        );
+    }
+}
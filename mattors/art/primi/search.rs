@@ -0,0 +1,463 @@
+//! The strategies `primify` can use to search for the next shape to add to the
+//! image. The error of every shape is computed incrementally only over the
+//! pixels the shape covers and the independent evaluations run in parallel.
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use rand::Rng;
+use rayon::prelude::*;
+
+use geo::utils::clamp;
+
+use crate::art::primi::error::{pixel_error, ErrorMetric, WeightMap};
+use crate::art::primi::{optimal_color, Alpha, PrimifyConfig, PrimifyImage, Scanline, Shape};
+
+/// How `primify` searches for the next shape to add.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    /// Generate `candidates` random shapes, keep the best one and improve it
+    /// with hill climbing. This is what
+    /// [primitive](https://github.com/fogleman/primitive) does.
+    HillClimbing {
+        /// How many random shapes to start from.
+        candidates: u32,
+    },
+
+    /// Run an independent hill climbing from `restarts` random shapes and keep
+    /// the best result.
+    RandomRestarts {
+        /// How many hill climbings to run.
+        restarts: u32,
+    },
+
+    /// Start from the best random shape and improve it using [simulated
+    /// annealing](https://en.wikipedia.org/wiki/Simulated_annealing). The
+    /// temperature decreases geometrically from `initial_temperature` to
+    /// `final_temperature` over the mutations. The temperatures are in the same
+    /// unit of the root mean square error of a single channel, or of a single
+    /// component of the color with `ErrorMetric::Lab`.
+    SimulatedAnnealing {
+        /// The temperature of the first mutation.
+        initial_temperature: f64,
+
+        /// The temperature of the last mutation.
+        final_temperature: f64,
+    },
+}
+
+/// A shape along with the color it should be drawn with and the squared error
+/// the image would have once the shape is drawn.
+#[derive(Clone, Debug)]
+pub struct Candidate<S, P> {
+    /// The shape itself.
+    pub shape: S,
+
+    /// The alpha the shape is drawn with.
+    pub alpha: u8,
+
+    /// The color the shape is drawn with, alpha included.
+    pub color: P,
+
+    /// The scanlines covered by the shape.
+    pub lines: Vec<Scanline>,
+
+    /// The squared error of the image once the shape is drawn.
+    pub squared_error: f64,
+}
+
+/// Everything the strategies need to evaluate shapes.
+struct Problem<'a, P: image::Pixel> {
+    origin: &'a PrimifyImage<P>,
+    current: &'a PrimifyImage<P>,
+    squared_error: f64,
+    config: &'a PrimifyConfig,
+}
+
+/// Search for the shape that reduces the squared error between `origin` and
+/// `current`, that is `squared_error`, the most according to the strategy in
+/// `config`.
+pub fn search<P, S>(
+    origin: &PrimifyImage<P>,
+    current: &PrimifyImage<P>,
+    squared_error: f64,
+    config: &PrimifyConfig,
+) -> Candidate<S, P>
+where
+    P: 'static + image::Pixel + Debug + Send + Sync,
+    P::Subpixel: From<u8> + Send + Sync,
+    f64: From<P::Subpixel>,
+    S: Shape,
+{
+    let problem = Problem {
+        origin,
+        current,
+        squared_error,
+        config,
+    };
+
+    match config.strategy {
+        Strategy::HillClimbing { candidates } => {
+            let start = problem.best_random_shape(candidates);
+            problem.hill_climb(start)
+        }
+        Strategy::RandomRestarts { restarts } => (0..restarts.max(1))
+            .into_par_iter()
+            .map(|_| {
+                let start = problem.best_random_shape(1);
+                problem.hill_climb(start)
+            })
+            .min_by(cmp_candidates)
+            .unwrap(),
+        Strategy::SimulatedAnnealing {
+            initial_temperature,
+            final_temperature,
+        } => {
+            let start = problem.best_random_shape(1);
+            problem.anneal(start, initial_temperature, final_temperature)
+        }
+    }
+}
+
+impl<'a, P> Problem<'a, P>
+where
+    P: 'static + image::Pixel + Debug + Send + Sync,
+    P::Subpixel: From<u8> + Send + Sync,
+    f64: From<P::Subpixel>,
+{
+    /// Evaluate `n` batches of random shapes and return the best one.
+    fn best_random_shape<S: Shape>(&self, n: u32) -> Candidate<S, P> {
+        let (width, height) = self.origin.dimensions();
+        let alpha = self.initial_alpha();
+
+        (0..n.max(1))
+            .into_par_iter()
+            .flat_map(|_| S::candidates(width, height, self.config.dx, self.config.dy))
+            .map(|shape| self.evaluate(shape, alpha))
+            .min_by(cmp_candidates)
+            .unwrap()
+    }
+
+    /// Improve the given candidate by keeping only the mutations that reduce
+    /// the error. The mutations are evaluated in parallel in batches of the
+    /// configured size and the best one of each batch becomes the starting
+    /// point of the next if it's an improvement.
+    fn hill_climb<S: Shape>(&self, mut best: Candidate<S, P>) -> Candidate<S, P> {
+        let batch_size = self.config.mutation_batch;
+
+        let mut remaining = self.config.nmutations;
+        while remaining > 0 {
+            let batch = remaining.min(batch_size);
+            remaining -= batch;
+
+            let mutated = (0..batch)
+                .into_par_iter()
+                .map(|_| self.mutate(&best))
+                .min_by(cmp_candidates);
+
+            if let Some(mutated) = mutated {
+                if mutated.squared_error < best.squared_error {
+                    best = mutated;
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Improve the given candidate with simulated annealing and return the best
+    /// candidate ever seen.
+    fn anneal<S: Shape>(
+        &self,
+        start: Candidate<S, P>,
+        initial_temperature: f64,
+        final_temperature: f64,
+    ) -> Candidate<S, P> {
+        let mut rng = rand::thread_rng();
+
+        let nmutations = self.config.nmutations;
+        let cooling = (final_temperature / initial_temperature)
+            .powf(1.0 / <f64 as From<u32>>::from(nmutations));
+
+        let mut temperature = initial_temperature;
+        let mut best = start.clone();
+        let mut current = start;
+
+        for _ in 0..nmutations {
+            let mutated = self.mutate(&current);
+            let delta = self.rmse(mutated.squared_error) - self.rmse(current.squared_error);
+
+            if delta < 0.0 || rng.gen::<f64>() < (-delta / temperature).exp() {
+                if mutated.squared_error < best.squared_error {
+                    best = mutated.clone();
+                }
+
+                current = mutated;
+            }
+
+            temperature *= cooling;
+        }
+
+        best
+    }
+
+    fn mutate<S: Shape>(&self, candidate: &Candidate<S, P>) -> Candidate<S, P> {
+        let (width, height) = self.origin.dimensions();
+
+        let shape = candidate
+            .shape
+            .mutate(width, height, self.config.dx, self.config.dy);
+
+        let alpha = match self.config.alpha {
+            Alpha::Fixed(a) => a,
+            Alpha::Auto => {
+                let mut rng = rand::thread_rng();
+                clamp(i64::from(candidate.alpha) + rng.gen_range(-10, 11), 1, 255) as u8
+            }
+        };
+
+        self.evaluate(shape, alpha)
+    }
+
+    fn initial_alpha(&self) -> u8 {
+        match self.config.alpha {
+            Alpha::Fixed(a) => a,
+            Alpha::Auto => 128,
+        }
+    }
+
+    /// Find the color of the given shape and compute the error the image would
+    /// have if the shape was drawn on `current` without actually drawing it.
+    fn evaluate<S: Shape>(&self, shape: S, alpha: u8) -> Candidate<S, P> {
+        evaluate_shape(
+            self.origin,
+            self.current,
+            self.squared_error,
+            self.config,
+            shape,
+            alpha,
+        )
+    }
+
+    /// Return the root mean square error of a single channel given the squared
+    /// error of the whole image.
+    fn rmse(&self, squared_error: f64) -> f64 {
+        let (width, height) = self.origin.dimensions();
+        let n = <f64 as From<u32>>::from(width)
+            * <f64 as From<u32>>::from(height)
+            * <f64 as From<u8>>::from(P::CHANNEL_COUNT);
+
+        (squared_error / n).sqrt()
+    }
+}
+
+/// Find the color of the given shape and compute the error the image would
+/// have if the shape was drawn on `current`, whose squared error is
+/// `squared_error`, without actually drawing it. The error is measured as
+/// configured in `config`.
+pub fn evaluate_shape<P, S>(
+    origin: &PrimifyImage<P>,
+    current: &PrimifyImage<P>,
+    squared_error: f64,
+    config: &PrimifyConfig,
+    shape: S,
+    alpha: u8,
+) -> Candidate<S, P>
+where
+    P: 'static + image::Pixel + Debug,
+    P::Subpixel: From<u8>,
+    f64: From<P::Subpixel>,
+    S: Shape,
+{
+    let lines = shape.rasterize(origin.width(), origin.height());
+
+    let weights = config.weights.as_ref();
+
+    let (color, squared_error) = match optimal_color(origin, current, &lines, alpha, weights) {
+        Some(color) => {
+            let delta =
+                squared_error_delta(origin, current, &lines, &color, config.metric, weights);
+
+            (color, squared_error + delta)
+        }
+        // an empty shape doesn't change the image, the color doesn't really
+        // matter
+        None => (
+            current
+                .get_pixel(0, 0)
+                .map_with_alpha(|c| c, |_| From::from(alpha)),
+            squared_error,
+        ),
+    };
+
+    Candidate {
+        shape,
+        alpha,
+        color,
+        lines,
+        squared_error,
+    }
+}
+
+/// Return how much the squared error between `target` and `current` would
+/// change if `color` was drawn over the scanlines. Only the covered pixels are
+/// visited.
+fn squared_error_delta<P>(
+    target: &PrimifyImage<P>,
+    current: &PrimifyImage<P>,
+    lines: &[Scanline],
+    color: &P,
+    metric: ErrorMetric,
+    weights: Option<&WeightMap>,
+) -> f64
+where
+    P: 'static + image::Pixel,
+    f64: From<P::Subpixel>,
+{
+    let (width, height) = target.dimensions();
+    let mut delta = 0.0;
+
+    for line in lines {
+        for x in line.x1..=line.x2 {
+            let w = weights.map_or(1.0, |w| w.weight(x, line.y, width, height));
+            if w == 0.0 {
+                continue;
+            }
+
+            let t = target.get_pixel(x, line.y);
+            let c = current.get_pixel(x, line.y);
+
+            let mut blended = *c;
+            blended.blend(color);
+
+            delta += w * (pixel_error(metric, t, &blended) - pixel_error(metric, t, c));
+        }
+    }
+
+    delta
+}
+
+fn cmp_candidates<S, P>(c1: &Candidate<S, P>, c2: &Candidate<S, P>) -> Ordering {
+    c1.squared_error.partial_cmp(&c2.squared_error).unwrap()
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    /// Parse the name of the strategy, the parameters are the default ones.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hill-climbing" => Ok(Strategy::HillClimbing { candidates: 16 }),
+            "restarts" => Ok(Strategy::RandomRestarts { restarts: 4 }),
+            "annealing" => Ok(Strategy::SimulatedAnnealing {
+                initial_temperature: 0.1,
+                final_temperature: 0.001,
+            }),
+            _ => Err(format!(
+                "unknown strategy {}, please use one of hill-climbing, restarts or annealing",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::art::primi::error::image_error;
+    use crate::art::primi::scanline;
+
+    fn images() -> (image::RgbaImage, image::RgbaImage) {
+        let target = image::RgbaImage::from_fn(8, 8, |x, y| {
+            image::Rgba([(x * 30) as u8, (y * 30) as u8, 100, 255])
+        });
+        let current = image::RgbaImage::from_pixel(8, 8, image::Rgba([50, 60, 70, 255]));
+
+        (target, current)
+    }
+
+    fn squared_error(target: &image::RgbaImage, current: &image::RgbaImage) -> f64 {
+        target
+            .iter()
+            .zip(current.iter())
+            .map(|(t, c)| (f64::from(*t) - f64::from(*c)).powi(2))
+            .sum()
+    }
+
+    #[test]
+    fn test_incremental_error() {
+        let (target, current) = images();
+        let err = squared_error(&target, &current);
+
+        let shape = geo::Triangle::new(
+            geo::PointU32::new(0, 0),
+            geo::PointU32::new(7, 2),
+            geo::PointU32::new(3, 7),
+        );
+        let candidate = evaluate_shape(&target, &current, err, &PrimifyConfig::new(), shape, 128);
+
+        let mut drawn = current.clone();
+        scanline::draw(&candidate.lines, &candidate.color, &mut drawn);
+
+        let expected = squared_error(&target, &drawn);
+        assert!((candidate.squared_error - expected).abs() < 1e-6);
+        assert!(candidate.squared_error < err);
+    }
+
+    #[test]
+    fn test_incremental_weighted_lab_error() {
+        let (target, current) = images();
+        let mask = image::GrayImage::from_fn(4, 4, |x, y| image::Luma([(x * 60 + y * 20) as u8]));
+        let weights = WeightMap::from_mask(&mask);
+        let config = PrimifyConfig::new()
+            .metric(ErrorMetric::Lab)
+            .weights(weights.clone());
+
+        let err = image_error(ErrorMetric::Lab, Some(&weights), &target, &current);
+
+        let shape =
+            geo::BoundingBox::from_points(&[geo::PointU32::new(1, 1), geo::PointU32::new(6, 5)]);
+        let candidate = evaluate_shape(&target, &current, err, &config, shape, 200);
+
+        let mut drawn = current.clone();
+        scanline::draw(&candidate.lines, &candidate.color, &mut drawn);
+
+        let expected = image_error(ErrorMetric::Lab, Some(&weights), &target, &drawn);
+        assert!((candidate.squared_error - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_strategies_improve_the_error() {
+        let (target, current) = images();
+        let err = squared_error(&target, &current);
+
+        let strategies = [
+            "hill-climbing".parse().unwrap(),
+            "restarts".parse().unwrap(),
+            "annealing".parse().unwrap(),
+        ];
+
+        for strategy in &strategies {
+            let config = PrimifyConfig::new()
+                .mutations(20)
+                .delta(4, 4)
+                .strategy(*strategy);
+
+            let candidate: Candidate<geo::Triangle<u32>, _> =
+                search(&target, &current, err, &config);
+
+            assert!(candidate.squared_error <= err);
+        }
+    }
+
+    #[test]
+    fn test_strategy_from_str() {
+        assert_eq!(
+            "restarts".parse(),
+            Ok(Strategy::RandomRestarts { restarts: 4 })
+        );
+        assert!("genetic".parse::<Strategy>().is_err());
+    }
+}
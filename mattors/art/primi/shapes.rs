@@ -0,0 +1,1047 @@
+//! All the shapes that `primi` can use to approximate an image. Each of them
+//! implements the `Shape` trait and it's rasterized into scanlines.
+
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use geo::utils::clamp;
+use geo::{BoundingBox, PointF64, PointU32, Transform};
+
+use crate::art::primi::scanline::{self, Scanline};
+use crate::art::primi::Shape;
+
+/// How many segments are used to approximate curved shapes that cannot be
+/// rasterized directly.
+const CURVE_SEGMENTS: u32 = 32;
+
+/// The kind of shapes that `primi` can use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShapeKind {
+    /// `geo::Triangle`.
+    Triangle,
+
+    /// Axis aligned rectangle, i.e. `geo::BoundingBox`.
+    Rectangle,
+
+    /// `RotatedRectangle`.
+    RotatedRectangle,
+
+    /// `Ellipse`.
+    Ellipse,
+
+    /// `RotatedEllipse`.
+    RotatedEllipse,
+
+    /// `Circle`.
+    Circle,
+
+    /// `Quadrilateral`.
+    Quadrilateral,
+
+    /// `ThickLine`.
+    Line,
+
+    /// `QuadraticBezier`.
+    QuadraticBezier,
+
+    /// Choose the best kind of shape at each step, see `AnyShape`.
+    Mixed,
+}
+
+/// Rectangle rotated by `angle` degrees around its `center`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RotatedRectangle {
+    /// The center of the rectangle.
+    pub center: PointU32,
+
+    /// Half of the width of the rectangle.
+    pub half_width: u32,
+
+    /// Half of the height of the rectangle.
+    pub half_height: u32,
+
+    /// Rotation angle in degrees.
+    pub angle: u32,
+}
+
+/// Axis aligned ellipse.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Ellipse {
+    /// The center of the ellipse.
+    pub center: PointU32,
+
+    /// The radius along the x axis.
+    pub rx: u32,
+
+    /// The radius along the y axis.
+    pub ry: u32,
+}
+
+/// Ellipse rotated by `angle` degrees around its `center`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RotatedEllipse {
+    /// The center of the ellipse.
+    pub center: PointU32,
+
+    /// The radius along the x axis before the rotation.
+    pub rx: u32,
+
+    /// The radius along the y axis before the rotation.
+    pub ry: u32,
+
+    /// Rotation angle in degrees.
+    pub angle: u32,
+}
+
+/// Simple circle.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Circle {
+    /// The center of the circle.
+    pub center: PointU32,
+
+    /// The radius of the circle.
+    pub radius: u32,
+}
+
+/// Generic quadrilateral, it can be concave or even self intersecting.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Quadrilateral {
+    /// The vertices of the quadrilateral.
+    pub points: [PointU32; 4],
+}
+
+/// Straight line with the given thickness.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ThickLine {
+    /// Start of the line.
+    pub start: PointU32,
+
+    /// End of the line.
+    pub end: PointU32,
+
+    /// Thickness of the line.
+    pub width: u32,
+}
+
+/// Stroke of a [quadratic Bézier
+/// curve](https://en.wikipedia.org/wiki/B%C3%A9zier_curve#Quadratic_B%C3%A9zier_curves)
+/// with the given thickness.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct QuadraticBezier {
+    /// Start of the curve.
+    pub start: PointU32,
+
+    /// Control point of the curve.
+    pub control: PointU32,
+
+    /// End of the curve.
+    pub end: PointU32,
+
+    /// Thickness of the stroke.
+    pub width: u32,
+}
+
+/// A shape of any of the supported kinds. When used in `primify` every step
+/// starts with one random shape for each kind and keeps the best one, like
+/// [primitive](https://github.com/fogleman/primitive) does in its mixed mode.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum AnyShape {
+    /// `geo::Triangle`.
+    Triangle(geo::Triangle<u32>),
+
+    /// Axis aligned rectangle.
+    Rectangle(BoundingBox<u32>),
+
+    /// `RotatedRectangle`.
+    RotatedRectangle(RotatedRectangle),
+
+    /// `Ellipse`.
+    Ellipse(Ellipse),
+
+    /// `RotatedEllipse`.
+    RotatedEllipse(RotatedEllipse),
+
+    /// `Circle`.
+    Circle(Circle),
+
+    /// `Quadrilateral`.
+    Quadrilateral(Quadrilateral),
+
+    /// `ThickLine`.
+    Line(ThickLine),
+
+    /// `QuadraticBezier`.
+    QuadraticBezier(QuadraticBezier),
+}
+
+impl Shape for geo::Triangle<u32> {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let p1 = random_point(&mut rng, width, height);
+        let p2 = random_point_around(&mut rng, p1, width, height, dx, dy);
+        let p3 = random_point_around(&mut rng, p1, width, height, dx, dy);
+
+        geo::Triangle::new(p1, p2, p3)
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let mut tri = self.clone();
+        let pt_ix = rng.gen_range(0, tri.points.len());
+
+        tri.points[pt_ix] = random_point_around(&mut rng, tri.points[pt_ix], width, height, dx, dy);
+
+        tri
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        scanline::polygon(&to_f64_points(&self.points), width, height)
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        svg_polygon(&self.points, color, opacity)
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        geo::Triangle {
+            points: [
+                upscale_point(self.points[0], factor),
+                upscale_point(self.points[1], factor),
+                upscale_point(self.points[2], factor),
+            ],
+        }
+    }
+}
+
+impl Shape for BoundingBox<u32> {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let p1 = random_point(&mut rng, width, height);
+        let p2 = random_point_around(&mut rng, p1, width, height, dx, dy);
+
+        BoundingBox::from_points(&[p1, p2])
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let mut corners = [*self.min(), *self.max()];
+        let ix = rng.gen_range(0, corners.len());
+        corners[ix] = random_point_around(&mut rng, corners[ix], width, height, dx, dy);
+
+        BoundingBox::from_points(&corners)
+    }
+
+    /// The box covers the pixels from its min corner included to its max
+    /// corner excluded like the other shapes.
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        if self.is_empty() || self.min().x >= width || self.min().y >= height {
+            return vec![];
+        }
+
+        let x2 = self.max().x.min(width);
+        let y2 = self.max().y.min(height);
+        if x2 <= self.min().x {
+            return vec![];
+        }
+
+        (self.min().y..y2)
+            .map(|y| Scanline::new(y, self.min().x, x2 - 1))
+            .collect()
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        format!(
+            r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+            self.min().x,
+            self.min().y,
+            self.max().x - self.min().x,
+            self.max().y - self.min().y,
+            svg_fill(color, opacity)
+        )
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        BoundingBox::from_points(&[
+            upscale_point(*self.min(), factor),
+            upscale_point(*self.max(), factor),
+        ])
+    }
+}
+
+impl Shape for RotatedRectangle {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        RotatedRectangle {
+            center: random_point(&mut rng, width, height),
+            half_width: rng.gen_range(1, dx.max(1) + 1),
+            half_height: rng.gen_range(1, dy.max(1) + 1),
+            angle: rng.gen_range(0, 360),
+        }
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut rect = self.clone();
+
+        match rng.gen_range(0, 3) {
+            0 => rect.center = random_point_around(&mut rng, rect.center, width, height, dx, dy),
+            1 => {
+                rect.half_width = jitter(&mut rng, rect.half_width, dx, 1, width);
+                rect.half_height = jitter(&mut rng, rect.half_height, dy, 1, height);
+            }
+            _ => rect.angle = mutate_angle(&mut rng, rect.angle),
+        }
+
+        rect
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        let hw = f64::from(self.half_width);
+        let hh = f64::from(self.half_height);
+
+        let corners = [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)]
+            .iter()
+            .map(|&(x, y)| rotate_around(self.center, x, y, self.angle))
+            .collect::<Vec<_>>();
+
+        scanline::polygon(&corners, width, height)
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        format!(
+            r#"<rect x="{}" y="{}" width="{}" height="{}" transform="rotate({} {} {})" {}/>"#,
+            i64::from(self.center.x) - i64::from(self.half_width),
+            i64::from(self.center.y) - i64::from(self.half_height),
+            self.half_width * 2,
+            self.half_height * 2,
+            self.angle,
+            self.center.x,
+            self.center.y,
+            svg_fill(color, opacity)
+        )
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        RotatedRectangle {
+            center: upscale_point(self.center, factor),
+            half_width: self.half_width * factor,
+            half_height: self.half_height * factor,
+            angle: self.angle,
+        }
+    }
+}
+
+impl Shape for Ellipse {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        Ellipse {
+            center: random_point(&mut rng, width, height),
+            rx: rng.gen_range(1, dx.max(1) + 1),
+            ry: rng.gen_range(1, dy.max(1) + 1),
+        }
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut ellipse = self.clone();
+
+        match rng.gen_range(0, 3) {
+            0 => {
+                ellipse.center =
+                    random_point_around(&mut rng, ellipse.center, width, height, dx, dy)
+            }
+            1 => ellipse.rx = jitter(&mut rng, ellipse.rx, dx, 1, width),
+            _ => ellipse.ry = jitter(&mut rng, ellipse.ry, dy, 1, height),
+        }
+
+        ellipse
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        scanline::ellipse(
+            f64::from(self.center.x),
+            f64::from(self.center.y),
+            f64::from(self.rx),
+            f64::from(self.ry),
+            width,
+            height,
+        )
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        format!(
+            r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {}/>"#,
+            self.center.x,
+            self.center.y,
+            self.rx,
+            self.ry,
+            svg_fill(color, opacity)
+        )
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        Ellipse {
+            center: upscale_point(self.center, factor),
+            rx: self.rx * factor,
+            ry: self.ry * factor,
+        }
+    }
+}
+
+impl Shape for RotatedEllipse {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        RotatedEllipse {
+            center: random_point(&mut rng, width, height),
+            rx: rng.gen_range(1, dx.max(1) + 1),
+            ry: rng.gen_range(1, dy.max(1) + 1),
+            angle: rng.gen_range(0, 360),
+        }
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut ellipse = self.clone();
+
+        match rng.gen_range(0, 4) {
+            0 => {
+                ellipse.center =
+                    random_point_around(&mut rng, ellipse.center, width, height, dx, dy)
+            }
+            1 => ellipse.rx = jitter(&mut rng, ellipse.rx, dx, 1, width),
+            2 => ellipse.ry = jitter(&mut rng, ellipse.ry, dy, 1, height),
+            _ => ellipse.angle = mutate_angle(&mut rng, ellipse.angle),
+        }
+
+        ellipse
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        let rx = f64::from(self.rx);
+        let ry = f64::from(self.ry);
+
+        let points = (0..CURVE_SEGMENTS)
+            .map(|i| {
+                let t = 2.0 * PI * f64::from(i) / f64::from(CURVE_SEGMENTS);
+
+                rotate_around(self.center, rx * t.cos(), ry * t.sin(), self.angle)
+            })
+            .collect::<Vec<_>>();
+
+        scanline::polygon(&points, width, height)
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        format!(
+            r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" transform="rotate({} {} {})" {}/>"#,
+            self.center.x,
+            self.center.y,
+            self.rx,
+            self.ry,
+            self.angle,
+            self.center.x,
+            self.center.y,
+            svg_fill(color, opacity)
+        )
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        RotatedEllipse {
+            center: upscale_point(self.center, factor),
+            rx: self.rx * factor,
+            ry: self.ry * factor,
+            angle: self.angle,
+        }
+    }
+}
+
+impl Shape for Circle {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        Circle {
+            center: random_point(&mut rng, width, height),
+            radius: rng.gen_range(1, dx.max(dy).max(1) + 1),
+        }
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut circle = self.clone();
+
+        if rng.gen() {
+            circle.center = random_point_around(&mut rng, circle.center, width, height, dx, dy);
+        } else {
+            circle.radius = jitter(&mut rng, circle.radius, dx.max(dy), 1, width.max(height));
+        }
+
+        circle
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        let r = f64::from(self.radius);
+
+        scanline::ellipse(
+            f64::from(self.center.x),
+            f64::from(self.center.y),
+            r,
+            r,
+            width,
+            height,
+        )
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        format!(
+            r#"<circle cx="{}" cy="{}" r="{}" {}/>"#,
+            self.center.x,
+            self.center.y,
+            self.radius,
+            svg_fill(color, opacity)
+        )
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        Circle {
+            center: upscale_point(self.center, factor),
+            radius: self.radius * factor,
+        }
+    }
+}
+
+impl Shape for Quadrilateral {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let p1 = random_point(&mut rng, width, height);
+        let mut points = [p1; 4];
+        for pt in &mut points[1..] {
+            *pt = random_point_around(&mut rng, p1, width, height, dx, dy);
+        }
+
+        Quadrilateral { points }
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let mut quad = self.clone();
+        let pt_ix = rng.gen_range(0, quad.points.len());
+
+        quad.points[pt_ix] =
+            random_point_around(&mut rng, quad.points[pt_ix], width, height, dx, dy);
+
+        quad
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        scanline::polygon(&to_f64_points(&self.points), width, height)
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        svg_polygon(&self.points, color, opacity)
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        let mut points = self.points;
+        for pt in &mut points {
+            *pt = upscale_point(*pt, factor);
+        }
+
+        Quadrilateral { points }
+    }
+}
+
+impl Shape for ThickLine {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let start = random_point(&mut rng, width, height);
+        let end = random_point_around(&mut rng, start, width, height, dx, dy);
+
+        ThickLine {
+            start,
+            end,
+            width: rng.gen_range(1, max_stroke_width(dx, dy) + 1),
+        }
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut line = self.clone();
+
+        match rng.gen_range(0, 3) {
+            0 => line.start = random_point_around(&mut rng, line.start, width, height, dx, dy),
+            1 => line.end = random_point_around(&mut rng, line.end, width, height, dx, dy),
+            _ => {
+                let max_width = max_stroke_width(dx, dy);
+                line.width = jitter(&mut rng, line.width, max_width, 1, max_width + 1);
+            }
+        }
+
+        line
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        stroke(
+            &[to_f64_point(self.start), to_f64_point(self.end)],
+            self.width,
+            width,
+            height,
+        )
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        format!(
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+            self.start.x,
+            self.start.y,
+            self.end.x,
+            self.end.y,
+            svg_stroke(color, opacity, self.width)
+        )
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        ThickLine {
+            start: upscale_point(self.start, factor),
+            end: upscale_point(self.end, factor),
+            width: self.width * factor,
+        }
+    }
+}
+
+impl Shape for QuadraticBezier {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let start = random_point(&mut rng, width, height);
+        let control = random_point_around(&mut rng, start, width, height, dx, dy);
+        let end = random_point_around(&mut rng, start, width, height, dx, dy);
+
+        QuadraticBezier {
+            start,
+            control,
+            end,
+            width: rng.gen_range(1, max_stroke_width(dx, dy) + 1),
+        }
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut curve = self.clone();
+
+        match rng.gen_range(0, 4) {
+            0 => curve.start = random_point_around(&mut rng, curve.start, width, height, dx, dy),
+            1 => {
+                curve.control = random_point_around(&mut rng, curve.control, width, height, dx, dy)
+            }
+            2 => curve.end = random_point_around(&mut rng, curve.end, width, height, dx, dy),
+            _ => {
+                let max_width = max_stroke_width(dx, dy);
+                curve.width = jitter(&mut rng, curve.width, max_width, 1, max_width + 1);
+            }
+        }
+
+        curve
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        let (x0, y0) = to_f64_point(self.start);
+        let (x1, y1) = to_f64_point(self.control);
+        let (x2, y2) = to_f64_point(self.end);
+
+        let points = (0..=CURVE_SEGMENTS)
+            .map(|i| {
+                let t = f64::from(i) / f64::from(CURVE_SEGMENTS);
+                let mt = 1.0 - t;
+
+                (
+                    mt * mt * x0 + 2.0 * mt * t * x1 + t * t * x2,
+                    mt * mt * y0 + 2.0 * mt * t * y1 + t * t * y2,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        stroke(&points, self.width, width, height)
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        format!(
+            r#"<path d="M {} {} Q {} {} {} {}" fill="none" {}/>"#,
+            self.start.x,
+            self.start.y,
+            self.control.x,
+            self.control.y,
+            self.end.x,
+            self.end.y,
+            svg_stroke(color, opacity, self.width)
+        )
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        QuadraticBezier {
+            start: upscale_point(self.start, factor),
+            control: upscale_point(self.control, factor),
+            end: upscale_point(self.end, factor),
+            width: self.width * factor,
+        }
+    }
+}
+
+impl Shape for AnyShape {
+    fn random(width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        let kind = rand::thread_rng().gen_range(0, ShapeKind::MIXABLE.len());
+
+        AnyShape::random_of_kind(ShapeKind::MIXABLE[kind], width, height, dx, dy)
+    }
+
+    fn candidates(width: u32, height: u32, dx: u32, dy: u32) -> Vec<Self> {
+        ShapeKind::MIXABLE
+            .iter()
+            .map(|kind| AnyShape::random_of_kind(*kind, width, height, dx, dy))
+            .collect()
+    }
+
+    fn mutate(&self, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        match self {
+            AnyShape::Triangle(s) => AnyShape::Triangle(s.mutate(width, height, dx, dy)),
+            AnyShape::Rectangle(s) => AnyShape::Rectangle(s.mutate(width, height, dx, dy)),
+            AnyShape::RotatedRectangle(s) => {
+                AnyShape::RotatedRectangle(s.mutate(width, height, dx, dy))
+            }
+            AnyShape::Ellipse(s) => AnyShape::Ellipse(s.mutate(width, height, dx, dy)),
+            AnyShape::RotatedEllipse(s) => {
+                AnyShape::RotatedEllipse(s.mutate(width, height, dx, dy))
+            }
+            AnyShape::Circle(s) => AnyShape::Circle(s.mutate(width, height, dx, dy)),
+            AnyShape::Quadrilateral(s) => AnyShape::Quadrilateral(s.mutate(width, height, dx, dy)),
+            AnyShape::Line(s) => AnyShape::Line(s.mutate(width, height, dx, dy)),
+            AnyShape::QuadraticBezier(s) => {
+                AnyShape::QuadraticBezier(s.mutate(width, height, dx, dy))
+            }
+        }
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> Vec<Scanline> {
+        match self {
+            AnyShape::Triangle(s) => s.rasterize(width, height),
+            AnyShape::Rectangle(s) => s.rasterize(width, height),
+            AnyShape::RotatedRectangle(s) => s.rasterize(width, height),
+            AnyShape::Ellipse(s) => s.rasterize(width, height),
+            AnyShape::RotatedEllipse(s) => s.rasterize(width, height),
+            AnyShape::Circle(s) => s.rasterize(width, height),
+            AnyShape::Quadrilateral(s) => s.rasterize(width, height),
+            AnyShape::Line(s) => s.rasterize(width, height),
+            AnyShape::QuadraticBezier(s) => s.rasterize(width, height),
+        }
+    }
+
+    fn to_svg(&self, color: &str, opacity: f64) -> String {
+        match self {
+            AnyShape::Triangle(s) => s.to_svg(color, opacity),
+            AnyShape::Rectangle(s) => s.to_svg(color, opacity),
+            AnyShape::RotatedRectangle(s) => s.to_svg(color, opacity),
+            AnyShape::Ellipse(s) => s.to_svg(color, opacity),
+            AnyShape::RotatedEllipse(s) => s.to_svg(color, opacity),
+            AnyShape::Circle(s) => s.to_svg(color, opacity),
+            AnyShape::Quadrilateral(s) => s.to_svg(color, opacity),
+            AnyShape::Line(s) => s.to_svg(color, opacity),
+            AnyShape::QuadraticBezier(s) => s.to_svg(color, opacity),
+        }
+    }
+
+    fn upscale(&self, factor: u32) -> Self {
+        match self {
+            AnyShape::Triangle(s) => AnyShape::Triangle(s.upscale(factor)),
+            AnyShape::Rectangle(s) => AnyShape::Rectangle(s.upscale(factor)),
+            AnyShape::RotatedRectangle(s) => AnyShape::RotatedRectangle(s.upscale(factor)),
+            AnyShape::Ellipse(s) => AnyShape::Ellipse(s.upscale(factor)),
+            AnyShape::RotatedEllipse(s) => AnyShape::RotatedEllipse(s.upscale(factor)),
+            AnyShape::Circle(s) => AnyShape::Circle(s.upscale(factor)),
+            AnyShape::Quadrilateral(s) => AnyShape::Quadrilateral(s.upscale(factor)),
+            AnyShape::Line(s) => AnyShape::Line(s.upscale(factor)),
+            AnyShape::QuadraticBezier(s) => AnyShape::QuadraticBezier(s.upscale(factor)),
+        }
+    }
+}
+
+impl AnyShape {
+    /// Generate a random shape of the given kind. `ShapeKind::Mixed` picks a
+    /// random kind.
+    pub fn random_of_kind(kind: ShapeKind, width: u32, height: u32, dx: u32, dy: u32) -> Self {
+        match kind {
+            ShapeKind::Triangle => AnyShape::Triangle(Shape::random(width, height, dx, dy)),
+            ShapeKind::Rectangle => AnyShape::Rectangle(Shape::random(width, height, dx, dy)),
+            ShapeKind::RotatedRectangle => {
+                AnyShape::RotatedRectangle(Shape::random(width, height, dx, dy))
+            }
+            ShapeKind::Ellipse => AnyShape::Ellipse(Shape::random(width, height, dx, dy)),
+            ShapeKind::RotatedEllipse => {
+                AnyShape::RotatedEllipse(Shape::random(width, height, dx, dy))
+            }
+            ShapeKind::Circle => AnyShape::Circle(Shape::random(width, height, dx, dy)),
+            ShapeKind::Quadrilateral => {
+                AnyShape::Quadrilateral(Shape::random(width, height, dx, dy))
+            }
+            ShapeKind::Line => AnyShape::Line(Shape::random(width, height, dx, dy)),
+            ShapeKind::QuadraticBezier => {
+                AnyShape::QuadraticBezier(Shape::random(width, height, dx, dy))
+            }
+            ShapeKind::Mixed => Shape::random(width, height, dx, dy),
+        }
+    }
+}
+
+impl ShapeKind {
+    /// All the kinds of shapes that `ShapeKind::Mixed` chooses from.
+    pub const MIXABLE: [ShapeKind; 9] = [
+        ShapeKind::Triangle,
+        ShapeKind::Rectangle,
+        ShapeKind::RotatedRectangle,
+        ShapeKind::Ellipse,
+        ShapeKind::RotatedEllipse,
+        ShapeKind::Circle,
+        ShapeKind::Quadrilateral,
+        ShapeKind::Line,
+        ShapeKind::QuadraticBezier,
+    ];
+}
+
+impl FromStr for ShapeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "triangle" => Ok(ShapeKind::Triangle),
+            "rectangle" => Ok(ShapeKind::Rectangle),
+            "rotated-rectangle" => Ok(ShapeKind::RotatedRectangle),
+            "ellipse" => Ok(ShapeKind::Ellipse),
+            "rotated-ellipse" => Ok(ShapeKind::RotatedEllipse),
+            "circle" => Ok(ShapeKind::Circle),
+            "quadrilateral" => Ok(ShapeKind::Quadrilateral),
+            "line" => Ok(ShapeKind::Line),
+            "bezier" => Ok(ShapeKind::QuadraticBezier),
+            "mixed" => Ok(ShapeKind::Mixed),
+            _ => Err(format!(
+                "unknown shape {}, please use one of triangle, rectangle, \
+                 rotated-rectangle, ellipse, rotated-ellipse, circle, quadrilateral, \
+                 line, bezier or mixed",
+                s
+            )),
+        }
+    }
+}
+
+/// Rasterize a polyline with the given thickness as the union of the
+/// rectangles around each of its segments.
+fn stroke(points: &[(f64, f64)], thickness: u32, width: u32, height: u32) -> Vec<Scanline> {
+    let hw = f64::from(thickness) / 2.0;
+
+    let lines = points
+        .windows(2)
+        .flat_map(|seg| {
+            let (x0, y0) = seg[0];
+            let (x1, y1) = seg[1];
+
+            let len = (x1 - x0).hypot(y1 - y0);
+
+            // normal and direction of the segment scaled by half of the
+            // thickness, degenerate segments become squares.
+            let (nx, ny, tx, ty) = if len == 0.0 {
+                (0.0, hw, hw, 0.0)
+            } else {
+                let (ux, uy) = ((x1 - x0) / len, (y1 - y0) / len);
+                (-uy * hw, ux * hw, ux * hw, uy * hw)
+            };
+
+            let corners = [
+                (x0 - tx + nx, y0 - ty + ny),
+                (x1 + tx + nx, y1 + ty + ny),
+                (x1 + tx - nx, y1 + ty - ny),
+                (x0 - tx - nx, y0 - ty - ny),
+            ];
+
+            scanline::polygon(&corners, width, height)
+        })
+        .collect();
+
+    scanline::merge(lines)
+}
+
+fn svg_polygon(points: &[PointU32], color: &str, opacity: f64) -> String {
+    let points = points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(
+        r#"<polygon points="{}" {}/>"#,
+        points,
+        svg_fill(color, opacity)
+    )
+}
+
+fn svg_fill(color: &str, opacity: f64) -> String {
+    format!(r#"fill="{}" fill-opacity="{:.3}""#, color, opacity)
+}
+
+fn svg_stroke(color: &str, opacity: f64, width: u32) -> String {
+    format!(
+        r#"stroke="{}" stroke-opacity="{:.3}" stroke-width="{}" stroke-linecap="square""#,
+        color, opacity, width
+    )
+}
+
+fn random_point<R: Rng>(rng: &mut R, width: u32, height: u32) -> PointU32 {
+    PointU32::new(rng.gen_range(0, width), rng.gen_range(0, height))
+}
+
+fn random_point_around<R: Rng>(
+    rng: &mut R,
+    pt: PointU32,
+    width: u32,
+    height: u32,
+    dx: u32,
+    dy: u32,
+) -> PointU32 {
+    PointU32::new(
+        jitter(rng, pt.x, dx, 0, width),
+        jitter(rng, pt.y, dy, 0, height),
+    )
+}
+
+/// Move `v` by a random amount in `[-delta, delta]` and clamp the result in
+/// `[min, max)`.
+fn jitter<R: Rng>(rng: &mut R, v: u32, delta: u32, min: u32, max: u32) -> u32 {
+    let delta = i64::from(delta);
+    let v = i64::from(v) + rng.gen_range(-delta, delta + 1);
+
+    clamp(v, min, max.max(min + 1) - 1)
+}
+
+fn mutate_angle<R: Rng>(rng: &mut R, angle: u32) -> u32 {
+    (angle + rng.gen_range(0, 30) + 345) % 360
+}
+
+fn max_stroke_width(dx: u32, dy: u32) -> u32 {
+    (dx.min(dy) / 4).max(1)
+}
+
+fn rotate_around(center: PointU32, x: f64, y: f64, angle: u32) -> (f64, f64) {
+    let transform = Transform::rotation(f64::from(angle).to_radians())
+        .translate(f64::from(center.x), f64::from(center.y));
+    let pt = transform.apply(&PointF64::new(x, y));
+
+    (pt.x, pt.y)
+}
+
+fn upscale_point(pt: PointU32, factor: u32) -> PointU32 {
+    PointU32::new(pt.x * factor, pt.y * factor)
+}
+
+fn to_f64_point(pt: PointU32) -> (f64, f64) {
+    (f64::from(pt.x), f64::from(pt.y))
+}
+
+fn to_f64_points(pts: &[PointU32]) -> Vec<(f64, f64)> {
+    pts.iter().cloned().map(to_f64_point).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape_kind_from_str() {
+        assert_eq!("triangle".parse(), Ok(ShapeKind::Triangle));
+        assert_eq!("bezier".parse(), Ok(ShapeKind::QuadraticBezier));
+        assert_eq!("mixed".parse(), Ok(ShapeKind::Mixed));
+        assert!("hexagon".parse::<ShapeKind>().is_err());
+    }
+
+    #[test]
+    fn test_random_shapes_are_inside_the_image() {
+        for _ in 0..50 {
+            for shape in AnyShape::candidates(40, 30, 16, 16) {
+                let shape = shape.mutate(40, 30, 16, 16);
+
+                for line in shape.rasterize(40, 30) {
+                    assert!(line.y < 30);
+                    assert!(line.x1 <= line.x2 && line.x2 < 40);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rasterize_rectangles() {
+        let rect = BoundingBox::from_points(&[PointU32::new(1, 1), PointU32::new(3, 4)]);
+        assert_eq!(
+            rect.rasterize(10, 10),
+            vec![
+                Scanline::new(1, 1, 2),
+                Scanline::new(2, 1, 2),
+                Scanline::new(3, 1, 2)
+            ]
+        );
+        assert_eq!(
+            rect.rasterize(2, 3),
+            vec![Scanline::new(1, 1, 1), Scanline::new(2, 1, 1)]
+        );
+
+        let outside = BoundingBox::from_points(&[PointU32::new(5, 1), PointU32::new(8, 3)]);
+        assert_eq!(outside.rasterize(5, 10), vec![]);
+        assert_eq!(outside.rasterize(10, 1), vec![]);
+
+        // the same rectangle covers the same number of pixels at any multiple
+        // of 90 degrees
+        let covered = |lines: Vec<Scanline>| -> u32 { lines.iter().map(|l| l.x2 - l.x1 + 1).sum() };
+        for angle in &[0, 90, 180, 270] {
+            let rotated = RotatedRectangle {
+                center: PointU32::new(5, 5),
+                half_width: 1,
+                half_height: 3,
+                angle: *angle,
+            };
+            assert_eq!(covered(rotated.rasterize(10, 10)), 12);
+        }
+
+        let rotated = RotatedRectangle {
+            center: PointU32::new(5, 5),
+            half_width: 1,
+            half_height: 3,
+            angle: 90,
+        };
+        assert_eq!(
+            rotated.rasterize(10, 10),
+            vec![Scanline::new(4, 2, 7), Scanline::new(5, 2, 7)]
+        );
+    }
+
+    #[test]
+    fn test_rasterize_thick_line() {
+        let line = ThickLine {
+            start: PointU32::new(1, 2),
+            end: PointU32::new(5, 2),
+            width: 2,
+        };
+
+        assert_eq!(
+            line.rasterize(10, 10),
+            vec![Scanline::new(1, 0, 5), Scanline::new(2, 0, 5)]
+        );
+    }
+}
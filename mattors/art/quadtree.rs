@@ -0,0 +1,103 @@
+//! Subdivide an image with a [Quadtree](https://en.wikipedia.org/wiki/Quadtree)
+//! built over points that are denser on the edges of the image. The cells are
+//! therefore small on the details and big on the flat regions and each one is
+//! filled with the average color of the image inside it.
+
+use geo::quadtree::Quadtree;
+use geo::BoundingBox;
+
+use crate::art::edges;
+
+/// Subdivide the image in the leaves of a Quadtree of `npoints` points sampled
+/// on the edges of the image where every leaf holds at most `capacity`
+/// points. If `border` is given the top and left sides of every cell are drawn
+/// with that color.
+pub fn quadtree_subdivision(
+    img: &image::RgbImage,
+    npoints: usize,
+    capacity: usize,
+    border: Option<image::Rgb<u8>>,
+) -> image::RgbImage {
+    let (width, height) = img.dimensions();
+    let mut rng = rand::thread_rng();
+
+    let edge_map = edges::sobel(&image::imageops::grayscale(img));
+
+    let bbox = BoundingBox::from_dimensions(f64::from(width), f64::from(height));
+    let mut tree = Quadtree::new(bbox, capacity);
+    for p in edges::sample_edge_points(&mut rng, &edge_map, npoints) {
+        tree.add(p, ());
+    }
+
+    let mut out = image::RgbImage::new(width, height);
+
+    for (cell, _) in tree.leaves() {
+        // the cells share their sides, rounding them assigns every pixel to
+        // exactly one cell
+        let (x0, y0) = (cell.min().x.round() as u32, cell.min().y.round() as u32);
+        let (x1, y1) = (cell.max().x.round() as u32, cell.max().y.round() as u32);
+        if x0 >= x1 || y0 >= y1 {
+            continue;
+        }
+
+        let mut sum = [0_u64; 3];
+        for y in y0..y1 {
+            for x in x0..x1 {
+                for (s, c) in sum.iter_mut().zip(img.get_pixel(x, y).0.iter()) {
+                    *s += u64::from(*c);
+                }
+            }
+        }
+
+        let n = u64::from(x1 - x0) * u64::from(y1 - y0);
+        let fill = image::Rgb([(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8]);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let pix = match border {
+                    Some(border) if x == x0 || y == y0 => border,
+                    _ => fill,
+                };
+
+                out.put_pixel(x, y, pix);
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quadtree_subdivision() {
+        // a flat image has no edges, it's a single cell
+        let flat = image::RgbImage::from_pixel(30, 20, image::Rgb([1, 2, 3]));
+        let out = quadtree_subdivision(&flat, 100, 1, None);
+        assert_eq!(out.into_raw(), flat.clone().into_raw());
+
+        let black = image::Rgb([0, 0, 0]);
+        let out = quadtree_subdivision(&flat, 100, 1, Some(black));
+        assert_eq!(out.get_pixel(0, 5), &black);
+        assert_eq!(out.get_pixel(5, 0), &black);
+        assert_eq!(out.get_pixel(5, 5), &image::Rgb([1, 2, 3]));
+
+        // the square is split in many cells
+        let white = image::Rgb([255, 255, 255]);
+        let img = image::RgbImage::from_fn(64, 64, |x, y| {
+            if (20..30).contains(&x) && (20..30).contains(&y) {
+                black
+            } else {
+                white
+            }
+        });
+        let out = quadtree_subdivision(&img, 200, 1, Some(black));
+
+        // a single cell would have 127 border pixels
+        let borders = out.pixels().filter(|p| **p == black).count();
+        assert!(borders > 2 * 127);
+        assert_eq!(out.get_pixel(63, 63), &white);
+    }
+}
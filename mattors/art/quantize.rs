@@ -144,7 +144,7 @@ where
         return None;
     }
 
-    let mut ranges = vec![(u64::max_value(), u64::min_value()); From::from(P::CHANNEL_COUNT)];
+    let mut ranges = vec![(u64::MAX, u64::MIN); From::from(P::CHANNEL_COUNT)];
 
     for &(pix, _) in pixels_freqs {
         for (i, ch) in pix.channels().iter().enumerate() {
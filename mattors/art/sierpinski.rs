@@ -40,26 +40,21 @@ impl Iterator for SierpinskiIter {
     type Item = Vec<SierpinskiTriangle>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        use std::mem::replace;
+        let old_triangles = std::mem::take(&mut self.triangles);
 
-        let old_triangles = replace(&mut self.triangles, vec![]);
+        self.triangles
+            .extend(old_triangles.iter().flat_map(|(top, left, right)| {
+                let mid_left =
+                    PointU32::new(top.x - (top.x - left.x) / 2, top.y + (left.y - top.y) / 2);
+                let mid_right = PointU32::new(top.x + (top.x - left.x) / 2, mid_left.y);
+                let mid_bottom = PointU32::new(top.x, left.y);
 
-        self.triangles.extend(
-            old_triangles
-                .iter()
-                .flat_map(|&(ref top, ref left, ref right)| {
-                    let mid_left =
-                        PointU32::new(top.x - (top.x - left.x) / 2, top.y + (left.y - top.y) / 2);
-                    let mid_right = PointU32::new(top.x + (top.x - left.x) / 2, mid_left.y);
-                    let mid_bottom = PointU32::new(top.x, left.y);
+                let new_top = (*top, mid_left, mid_right);
+                let new_left = (mid_left, *left, mid_bottom);
+                let new_right = (mid_right, mid_bottom, *right);
 
-                    let new_top = (*top, mid_left, mid_right);
-                    let new_left = (mid_left, *left, mid_bottom);
-                    let new_right = (mid_right, mid_bottom, *right);
-
-                    vec![new_top, new_left, new_right].into_iter()
-                }),
-        );
+                vec![new_top, new_left, new_right].into_iter()
+            }));
 
         Some(old_triangles)
     }
@@ -101,7 +96,7 @@ pub fn fancy_sierpinski<I>(
             siter.take(iterations).for_each(|triangles| {
                 triangles
                     .iter()
-                    .for_each(|&(ref mid_left, ref mid_right, ref mid_bottom)| {
+                    .for_each(|(mid_left, mid_right, mid_bottom)| {
                         let pix = pixs.choose(&mut rng).unwrap();
 
                         if hollow_triangles {
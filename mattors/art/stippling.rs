@@ -1,8 +1,13 @@
 //! Generate some stippling art.
 
-use geo::{BoundingBox, PointU32};
+use rand::Rng;
 
+use geo::{BoundingBox, PointF64, PointU32, Polygon};
+
+use crate::art::lloyd;
+use crate::art::poisson::{self, PointDistribution};
 use crate::art::{random_bbox_subdivisions, random_point_in_bbox};
+use crate::color::theme::to_hex;
 use crate::drawing::{Drawer, NoopBlender};
 
 /// The direction of gradient made of stippled points.
@@ -30,6 +35,7 @@ pub fn gradient(
     grow_coeff: u32,
     pix: image::Rgb<u8>,
     dir: Direction,
+    distribution: PointDistribution,
 ) {
     let mut band = initial_band(dir, img.width(), img.height(), bands);
     let mut band_npoints = base_points_per_band;
@@ -37,7 +43,7 @@ pub fn gradient(
     let mut drawer = Drawer::new_with_no_blending(img);
 
     for i in 0..bands {
-        stipple(&mut drawer, &band, band_npoints, pix);
+        stipple(&mut drawer, &band, band_npoints, pix, distribution);
 
         // prevent overflow when dir is either RightToLeft or BottomToTop,
         // because at the (bands - 1)-th iteration we reached x = 0 or y = 0 and
@@ -58,6 +64,7 @@ pub fn rects(
     points: u32,
     minimum_area: u32,
     pix: image::Rgb<u8>,
+    distribution: PointDistribution,
 ) {
     let mut rng = rand::thread_rng();
 
@@ -73,26 +80,343 @@ pub fn rects(
             continue;
         }
 
-        stipple(&mut drawer, &piece, points, pix);
+        stipple(&mut drawer, &piece, points, pix, distribution);
     }
 }
 
-/// Stipple the given bbox of the image with the desired amount of points.
+/// Stipple the given bbox of the image with the desired amount of points. The
+/// Poisson distribution only places about that many points.
 pub fn stipple(
     drawer: &mut Drawer<image::RgbImage, NoopBlender>,
     bbox: &BoundingBox<u32>,
     points: u32,
     pix: image::Rgb<u8>,
+    distribution: PointDistribution,
 ) {
     let mut rng = rand::thread_rng();
 
-    for _ in 0..points {
-        let point = random_point_in_bbox(&mut rng, bbox);
+    match distribution {
+        PointDistribution::Uniform => {
+            for _ in 0..points {
+                let point = random_point_in_bbox(&mut rng, bbox);
+
+                drawer.draw_pixel(point.x, point.y, &pix);
+            }
+        }
+        PointDistribution::Poisson => {
+            // same as random_point_in_bbox, the max coordinates are excluded
+            let region = BoundingBox::from_dimensions_and_origin(
+                &bbox.min().cast(),
+                f64::from(bbox.max().x - bbox.min().x) - 1e-6,
+                f64::from(bbox.max().y - bbox.min().y) - 1e-6,
+            );
 
-        drawer.draw_pixel(point.x, point.y, &pix);
+            for point in poisson::sample(&mut rng, distribution, points as usize, &region) {
+                drawer.draw_pixel(point.x as u32, point.y as u32, &pix);
+            }
+        }
     }
 }
 
+/// A dot of a weighted stippling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stipple {
+    /// The center of the dot.
+    pub center: PointF64,
+
+    /// The radius of the dot.
+    pub radius: f64,
+}
+
+/// Place `npoints` dots on the given image so that their density follows the
+/// darkness of the image using [Weighted Voronoi
+/// Stippling](https://www.cs.ubc.ca/labs/imager/tr/2002/secord2002b/secord.2002b.pdf).
+/// The dots are initially sampled according to the darkness and then moved
+/// towards the weighted centroid of their Voronoi cell for the given number of
+/// iterations or until they stop moving. With the Poisson distribution the
+/// initial dots are already evenly spaced, but there are only about `npoints`
+/// of them.
+///
+/// The radius of every dot goes from `min_radius` to `max_radius` according to
+/// the mean darkness of its cell. An image that is completely white has no
+/// dots.
+pub fn weighted(
+    img: &image::GrayImage,
+    npoints: usize,
+    iterations: usize,
+    (min_radius, max_radius): (f64, f64),
+    distribution: PointDistribution,
+) -> Vec<Stipple> {
+    let density = Density::new(img);
+    if npoints == 0 || density.total() == 0.0 {
+        return vec![];
+    }
+
+    let mut rng = rand::thread_rng();
+    let bbox = BoundingBox::from_dimensions(f64::from(img.width()), f64::from(img.height()));
+
+    let mut sites = match distribution {
+        PointDistribution::Uniform => density.sample(&mut rng, npoints),
+        PointDistribution::Poisson => {
+            // the number of points around p is proportional to 1 / radius(p)²
+            // and therefore to the darkness
+            let radius = poisson::radius_for(density.total(), npoints);
+            let darkness = |p: &PointF64| {
+                let x = (p.x as u32).min(img.width() - 1);
+                let y = (p.y as u32).min(img.height() - 1);
+
+                density.at(x, y)
+            };
+
+            let mut sites =
+                poisson::variable_poisson_disc(&mut rng, &bbox, (radius, 10.0 * radius), |p| {
+                    radius / darkness(p).sqrt()
+                });
+
+            // there's no reason to put dots on white pixels
+            sites.retain(|p| darkness(p) > 0.0);
+            sites
+        }
+    };
+
+    let voronoi = lloyd::relax_with(&mut sites, &bbox, iterations, |cell| {
+        density.integrate(cell).centroid()
+    });
+
+    sites
+        .iter()
+        .zip(voronoi.cells())
+        .map(|(center, cell)| {
+            let darkness = cell
+                .as_ref()
+                .map_or(0.0, |cell| density.integrate(cell).mean());
+
+            Stipple {
+                center: *center,
+                radius: min_radius + (max_radius - min_radius) * darkness,
+            }
+        })
+        .collect()
+}
+
+/// Draw the given dots on the image.
+pub fn draw_stipples(img: &mut image::RgbImage, stipples: &[Stipple], pix: image::Rgb<u8>) {
+    let mut drawer = Drawer::new_with_no_blending(img);
+
+    for stipple in stipples {
+        drawer.circle(stipple.center, stipple.radius, &pix);
+    }
+}
+
+/// Write the given dots as an SVG document made of circles which is handy for
+/// plotters.
+pub fn write_svg(
+    out: &mut impl std::io::Write,
+    (width, height): (u32, u32),
+    stipples: &[Stipple],
+    background: image::Rgb<u8>,
+    fill: image::Rgb<u8>,
+) -> std::io::Result<()> {
+    write!(
+        out,
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+<svg xmlns="http://www.w3.org/2000/svg" version="1.1" viewBox="0 0 {w} {h}">
+<rect x="0" y="0" width="{w}" height="{h}" fill="{background}"/>
+<g fill="{fill}">
+"##,
+        w = width,
+        h = height,
+        background = to_hex(background),
+        fill = to_hex(fill),
+    )?;
+
+    for stipple in stipples {
+        writeln!(
+            out,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}"/>"#,
+            stipple.center.x, stipple.center.y, stipple.radius
+        )?;
+    }
+
+    write!(out, "</g>\n</svg>")
+}
+
+/// The darkness of every pixel of an image along with the prefix sums of every
+/// row so that the mass and the centroid of any convex region can be computed
+/// in time proportional to its height.
+struct Density {
+    width: u32,
+    height: u32,
+    darkness: Vec<f64>,
+
+    // sum of the darkness and of the darkness times the x coordinate of the
+    // pixels before the i-th one in the same row.
+    mass: Vec<f64>,
+    moment: Vec<f64>,
+}
+
+/// The integral of the darkness over a region of the image.
+#[derive(Debug, Default)]
+struct Integral {
+    pixels: f64,
+    mass: f64,
+    moment_x: f64,
+    moment_y: f64,
+}
+
+impl Density {
+    fn new(img: &image::GrayImage) -> Self {
+        let (width, height) = img.dimensions();
+        let row_len = width as usize + 1;
+
+        let darkness = img
+            .pixels()
+            .map(|p| 1.0 - f64::from(p[0]) / 255.0)
+            .collect::<Vec<_>>();
+
+        let mut mass = vec![0.0; row_len * height as usize];
+        let mut moment = vec![0.0; row_len * height as usize];
+
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let d = darkness[y * width as usize + x];
+
+                mass[y * row_len + x + 1] = mass[y * row_len + x] + d;
+                moment[y * row_len + x + 1] = moment[y * row_len + x] + d * (x as f64 + 0.5);
+            }
+        }
+
+        Density {
+            width,
+            height,
+            darkness,
+            mass,
+            moment,
+        }
+    }
+
+    fn at(&self, x: u32, y: u32) -> f64 {
+        self.darkness[(y * self.width + x) as usize]
+    }
+
+    fn total(&self) -> f64 {
+        self.darkness.iter().sum()
+    }
+
+    /// Sample `n` random points whose density follows the darkness, white
+    /// pixels never get a point.
+    fn sample<R: Rng>(&self, rng: &mut R, n: usize) -> Vec<PointF64> {
+        let cumulative = self
+            .darkness
+            .iter()
+            .scan(0.0, |sum, d| {
+                *sum += d;
+                Some(*sum)
+            })
+            .collect::<Vec<_>>();
+
+        let total = cumulative.last().copied().unwrap_or(0.0);
+        if total <= 0.0 {
+            return vec![];
+        }
+
+        (0..n)
+            .map(|_| {
+                let target = rng.gen::<f64>() * total;
+                let i = cumulative
+                    .partition_point(|c| *c <= target)
+                    .min(cumulative.len() - 1) as u32;
+
+                PointF64::new(
+                    f64::from(i % self.width) + rng.gen::<f64>(),
+                    f64::from(i / self.width) + rng.gen::<f64>(),
+                )
+            })
+            .collect()
+    }
+
+    /// Integrate the darkness over the pixels whose center lies in the given
+    /// convex polygon.
+    fn integrate(&self, cell: &Polygon<f64>) -> Integral {
+        let bbox = cell.bounding_box();
+        let row_len = self.width as usize + 1;
+
+        let ymin = (bbox.min().y - 0.5).ceil().max(0.0) as u32;
+        let ymax = (bbox.max().y - 0.5)
+            .floor()
+            .min(f64::from(self.height) - 1.0);
+
+        let mut integral = Integral::default();
+
+        for y in (ymin..).take_while(|y| f64::from(*y) <= ymax) {
+            let cy = f64::from(y) + 0.5;
+
+            let (x0, x1) = match row_span(cell, cy) {
+                Some(span) => span,
+                None => continue,
+            };
+
+            let x0 = (x0 - 0.5).ceil().max(0.0);
+            let x1 = (x1 - 0.5).floor().min(f64::from(self.width) - 1.0);
+            if x1 < x0 {
+                continue;
+            }
+
+            let start = y as usize * row_len + x0 as usize;
+            let end = y as usize * row_len + x1 as usize + 1;
+            let mass = self.mass[end] - self.mass[start];
+
+            integral.pixels += x1 - x0 + 1.0;
+            integral.mass += mass;
+            integral.moment_x += self.moment[end] - self.moment[start];
+            integral.moment_y += mass * cy;
+        }
+
+        integral
+    }
+}
+
+impl Integral {
+    fn centroid(&self) -> Option<PointF64> {
+        if self.mass <= 0.0 {
+            return None;
+        }
+
+        Some(PointF64::new(
+            self.moment_x / self.mass,
+            self.moment_y / self.mass,
+        ))
+    }
+
+    fn mean(&self) -> f64 {
+        if self.pixels == 0.0 {
+            0.0
+        } else {
+            self.mass / self.pixels
+        }
+    }
+}
+
+/// Return the horizontal extent of the given convex polygon at height `y`.
+fn row_span(polygon: &Polygon<f64>, y: f64) -> Option<(f64, f64)> {
+    polygon
+        .edges()
+        .filter(|(a, b)| a.y.min(b.y) <= y && y <= a.y.max(b.y))
+        .map(|(a, b)| {
+            if a.y == b.y {
+                (a.x.min(b.x), a.x.max(b.x))
+            } else {
+                let x = a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x);
+                (x, x)
+            }
+        })
+        .fold(None, |span, (x0, x1)| match span {
+            None => Some((x0, x1)),
+            Some((s0, s1)) => Some((x0.min(s0), x1.max(s1))),
+        })
+}
+
 fn initial_band(dir: Direction, width: u32, height: u32, bands: u32) -> BoundingBox<u32> {
     let band_width = width / bands;
     let band_height = height / bands;
@@ -125,3 +449,69 @@ fn advance_band(band: &BoundingBox<u32>, dir: Direction) -> BoundingBox<u32> {
 
     BoundingBox::from_dimensions_and_origin(&band_new_origin, band_width, band_height)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_density_integrate() {
+        // the right half is black
+        let img =
+            image::GrayImage::from_fn(4, 2, |x, _| image::Luma([if x < 2 { 255 } else { 0 }]));
+        let density = Density::new(&img);
+
+        let whole = Polygon::new(BoundingBox::from_dimensions(4.0, 2.0).points().to_vec()).unwrap();
+        let integral = density.integrate(&whole);
+
+        assert_eq!(integral.pixels, 8.0);
+        assert_eq!(integral.mass, 4.0);
+        assert_eq!(integral.mean(), 0.5);
+        assert_eq!(integral.centroid(), Some(PointF64::new(3.0, 1.0)));
+
+        // only the pixel at (2, 0) has its center in the triangle
+        let triangle = Polygon::new(vec![
+            PointF64::new(2.0, 0.0),
+            PointF64::new(3.0, 0.0),
+            PointF64::new(2.0, 1.0),
+        ])
+        .unwrap();
+        let integral = density.integrate(&triangle);
+
+        assert_eq!(integral.pixels, 1.0);
+        assert_eq!(integral.centroid(), Some(PointF64::new(2.5, 0.5)));
+    }
+
+    #[test]
+    fn test_weighted() {
+        let white = image::GrayImage::from_pixel(10, 10, image::Luma([255]));
+        assert!(weighted(&white, 10, 5, (1.0, 1.0), PointDistribution::Uniform).is_empty());
+
+        // a single barely gray pixel gets all the dots
+        let mut almost_white = white.clone();
+        almost_white.put_pixel(3, 7, image::Luma([254]));
+        let stipples = weighted(&almost_white, 10, 0, (1.0, 1.0), PointDistribution::Uniform);
+        assert_eq!(stipples.len(), 10);
+        for s in &stipples {
+            assert_eq!((s.center.x as u32, s.center.y as u32), (3, 7));
+        }
+
+        // all the dots end up in the black square in the middle
+        let img = image::GrayImage::from_fn(90, 90, |x, y| {
+            let inside = (30..60).contains(&x) && (30..60).contains(&y);
+            image::Luma([if inside { 0 } else { 255 }])
+        });
+
+        let stipples = weighted(&img, 40, 10, (1.0, 3.0), PointDistribution::Uniform);
+        assert_eq!(stipples.len(), 40);
+
+        let poisson = weighted(&img, 40, 10, (1.0, 3.0), PointDistribution::Poisson);
+        assert!(!poisson.is_empty());
+
+        for s in stipples.iter().chain(&poisson) {
+            assert!(s.center.x >= 30.0 && s.center.x <= 60.0);
+            assert!(s.center.y >= 30.0 && s.center.y <= 60.0);
+            assert!(s.radius >= 1.0 && s.radius <= 3.0);
+        }
+    }
+}
@@ -5,7 +5,9 @@ use std::collections::HashSet;
 use rand::Rng;
 
 use geo::point::{PointF64, PointU32};
+use geo::segment::{Intersection, Segment};
 
+use crate::color::theme::to_hex;
 use crate::drawing::Drawer;
 
 /// A Vertex of a tangled web. It is a node of the graph.
@@ -16,7 +18,12 @@ pub struct Vertex {
 }
 
 /// generate a random image that can vaguely resemble a spider web.
-pub fn generate_img(img: &mut image::RgbImage, iterations: usize, circle_divisions: u8) {
+pub fn generate_img(
+    img: &mut image::RgbImage,
+    iterations: usize,
+    circle_divisions: u8,
+    stroke: image::Rgb<u8>,
+) {
     let (vertices, edges) = generate_tangled_web(img.dimensions(), iterations, circle_divisions);
 
     let width = f64::from(img.width());
@@ -34,7 +41,7 @@ pub fn generate_img(img: &mut image::RgbImage, iterations: usize, circle_divisio
         let v0 = vertices[*v0].position;
         let v1 = vertices[*v1].position;
 
-        drawer.line(line_pt(v0), line_pt(v1), &image::Rgb([154, 154, 154]));
+        drawer.line(line_pt(v0), line_pt(v1), &stroke);
     }
 }
 
@@ -44,6 +51,7 @@ pub fn generate_svg(
     (width, height): (u32, u32),
     iterations: usize,
     circle_divisions: u8,
+    stroke: image::Rgb<u8>,
 ) -> std::io::Result<()> {
     let (vertices, edges) = generate_tangled_web((width, height), iterations, circle_divisions);
 
@@ -52,10 +60,11 @@ pub fn generate_svg(
         r##"<?xml version="1.0" encoding="UTF-8"?>
 <!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
 <svg xmlns="http://www.w3.org/2000/svg" version="1.1" viewBox="0 0 {w} {h}">
-<path fill="none" stroke="#9a9a9a" d="
+<path fill="none" stroke="{stroke}" d="
 "##,
         w = width,
-        h = height
+        h = height,
+        stroke = to_hex(stroke),
     )?;
 
     for (v0, v1) in &edges {
@@ -126,16 +135,20 @@ pub fn generate_tangled_web(
         let a1 = rng.gen_range(0.0, TWO_PI);
         let d1 = (width.powi(2) + height.powi(2)).sqrt();
         let p1 = PointF64::new(p0.x + a1.cos() * d1, p0.y + a1.sin() * d1);
+        let ray = Segment::new(p0, p1);
 
+        // only the new ray can cross the edges: a Bentley–Ottmann sweep of
+        // all the segments would do more work just to report the edges that
+        // meet at the vertices of the web.
         let mut intersections = edges
             .iter()
             .filter_map(|(v0, v1)| {
-                let int = segment_intersection(
-                    (p0, p1),
-                    (vertices[*v0].position, vertices[*v1].position),
-                )?;
+                let edge = Segment::new(vertices[*v0].position, vertices[*v1].position);
 
-                Some((int, (*v0, *v1)))
+                match ray.intersection(&edge)? {
+                    Intersection::Point(int) => Some((int, (*v0, *v1))),
+                    Intersection::Overlap(_) => None,
+                }
             })
             .collect::<Vec<_>>();
 
@@ -208,35 +221,3 @@ impl Vertex {
         }
     }
 }
-
-fn segment_intersection(
-    (p0, p1): (PointF64, PointF64),
-    (q0, q1): (PointF64, PointF64),
-) -> Option<PointF64> {
-    fn cross(a: PointF64, b: PointF64) -> f64 {
-        (a.x * b.y) - (b.x * a.y)
-    }
-
-    let sa = PointF64::new(p1.x - p0.x, p1.y - p0.y);
-    let sb = PointF64::new(q1.x - q0.x, q1.y - q0.y);
-    let u = cross(sa, sb);
-
-    // this is just a safe-guard so we do not divide by zero below.
-    // it is not a good way to test for parallel lines
-    if u.abs() <= 0.0 {
-        return None;
-    }
-
-    let ba = PointF64::new(p0.x - q0.x, p0.y - q0.y);
-    let ta = cross(sb, ba) / u;
-    let tb = cross(sa, ba) / u;
-
-    if ta >= 0.0 && ta <= 1.0 && tb >= 0.0 && tb <= 1.0 {
-        Some(PointF64::new(
-            p0.x + ta * (p1.x - p0.x),
-            p0.y + ta * (p1.y - p0.y),
-        ))
-    } else {
-        None
-    }
-}
@@ -1,11 +1,179 @@
 //! Draw some [Voronoi diagrams](https://en.wikipedia.org/wiki/Voronoi_diagram).
 
+use std::collections::HashSet;
+use std::str::FromStr;
+
 use rand::Rng;
 
-use geo::{kdtree, BoundingBox, PointU32};
+use geo::{kdtree, BoundingBox, Metric, PointF64, PointU32, Weighting};
 
+use crate::art::edges;
 use crate::art::generate_distinct_random_points;
-use crate::color::{random_color, RandomColorConfig};
+use crate::art::lloyd;
+use crate::art::poisson::{self, PointDistribution};
+
+/// The settings of the Voronoi diagrams.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoronoiConfig {
+    npoints: usize,
+    distribution: PointDistribution,
+    relax_iterations: usize,
+    metric: Metric,
+    weights: Option<SiteWeights>,
+}
+
+/// How the sites are randomly weighted so that some cells grow at the expense
+/// of their neighbors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SiteWeights {
+    /// A random weight up to half the average spacing of the sites is
+    /// subtracted from the distances, the cells are bounded by hyperbolas.
+    Additive,
+
+    /// The distances are divided by a random weight between 0.5 and 1.5, the
+    /// cells are bounded by circles and they might be split.
+    Multiplicative,
+
+    /// Power diagram where the squared Euclidean distances are additively
+    /// weighted, the cells are bounded by straight lines. It overrides the
+    /// metric.
+    Power,
+}
+
+impl VoronoiConfig {
+    /// Create a new `VoronoiConfig` with the default settings: 50 unweighted
+    /// sites spread uniformly and Euclidean distance.
+    pub fn new() -> Self {
+        VoronoiConfig {
+            npoints: 50,
+            distribution: PointDistribution::Uniform,
+            relax_iterations: 0,
+            metric: Metric::Euclidean,
+            weights: None,
+        }
+    }
+
+    /// Set the number of sites.
+    pub fn points(mut self, npoints: usize) -> Self {
+        self.npoints = npoints;
+        self
+    }
+
+    /// Set how the sites are scattered.
+    pub fn distribution(mut self, distribution: PointDistribution) -> Self {
+        self.distribution = distribution;
+        self
+    }
+
+    /// Spread the sites by the given number of steps of Lloyd's relaxation.
+    /// The relaxation always uses Euclidean distances.
+    pub fn relax(mut self, relax_iterations: usize) -> Self {
+        self.relax_iterations = relax_iterations;
+        self
+    }
+
+    /// Set how the distance between a pixel and a site is measured.
+    pub fn metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Randomly weight the sites. By default they're not weighted.
+    pub fn weights(mut self, weights: SiteWeights) -> Self {
+        self.weights = Some(weights);
+        self
+    }
+}
+
+impl Default for VoronoiConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The sites of a diagram along with their values and weights.
+struct Sites<V> {
+    tree: kdtree::KdTree<PointU32, (V, f64)>,
+    metric: Metric,
+    weighting: Option<(Weighting, f64)>,
+}
+
+impl<V> Sites<V> {
+    /// Generate the sites of a `width` x `height` diagram. Return None if there
+    /// are no sites.
+    fn new(
+        width: u32,
+        height: u32,
+        config: &VoronoiConfig,
+        mut value: impl FnMut(&PointU32) -> V,
+    ) -> Option<Self> {
+        let mut rng = rand::thread_rng();
+
+        let sites = random_sites(
+            width,
+            height,
+            config.npoints,
+            config.distribution,
+            config.relax_iterations,
+        );
+        if sites.is_empty() {
+            return None;
+        }
+
+        let spacing = (f64::from(width) * f64::from(height) / sites.len() as f64).sqrt();
+        let (metric, weighting, (min_weight, max_weight)) = match config.weights {
+            None => (config.metric, None, (0.0, 0.0)),
+            Some(SiteWeights::Additive) => (
+                config.metric,
+                Some(Weighting::Additive),
+                (0.0, spacing / 2.0),
+            ),
+            Some(SiteWeights::Multiplicative) => {
+                (config.metric, Some(Weighting::Multiplicative), (0.5, 1.5))
+            }
+            Some(SiteWeights::Power) => (
+                Metric::SquaredEuclidean,
+                Some(Weighting::Additive),
+                (0.0, spacing * spacing / 4.0),
+            ),
+        };
+
+        let sites = sites
+            .iter()
+            .map(|pt| {
+                let weight = min_weight + rng.gen::<f64>() * (max_weight - min_weight);
+                (*pt, (value(pt), weight))
+            })
+            .collect();
+
+        Some(Sites {
+            tree: kdtree::KdTree::from_vector(sites),
+            metric,
+            weighting: weighting.map(|w| (w, max_weight)),
+        })
+    }
+
+    /// Return the k closest sites to the point along with their values and
+    /// their distances.
+    fn nearest(&self, pt: PointU32, k: usize) -> Vec<(&PointU32, &V, f64)> {
+        let nearest = match self.weighting {
+            None => self.tree.nearest_neighbors_by(pt, k, self.metric),
+            Some((weighting, max_weight)) => self.tree.weighted_nearest_neighbors(
+                pt,
+                k,
+                self.metric,
+                weighting,
+                |(_, w)| *w,
+                max_weight,
+            ),
+        };
+
+        nearest
+            .into_iter()
+            .map(|(site, (v, _), d)| (site, v, d))
+            .collect()
+    }
+}
 
 /// Generate a voronoi diagram where the colors are taken from the gradient
 /// going from color1 to color2.
@@ -13,69 +181,334 @@ pub fn gradient_voronoi(
     img: &mut image::RgbImage,
     color1: image::Rgb<u8>,
     color2: image::Rgb<u8>,
-    npoints: usize,
+    config: &VoronoiConfig,
 ) {
-    if npoints == 0 {
-        return;
-    }
+    let sites = match Sites::new(img.width(), img.height(), config, |_| ()) {
+        Some(sites) => sites,
+        None => return,
+    };
 
-    let random_points = generate_distinct_random_points(
-        &mut rand::thread_rng(),
-        npoints,
-        &BoundingBox::from_dimensions(img.width(), img.height()),
-    );
+    let img_width = img.width();
 
-    let points = random_points.iter().map(|pt| (*pt, ())).collect();
-    let points = kdtree::KdTree::from_vector(points);
+    for (x, y, pix) in img.enumerate_pixels_mut() {
+        let (closest_point, _, _) = sites.nearest(PointU32::new(x, y), 1)[0];
 
-    let dr = f64::from(color2[0]) - f64::from(color1[0]);
-    let dg = f64::from(color2[1]) - f64::from(color1[1]);
-    let db = f64::from(color2[2]) - f64::from(color1[2]);
+        let c = f64::from(closest_point.x) / f64::from(img_width);
+        *pix = lerp(color1, color2, c);
+    }
+}
 
-    let img_width = img.width();
+/// Generate some random Voronoi diagrams where each cell is filled with the
+/// pixel returned by `random_pixel`.
+pub fn random_voronoi(
+    img: &mut image::RgbImage,
+    config: &VoronoiConfig,
+    mut random_pixel: impl FnMut() -> image::Rgb<u8>,
+) {
+    let sites = match Sites::new(img.width(), img.height(), config, |_| random_pixel()) {
+        Some(sites) => sites,
+        None => return,
+    };
 
     for (x, y, pix) in img.enumerate_pixels_mut() {
-        let (closest_point, _) = points.nearest_neighbor(PointU32::new(x, y)).unwrap();
+        let (_, closest_point_color, _) = sites.nearest(PointU32::new(x, y), 1)[0];
 
-        let c = f64::from(closest_point.x) / f64::from(img_width);
-        *pix = image::Rgb([
-            (f64::from(color1[0]) + c * dr) as u8,
-            (f64::from(color1[1]) + c * dg) as u8,
-            (f64::from(color1[2]) + c * db) as u8,
-        ]);
+        *pix = *closest_point_color;
     }
 }
 
-/// Generate some random Voronoi diagrams.
-pub fn random_voronoi<R: Rng>(
+/// Generate some [cellular noise](https://en.wikipedia.org/wiki/Worley_noise)
+/// where every pixel is colored according to the difference between the
+/// distances to its second closest and closest sites, aka F2 - F1. The
+/// borders between the cells are colored with color1 and the sites with
+/// color2.
+pub fn cellular_voronoi(
     img: &mut image::RgbImage,
-    color_config: &mut RandomColorConfig<R>,
-    npoints: usize,
+    color1: image::Rgb<u8>,
+    color2: image::Rgb<u8>,
+    config: &VoronoiConfig,
 ) {
-    if npoints == 0 {
-        return;
+    let sites = match Sites::new(img.width(), img.height(), config, |_| ()) {
+        Some(sites) => sites,
+        None => return,
+    };
+
+    let noise = img
+        .enumerate_pixels()
+        .map(
+            |(x, y, _)| match &sites.nearest(PointU32::new(x, y), 2)[..] {
+                [(_, _, f1), (_, _, f2)] => f2 - f1,
+                _ => 0.0,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let max = noise.iter().cloned().fold(0.0, f64::max);
+
+    for (pix, f) in img.pixels_mut().zip(noise) {
+        *pix = lerp(color1, color2, if max > 0.0 { f / max } else { 0.0 });
     }
+}
 
-    let random_points = generate_distinct_random_points(
-        &mut rand::thread_rng(),
-        npoints,
-        &BoundingBox::from_dimensions(img.width(), img.height()),
-    );
+/// Return the color at `t` in the gradient going from color1 to color2.
+fn lerp(color1: image::Rgb<u8>, color2: image::Rgb<u8>, t: f64) -> image::Rgb<u8> {
+    let channel = |i: usize| {
+        let (c1, c2) = (f64::from(color1[i]), f64::from(color2[i]));
+        (c1 + t * (c2 - c1)) as u8
+    };
 
-    let points = random_points
+    image::Rgb([channel(0), channel(1), channel(2)])
+}
+
+/// Turn the given image into a stained glass mosaic of about `npoints` Voronoi
+/// cells each one filled with the average color of the source pixels inside
+/// it. The sites are evenly spaced unless `adaptive` is true in which case
+/// they're denser where the image has more details.
+///
+/// If `lead` is given the cells are separated by borders of the given width
+/// and color.
+pub fn mosaic(
+    img: &image::RgbImage,
+    npoints: usize,
+    adaptive: bool,
+    lead: Option<(f64, image::Rgb<u8>)>,
+) -> image::RgbImage {
+    let (width, height) = img.dimensions();
+    let mut rng = rand::thread_rng();
+    let bbox = BoundingBox::from_dimensions(f64::from(width), f64::from(height));
+
+    let sites = if adaptive {
+        let details = image::imageops::blur(&edges::sobel(&image::imageops::grayscale(img)), 2.0);
+        let max = f64::from(details.pixels().map(|p| p[0]).max().unwrap_or(0).max(1));
+        let weight = |x: f64, y: f64| {
+            let x = (x as u32).min(width - 1);
+            let y = (y as u32).min(height - 1);
+
+            0.1 + f64::from(details.get_pixel(x, y)[0]) / max
+        };
+
+        // the number of sites around p is proportional to its weight
+        let total = details
+            .pixels()
+            .map(|p| 0.1 + f64::from(p[0]) / max)
+            .sum::<f64>();
+        let radius = poisson::radius_for(total, npoints.max(1));
+
+        poisson::variable_poisson_disc(&mut rng, &bbox, (radius, radius * 10.0_f64.sqrt()), |p| {
+            radius / weight(p.x, p.y).sqrt()
+        })
+    } else {
+        poisson::sample(&mut rng, PointDistribution::Poisson, npoints, &bbox)
+    };
+
+    let sites = sites
+        .into_iter()
+        .filter_map(|p| p.try_cast())
+        .filter(|p: &PointU32| p.x < width && p.y < height)
+        .collect::<HashSet<_>>();
+
+    if sites.is_empty() {
+        return img.clone();
+    }
+
+    let tree =
+        kdtree::KdTree::from_vector(sites.iter().enumerate().map(|(i, p)| (*p, i)).collect());
+
+    // the index of the closest site of every pixel and the distance to the
+    // border with the second closest one
+    let mut labels = Vec::with_capacity(width as usize * height as usize);
+    let mut sums = vec![[0_u64; 4]; sites.len()];
+
+    for (x, y, pix) in img.enumerate_pixels() {
+        let p = PointU32::new(x, y);
+        let closest = tree.nearest_neighbors_by(p, 2, Metric::Euclidean);
+
+        let (s1, i, _) = closest[0];
+        let border = match closest.get(1) {
+            None => f64::INFINITY,
+            Some((s2, _, _)) => {
+                let (p, s1, s2) = (p.cast::<f64>(), s1.cast::<f64>(), s2.cast::<f64>());
+                let (d1, d2): (f64, f64) = (s1.squared_dist(&p), s2.squared_dist(&p));
+
+                (d2 - d1) / (2.0 * s1.dist::<f64>(&s2))
+            }
+        };
+
+        labels.push((*i, border));
+
+        let sum = &mut sums[*i];
+        for (s, c) in sum.iter_mut().zip(pix.0.iter()) {
+            *s += u64::from(*c);
+        }
+        sum[3] += 1;
+    }
+
+    let colors = sums
         .iter()
-        .map(|pt| (*pt, image::Rgb(random_color(color_config).to_rgb())))
-        .collect();
+        .map(|s| {
+            let n = s[3].max(1);
+            image::Rgb([(s[0] / n) as u8, (s[1] / n) as u8, (s[2] / n) as u8])
+        })
+        .collect::<Vec<_>>();
 
-    let points = kdtree::KdTree::from_vector(points);
+    let mut out = image::RgbImage::new(width, height);
+    for ((_, _, pix), (i, border)) in out.enumerate_pixels_mut().zip(labels) {
+        *pix = match lead {
+            Some((lead_width, lead_color)) if border < lead_width / 2.0 => lead_color,
+            _ => colors[i],
+        };
+    }
 
-    for (x, y, pix) in img.enumerate_pixels_mut() {
-        let (_, closest_point_color) = points.nearest_neighbor(PointU32::new(x, y)).unwrap();
+    out
+}
 
-        *pix = *closest_point_color;
+/// Return about `npoints` distinct sites in a `width` x `height` image.
+fn random_sites(
+    width: u32,
+    height: u32,
+    npoints: usize,
+    distribution: PointDistribution,
+    relax_iterations: usize,
+) -> HashSet<PointU32> {
+    let mut rng = rand::thread_rng();
+    let bbox = BoundingBox::from_dimensions(f64::from(width), f64::from(height));
+
+    let mut sites = match distribution {
+        PointDistribution::Uniform => generate_distinct_random_points(
+            &mut rng,
+            npoints,
+            &BoundingBox::from_dimensions(width, height),
+        )
+        .into_iter()
+        .map(|p| p.cast())
+        .collect(),
+        PointDistribution::Poisson => poisson::sample(&mut rng, distribution, npoints, &bbox),
+    };
+
+    if relax_iterations > 0 {
+        lloyd::relax(&mut sites, &bbox, relax_iterations);
+    }
+
+    sites
+        .into_iter()
+        .filter_map(|p: PointF64| p.try_cast())
+        .filter(|p: &PointU32| p.x < width && p.y < height)
+        .collect()
+}
+
+impl FromStr for SiteWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "additive" => Ok(SiteWeights::Additive),
+            "multiplicative" => Ok(SiteWeights::Multiplicative),
+            "power" => Ok(SiteWeights::Power),
+            _ => Err(format!(
+                "unknown site weights {}, please use additive, multiplicative or power",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mosaic() {
+        let img = image::RgbImage::from_fn(40, 20, |x, _| image::Rgb([0, (x * 6) as u8, 100]));
+
+        let out = mosaic(&img, 8, false, None);
+        assert_eq!(out.dimensions(), img.dimensions());
+
+        // every cell has an average color
+        assert!(out.pixels().all(|p| p[0] == 0 && p[2] == 100));
+
+        let black = image::Rgb([0, 0, 0]);
+        let lead = mosaic(&img, 8, true, Some((2.0, black)));
+        assert!(lead.pixels().any(|p| *p == black));
+        assert!(lead.pixels().filter(|p| **p == black).count() < 40 * 20 / 2);
+
+        // a flat image stays the same without borders
+        let flat = image::RgbImage::from_pixel(30, 30, image::Rgb([1, 2, 3]));
+        let out = mosaic(&flat, 10, true, None);
+        assert_eq!(out.into_raw(), flat.into_raw());
     }
 
-    // for point in random_points {
-    //     img.put_pixel(point.x, point.y, image::Rgb { data: [0, 0, 0] });
-    // }
+    #[test]
+    fn test_random_voronoi_metrics() {
+        // the probe is closer to b in Euclidean distance, 5.83 against 6, but
+        // closer to a in Manhattan distance, 8 against 6
+        let (a, b, probe) = (
+            PointU32::new(0, 0),
+            PointU32::new(9, 5),
+            PointU32::new(6, 0),
+        );
+        let sites = |metric, weighting, weight_a| Sites {
+            tree: kdtree::KdTree::from_vector(vec![(a, ('a', weight_a)), (b, ('b', 0.0))]),
+            metric,
+            weighting,
+        };
+        let owner = |sites: Sites<char>| *sites.nearest(probe, 1)[0].1;
+
+        assert_eq!(owner(sites(Metric::Euclidean, None, 0.0)), 'b');
+        assert_eq!(owner(sites(Metric::Manhattan, None, 0.0)), 'a');
+
+        // in the power diagram the weight of a makes it win, 36 - 10 against 34
+        let power = Some((Weighting::Additive, 10.0));
+        assert_eq!(owner(sites(Metric::SquaredEuclidean, power, 0.0)), 'b');
+        assert_eq!(owner(sites(Metric::SquaredEuclidean, power, 10.0)), 'a');
+
+        let config = VoronoiConfig::new().points(10);
+        let (red, blue) = (image::Rgb([255, 0, 0]), image::Rgb([0, 0, 255]));
+
+        for weights in &[
+            None,
+            Some(SiteWeights::Additive),
+            Some(SiteWeights::Multiplicative),
+            Some(SiteWeights::Power),
+        ] {
+            for metric in &[Metric::Manhattan, Metric::Chebyshev, Metric::Minkowski(3.0)] {
+                let mut config = config.clone().metric(*metric);
+                if let Some(weights) = weights {
+                    config = config.weights(*weights);
+                }
+
+                let mut colors = vec![red, blue].into_iter().cycle();
+                let mut img = image::RgbImage::new(50, 40);
+                random_voronoi(&mut img, &config, || colors.next().unwrap());
+
+                assert!(img.pixels().all(|p| *p == red || *p == blue));
+            }
+        }
+
+        // no sites, no changes
+        let mut img = image::RgbImage::new(20, 20);
+        random_voronoi(&mut img, &config.points(0), || red);
+        assert!(img.pixels().all(|p| *p == image::Rgb([0, 0, 0])));
+    }
+
+    #[test]
+    fn test_cellular_voronoi() {
+        let (black, white) = (image::Rgb([0, 0, 0]), image::Rgb([255, 255, 255]));
+
+        let mut img = image::RgbImage::new(50, 40);
+        cellular_voronoi(&mut img, black, white, &VoronoiConfig::new().points(2));
+
+        // the pixels on the border between the sites are as far from both
+        assert!(img.pixels().any(|p| p[0] < 10));
+        assert!(img.pixels().any(|p| *p == white));
+
+        let mut img = image::RgbImage::new(50, 40);
+        cellular_voronoi(&mut img, black, white, &VoronoiConfig::new().points(1));
+        assert!(img.pixels().all(|p| *p == black));
+    }
+
+    #[test]
+    fn test_site_weights_from_str() {
+        assert_eq!("power".parse(), Ok(SiteWeights::Power));
+        assert!("heavy".parse::<SiteWeights>().is_err());
+    }
 }
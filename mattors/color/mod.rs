@@ -1,10 +1,14 @@
 //! Simple module that helps with generating good looking colors.
 
+pub mod theme;
+
 use rand::prelude::*;
 
 use geo::line::linear_interpolate;
 use geo::point::Point;
 
+pub use self::theme::Theme;
+
 /// Simple struct to hold colors in HSV colorspace.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Hsv((u16, u8, u8));
@@ -126,7 +130,61 @@ fn pick_brightness(hue: u16, saturation: u8) -> u8 {
     0
 }
 
+/// Return the relative luminance of the given rgb color as defined by [ITU-R
+/// BT.709](https://en.wikipedia.org/wiki/Relative_luminance). The result is in
+/// [0, 255].
+pub fn luminance(rgb: [u8; 3]) -> f64 {
+    0.2126 * f64::from(rgb[0]) + 0.7152 * f64::from(rgb[1]) + 0.0722 * f64::from(rgb[2])
+}
+
 impl Hsv {
+    /// Convert a color from Rgb color space to Hsv.
+    pub fn from_rgb(rgb: [u8; 3]) -> Self {
+        let [r, g, b] = rgb;
+
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = f64::from(max - min);
+
+        let hue = if max == min {
+            0.0
+        } else if max == r {
+            60.0 * ((f64::from(g) - f64::from(b)) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((f64::from(b) - f64::from(r)) / delta + 2.0)
+        } else {
+            60.0 * ((f64::from(r) - f64::from(g)) / delta + 4.0)
+        };
+
+        let saturation = if max == 0 {
+            0.0
+        } else {
+            delta / f64::from(max) * 100.0
+        };
+        let brightness = f64::from(max) / 255.0 * 100.0;
+
+        Hsv((
+            hue.round() as u16 % 360,
+            saturation.round() as u8,
+            brightness.round() as u8,
+        ))
+    }
+
+    /// Return the hue in degrees.
+    pub fn hue(&self) -> u16 {
+        (self.0).0
+    }
+
+    /// Return the saturation in [0, 100].
+    pub fn saturation(&self) -> u8 {
+        (self.0).1
+    }
+
+    /// Return the brightness in [0, 100].
+    pub fn brightness(&self) -> u8 {
+        (self.0).2
+    }
+
     /// Convert a color from Hsv color space to Rgba with the given alpha.
     pub fn to_rgba(&self, alpha: u8) -> [u8; 4] {
         let [r, g, b] = self.to_rgb();
@@ -206,7 +264,7 @@ impl KnownHue {
     /// Return a KnownHue that encloses the given one.
     pub fn from_hue(hue: u16) -> Self {
         // map red to negative values
-        let hue = if hue >= 334 && hue <= 360 {
+        let hue = if (334..=360).contains(&hue) {
             i32::from(hue) - 360
         } else {
             i32::from(hue)
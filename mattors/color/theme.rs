@@ -0,0 +1,566 @@
+//! Named color themes that can be shared among the different artworks. A theme
+//! is composed by a background color, a palette of fill colors and an optional
+//! stroke color. Themes can either be picked from the builtin library or loaded
+//! from JSON or TOML files.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// A color theme.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    background: image::Rgb<u8>,
+    palette: Vec<image::Rgb<u8>>,
+    stroke: Option<image::Rgb<u8>>,
+}
+
+/// The on disk representation of a `Theme`. Colors are stored as strings so
+/// that they can be written by hand.
+#[derive(Debug, Deserialize, Serialize)]
+struct ThemeFile {
+    background: String,
+    palette: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stroke: Option<String>,
+}
+
+struct BuiltinTheme {
+    name: &'static str,
+    background: [u8; 3],
+    palette: &'static [[u8; 3]],
+    stroke: Option<[u8; 3]>,
+}
+
+const BUILTIN_THEMES: &[BuiltinTheme] = &[
+    BuiltinTheme {
+        name: "acid",
+        background: [0x00, 0x80, 0x00],
+        palette: &[
+            [0x3c, 0x9e, 0x0b],
+            [0x62, 0xbe, 0x17],
+            [0x87, 0xde, 0x23],
+            [0xac, 0xff, 0x2f],
+        ],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "bw",
+        background: [0xff, 0xff, 0xff],
+        palette: &[[0xff, 0xff, 0xff]],
+        stroke: Some([0x00, 0x00, 0x00]),
+    },
+    BuiltinTheme {
+        name: "cc232",
+        background: [0xe9, 0xec, 0xde],
+        palette: &[
+            [0x5c, 0x5f, 0x46],
+            [0xff, 0x70, 0x44],
+            [0xff, 0xce, 0x39],
+            [0x66, 0xae, 0xaa],
+        ],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "cc234",
+        background: [0xf7, 0xf4, 0xed],
+        palette: &[
+            [0xff, 0xce, 0x49],
+            [0xed, 0xe8, 0xdc],
+            [0xff, 0x57, 0x36],
+            [0xff, 0x99, 0xb4],
+        ],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "cc238",
+        background: [0xf5, 0xe9, 0xde],
+        palette: &[
+            [0x55, 0x3c, 0x60],
+            [0xff, 0xb0, 0xa0],
+            [0xff, 0x67, 0x49],
+            [0xfb, 0xe0, 0x90],
+        ],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "cc239",
+        background: [0xe0, 0xef, 0xf0],
+        palette: &[
+            [0xe3, 0xdd, 0x34],
+            [0x78, 0x49, 0x6b],
+            [0xf0, 0x52, 0x7f],
+            [0xa7, 0xe0, 0xe2],
+        ],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "cc242",
+        background: [0xfa, 0xf4, 0xe4],
+        palette: &[
+            [0xbb, 0xd4, 0x44],
+            [0xfc, 0xd7, 0x44],
+            [0xfa, 0x7b, 0x53],
+            [0x42, 0x3c, 0x6f],
+        ],
+        stroke: Some([0x00, 0x00, 0x00]),
+    },
+    BuiltinTheme {
+        name: "cc245",
+        background: [0xf6, 0xf4, 0xed],
+        palette: &[
+            [0x0d, 0x4a, 0x4e],
+            [0xff, 0x94, 0x7b],
+            [0xea, 0xd3, 0xa2],
+            [0x52, 0x84, 0xab],
+        ],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "dragons",
+        background: [0x00, 0x00, 0x00],
+        palette: &[[0xff, 0x00, 0x00], [0x00, 0x00, 0xff], [0x00, 0xff, 0x00]],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "flame",
+        background: [0xdc, 0x14, 0x3c],
+        palette: &[
+            [0xff, 0x00, 0x00],
+            [0xff, 0x30, 0x00],
+            [0xff, 0x47, 0x00],
+            [0xff, 0x58, 0x00],
+            [0xff, 0x67, 0x00],
+            [0xff, 0x75, 0x00],
+            [0xff, 0x82, 0x00],
+            [0xff, 0x8e, 0x00],
+            [0xff, 0x9a, 0x00],
+            [0xff, 0xa5, 0x00],
+        ],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "horns",
+        background: [0x00, 0x00, 0x00],
+        palette: &[[0xf6, 0x72, 0x80], [0x1d, 0x27, 0x86], [0x17, 0xb9, 0x78]],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "mondrian",
+        background: [0xe6, 0xeb, 0xc3],
+        palette: &[[0x8d, 0x22, 0x02], [0x0b, 0x18, 0x3b], [0xd0, 0x95, 0x02]],
+        stroke: Some([0x00, 0x00, 0x00]),
+    },
+    BuiltinTheme {
+        name: "night",
+        background: [0x00, 0x00, 0x00],
+        palette: &[[0xff, 0xff, 0xff]],
+        stroke: Some([0xff, 0xff, 0xff]),
+    },
+    BuiltinTheme {
+        name: "ocean",
+        background: [0x00, 0x00, 0x65],
+        palette: &[
+            [0x00, 0x00, 0x80],
+            [0x3a, 0x38, 0x9b],
+            [0x59, 0x68, 0xb5],
+            [0x72, 0x9b, 0xd0],
+            [0x87, 0xcf, 0xeb],
+        ],
+        stroke: None,
+    },
+    BuiltinTheme {
+        name: "patchwork",
+        background: [0xfd, 0xfd, 0xff],
+        palette: &[[0x52, 0x4b, 0x4b]],
+        stroke: Some([0x52, 0x4b, 0x4b]),
+    },
+    BuiltinTheme {
+        name: "sierpinski",
+        background: [0x40, 0xbe, 0xcd],
+        palette: &[
+            [0x02, 0x44, 0x0c],
+            [0x78, 0x94, 0x00],
+            [0xe4, 0xd5, 0x65],
+            [0xf3, 0xf5, 0xe7],
+        ],
+        stroke: Some([0xf3, 0xf5, 0xe7]),
+    },
+    BuiltinTheme {
+        name: "tangled-web",
+        background: [0x00, 0x00, 0x00],
+        palette: &[[0x9a, 0x9a, 0x9a]],
+        stroke: Some([0x9a, 0x9a, 0x9a]),
+    },
+];
+
+impl Theme {
+    /// Create a new `Theme`. Returns `None` if the palette is empty because
+    /// every theme must have at least one fill color.
+    pub fn new(
+        background: image::Rgb<u8>,
+        palette: Vec<image::Rgb<u8>>,
+        stroke: Option<image::Rgb<u8>>,
+    ) -> Option<Self> {
+        if palette.is_empty() {
+            return None;
+        }
+
+        Some(Theme {
+            background,
+            palette,
+            stroke,
+        })
+    }
+
+    /// Return the builtin theme with the given name, if any.
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_THEMES
+            .iter()
+            .find(|t| t.name == name)
+            .map(|t| Theme {
+                background: image::Rgb(t.background),
+                palette: t.palette.iter().map(|c| image::Rgb(*c)).collect(),
+                stroke: t.stroke.map(image::Rgb),
+            })
+    }
+
+    /// Return the names of all the builtin themes in alphabetical order.
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_THEMES.iter().map(|t| t.name)
+    }
+
+    /// Load a theme from the given file. The format is guessed from the
+    /// extension of the file and it can be either `json` or `toml`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read theme file {}, {}", path.display(), e))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&content),
+            Some("toml") => Self::from_toml(&content),
+            _ => Err(format!(
+                "unknown theme file format for {}, please use either json or toml",
+                path.display()
+            )),
+        }
+    }
+
+    /// Save this theme to the given file. The format is guessed from the
+    /// extension of the file and it can be either `json` or `toml`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+
+        let content = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => self.to_json(),
+            Some("toml") => self.to_toml(),
+            _ => Err(format!(
+                "unknown theme file format for {}, please use either json or toml",
+                path.display()
+            )),
+        }?;
+
+        fs::write(path, content)
+            .map_err(|e| format!("cannot write theme file {}, {}", path.display(), e))
+    }
+
+    /// Serialize this theme to a JSON string.
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&self.to_file())
+            .map_err(|e| format!("cannot serialize theme to json, {}", e))
+    }
+
+    /// Serialize this theme to a TOML string.
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(&self.to_file())
+            .map_err(|e| format!("cannot serialize theme to toml, {}", e))
+    }
+
+    /// Parse a theme from a JSON string.
+    pub fn from_json(s: &str) -> Result<Self, String> {
+        let file = serde_json::from_str(s).map_err(|e| format!("bad json theme, {}", e))?;
+
+        Self::from_file(file)
+    }
+
+    /// Parse a theme from a TOML string.
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        let file = toml::from_str(s).map_err(|e| format!("bad toml theme, {}", e))?;
+
+        Self::from_file(file)
+    }
+
+    /// Return the background color.
+    pub fn background(&self) -> image::Rgb<u8> {
+        self.background
+    }
+
+    /// Return the palette of fill colors. It's never empty.
+    pub fn palette(&self) -> &[image::Rgb<u8>] {
+        &self.palette
+    }
+
+    /// Return the stroke color, if any.
+    pub fn stroke(&self) -> Option<image::Rgb<u8>> {
+        self.stroke
+    }
+
+    /// Return the stroke color falling back to the first color of the palette
+    /// if the theme doesn't have a stroke. Handy for artworks that must draw
+    /// lines no matter what.
+    pub fn stroke_or_fill(&self) -> image::Rgb<u8> {
+        self.stroke.unwrap_or(self.palette[0])
+    }
+
+    /// Return the `i`-th palette color wrapping around if `i` is greater than
+    /// the palette length.
+    pub fn cycle_palette(&self, i: usize) -> image::Rgb<u8> {
+        self.palette[i % self.palette.len()]
+    }
+
+    /// Return the color at `t`, clamped in [0, 1], of the gradient that goes
+    /// through all the colors of the palette in order.
+    pub fn gradient(&self, t: f64) -> image::Rgb<u8> {
+        let last = self.palette.len() - 1;
+        let pos = t.clamp(0.0, 1.0) * last as f64;
+        let i = (pos.floor() as usize).min(last.saturating_sub(1));
+
+        let (from, to) = (self.palette[i], self.palette[(i + 1).min(last)]);
+        let t = pos - i as f64;
+        let channel = |c: usize| {
+            let (c1, c2) = (f64::from(from[c]), f64::from(to[c]));
+            (c1 + t * (c2 - c1)).round() as u8
+        };
+
+        image::Rgb([channel(0), channel(1), channel(2)])
+    }
+
+    /// Pick a random color from the palette.
+    pub fn random_fill<R: Rng>(&self, rng: &mut R) -> image::Rgb<u8> {
+        *self.palette.choose(rng).unwrap()
+    }
+
+    fn to_file(&self) -> ThemeFile {
+        ThemeFile {
+            background: to_hex(self.background),
+            palette: self.palette.iter().map(|c| to_hex(*c)).collect(),
+            stroke: self.stroke.map(to_hex),
+        }
+    }
+
+    fn from_file(file: ThemeFile) -> Result<Self, String> {
+        let background = parse_color(&file.background)?;
+        let palette = file
+            .palette
+            .iter()
+            .map(|c| parse_color(c))
+            .collect::<Result<Vec<_>, _>>()?;
+        let stroke = match file.stroke.as_ref().map(|s| s.trim()) {
+            None | Some("none") => None,
+            Some(s) => Some(parse_color(s)?),
+        };
+
+        Theme::new(background, palette, stroke)
+            .ok_or_else(|| "the theme palette must contain at least one color".to_string())
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    /// Parse a theme either by looking up its name in the builtin themes or by
+    /// loading the file at the given path.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(theme) = Theme::builtin(s) {
+            return Ok(theme);
+        }
+
+        if Path::new(s).is_file() {
+            return Theme::load(s);
+        }
+
+        Err(format!(
+            "{} is neither a theme file nor a builtin theme, available themes are: {}",
+            s,
+            Theme::builtin_names().collect::<Vec<_>>().join(", ")
+        ))
+    }
+}
+
+/// Parse a color in one of the following formats: `#rrggbb`, `#rgb`, `rgb(r,
+/// g, b)` or one of the few supported color names(e.g. `white`).
+pub fn parse_color(s: &str) -> Result<image::Rgb<u8>, String> {
+    let s = s.trim().to_lowercase();
+
+    if let Some(hex) = s.strip_prefix('#') {
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("bad hex color {}", s))?;
+
+        return match digits.as_slice() {
+            [r, g, b] => Ok(image::Rgb([r * 17, g * 17, b * 17])),
+            [r1, r0, g1, g0, b1, b0] => Ok(image::Rgb([r1 * 16 + r0, g1 * 16 + g0, b1 * 16 + b0])),
+            _ => Err(format!("bad hex color {}", s)),
+        };
+    }
+
+    if let Some(channels) = s.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+        let channels = channels
+            .split(',')
+            .map(|c| c.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("bad rgb color {}, {}", s, e))?;
+
+        return match channels.as_slice() {
+            [r, g, b] => Ok(image::Rgb([*r, *g, *b])),
+            _ => Err(format!("bad rgb color {}, expected 3 channels", s)),
+        };
+    }
+
+    match s.as_str() {
+        "black" => Ok(image::Rgb([0x00, 0x00, 0x00])),
+        "white" => Ok(image::Rgb([0xff, 0xff, 0xff])),
+        "red" => Ok(image::Rgb([0xff, 0x00, 0x00])),
+        "green" => Ok(image::Rgb([0x00, 0x80, 0x00])),
+        "blue" => Ok(image::Rgb([0x00, 0x00, 0xff])),
+        "crimson" => Ok(image::Rgb([0xdc, 0x14, 0x3c])),
+        _ => Err(format!("unknown color {}", s)),
+    }
+}
+
+/// Format the given color as a `#rrggbb` hex string.
+pub fn to_hex(c: image::Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#faf4e4"), Ok(image::Rgb([0xfa, 0xf4, 0xe4])));
+        assert_eq!(parse_color("#FFF"), Ok(image::Rgb([0xff, 0xff, 0xff])));
+        assert_eq!(
+            parse_color("rgb(255,  48, 0)"),
+            Ok(image::Rgb([255, 48, 0]))
+        );
+        assert_eq!(parse_color(" white "), Ok(image::Rgb([0xff, 0xff, 0xff])));
+
+        assert!(parse_color("#ff").is_err());
+        assert!(parse_color("#gggggg").is_err());
+        assert!(parse_color("rgb(1, 2)").is_err());
+        assert!(parse_color("rgb(1, 2, 256)").is_err());
+        assert!(parse_color("mauve").is_err());
+    }
+
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(to_hex(image::Rgb([0xfa, 0x04, 0xe4])), "#fa04e4");
+        assert_eq!(
+            parse_color(&to_hex(image::Rgb([1, 2, 3]))),
+            Ok(image::Rgb([1, 2, 3]))
+        );
+    }
+
+    #[test]
+    fn test_gradient() {
+        let theme = Theme::new(
+            image::Rgb([0, 0, 0]),
+            vec![
+                image::Rgb([0, 0, 0]),
+                image::Rgb([100, 200, 0]),
+                image::Rgb([200, 0, 50]),
+            ],
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(theme.gradient(-1.0), image::Rgb([0, 0, 0]));
+        assert_eq!(theme.gradient(0.25), image::Rgb([50, 100, 0]));
+        assert_eq!(theme.gradient(0.5), image::Rgb([100, 200, 0]));
+        assert_eq!(theme.gradient(1.0), image::Rgb([200, 0, 50]));
+        assert_eq!(theme.gradient(2.0), image::Rgb([200, 0, 50]));
+
+        let single = Theme::new(image::Rgb([0, 0, 0]), vec![image::Rgb([1, 2, 3])], None).unwrap();
+        assert_eq!(single.gradient(0.5), image::Rgb([1, 2, 3]));
+    }
+
+    #[test]
+    fn test_builtin_themes() {
+        for name in Theme::builtin_names() {
+            let theme = Theme::builtin(name);
+            assert!(theme.is_some(), "theme {} not found", name);
+            assert!(!theme.unwrap().palette().is_empty());
+        }
+
+        let names = Theme::builtin_names().collect::<Vec<_>>();
+        let mut sorted_names = names.clone();
+        sorted_names.sort();
+        assert_eq!(names, sorted_names);
+
+        assert!(Theme::builtin("not-a-theme").is_none());
+        assert_eq!(Theme::from_str("bw"), Ok(Theme::builtin("bw").unwrap()));
+        assert!(Theme::from_str("not-a-theme").is_err());
+    }
+
+    #[test]
+    fn test_theme_from_json() {
+        let theme = Theme::from_json(
+            r##"{"background": "#faf4e4", "palette": ["#bbd444", "rgb(1, 2, 3)"], "stroke": "black"}"##,
+        );
+
+        assert_eq!(
+            theme,
+            Ok(Theme {
+                background: image::Rgb([0xfa, 0xf4, 0xe4]),
+                palette: vec![image::Rgb([0xbb, 0xd4, 0x44]), image::Rgb([1, 2, 3])],
+                stroke: Some(image::Rgb([0, 0, 0])),
+            })
+        );
+
+        assert!(Theme::from_json(r#"{"background": "white", "palette": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_theme_serialization_roundtrip() {
+        for name in Theme::builtin_names() {
+            let theme = Theme::builtin(name).unwrap();
+
+            assert_eq!(
+                Theme::from_json(&theme.to_json().unwrap()),
+                Ok(theme.clone())
+            );
+            assert_eq!(Theme::from_toml(&theme.to_toml().unwrap()), Ok(theme));
+        }
+    }
+
+    #[test]
+    fn test_theme_from_toml() {
+        let theme = Theme::from_toml(
+            r##"
+background = "crimson"
+palette = ["#ff0000", "#ff3000"]
+stroke = "none"
+"##,
+        );
+
+        assert_eq!(
+            theme,
+            Ok(Theme {
+                background: image::Rgb([0xdc, 0x14, 0x3c]),
+                palette: vec![image::Rgb([0xff, 0, 0]), image::Rgb([0xff, 0x30, 0])],
+                stroke: None,
+            })
+        );
+    }
+}
@@ -12,7 +12,7 @@ use std::ops::{Deref, DerefMut};
 use image::Pixel;
 
 use geo::polygon::Polygon;
-use geo::{BoundingBox, LineEquation, Point, PointU32};
+use geo::{BoundingBox, LineEquation, Point, PointF64, PointU32, Transform};
 
 use crate::drawing::line::BresenhamLineIter;
 use crate::drawing::triangle::FlatTriangleIter;
@@ -26,6 +26,10 @@ pub trait Blender<P: image::Pixel> {
 
 /// Simple struct to easily write common geometric primitives onto a given image
 /// using the given `Blender`.
+///
+/// The shapes are drawn in a local coordinate system that is mapped to the
+/// image by the current transform, see `push_transform`. The transformed shapes
+/// are clipped to the image, therefore they can be partially outside of it.
 pub struct Drawer<'a, I: 'a, B>
 where
     I: image::GenericImage,
@@ -33,6 +37,8 @@ where
     B: Blender<I::Pixel>,
 {
     img: &'a mut I,
+    transform: Transform,
+    saved_transforms: Vec<Transform>,
     _blender: PhantomData<B>,
 }
 
@@ -86,6 +92,8 @@ where
     pub fn new(img: &'a mut I) -> Self {
         Drawer {
             img,
+            transform: Transform::identity(),
+            saved_transforms: vec![],
             _blender: PhantomData,
         }
     }
@@ -95,6 +103,58 @@ where
         self.img.dimensions()
     }
 
+    /// Return the transform that maps the local coordinates to the image.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Save the current transform and apply the given one before it, that is
+    /// the shapes are drawn in the coordinate system defined by `transform`
+    /// inside the current one. Restore the previous coordinate system with
+    /// `pop_transform`.
+    pub fn push_transform(&mut self, transform: &Transform) {
+        self.saved_transforms.push(self.transform);
+        self.transform = self.transform.compose(transform);
+    }
+
+    /// Restore the transform that was active before the last call to
+    /// `push_transform`. It does nothing if there's no transform to restore.
+    pub fn pop_transform(&mut self) {
+        if let Some(transform) = self.saved_transforms.pop() {
+            self.transform = transform;
+        }
+    }
+
+    /// Map the point to the image according to the current transform, the
+    /// result might be outside of the image.
+    fn to_image(&self, pt: PointU32) -> PointF64 {
+        self.transform.apply(&pt.cast())
+    }
+
+    /// Map the segment to the image according to the current transform and
+    /// clip it to the image. Return None if the segment is completely outside
+    /// of the image.
+    fn image_segment(&self, start: PointU32, end: PointU32) -> Option<(PointU32, PointU32)> {
+        if self.transform.is_identity() {
+            return Some((start, end));
+        }
+
+        let (width, height) = self.dimensions();
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let (start, end) = clip_segment(
+            self.to_image(start),
+            self.to_image(end),
+            f64::from(width - 1),
+            f64::from(height - 1),
+        )?;
+
+        let round = |p: PointF64| PointU32::new(p.x.round() as u32, p.y.round() as u32);
+        Some((round(start), round(end)))
+    }
+
     /// Draw the given `pix`el at `x` and `y`. It does nothing if the
     /// coordinates are out of bounds.
     pub fn draw_pixel(&mut self, x: u32, y: u32, pix: &I::Pixel) {
@@ -109,6 +169,13 @@ where
     /// Draw a line on the given image using [Bresenham's line
     /// algorithm](https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm).
     pub fn line(&mut self, start: PointU32, end: PointU32, pix: &I::Pixel) {
+        if let Some((start, end)) = self.image_segment(start, end) {
+            self.image_line(start, end, pix);
+        }
+    }
+
+    /// Draw a line between the given image coordinates ignoring the transform.
+    fn image_line(&mut self, start: PointU32, end: PointU32, pix: &I::Pixel) {
         let it = BresenhamLineIter::new(start, end);
         for pt in it {
             self.draw_pixel(pt.x, pt.y, pix);
@@ -124,6 +191,12 @@ where
 
     /// Draw a triangle on the given image filled with the given `pix`.
     pub fn triangle(&mut self, p1: PointU32, p2: PointU32, p3: PointU32, pix: &I::Pixel) {
+        if !self.transform.is_identity() {
+            let points = [self.to_image(p1), self.to_image(p2), self.to_image(p3)];
+            self.fill_image_polygon(&points, pix);
+            return;
+        }
+
         // the idea here is pretty simple: divide the triangle in an upper and
         // bottom flat triangles. At that point draw horizontal lines between the
         // edge points of the triangle.
@@ -156,7 +229,7 @@ where
 
         let upper_triangle = FlatTriangleIter::new(tl, mid, break_point);
         for (start, end) in upper_triangle {
-            self.line(start, end, pix);
+            self.image_line(start, end, pix);
         }
 
         let mut bottom_triangle = FlatTriangleIter::new(br, break_point, mid).peekable();
@@ -172,7 +245,7 @@ where
                     let are_last_points = bottom_triangle.peek().is_none();
 
                     if !are_last_points {
-                        self.line(start, end, pix);
+                        self.image_line(start, end, pix);
                     }
                 }
                 _ => break,
@@ -180,14 +253,32 @@ where
         }
     }
 
-    /// Draw a rectangle filled with the given pixel.
+    /// Draw a rectangle filled with the given pixel. If the current transform
+    /// isn't the identity the area covered by the pixels of the rectangle is
+    /// transformed and drawn as a polygon.
     pub fn rect(&mut self, rect: &BoundingBox<u32>, pix: &I::Pixel) {
         if rect.is_empty() {
             return;
         }
 
+        if !self.transform.is_identity() {
+            let (min, max) = (rect.min().cast::<f64>(), rect.max().cast::<f64>());
+            let corners = [
+                PointF64::new(min.x - 0.5, min.y - 0.5),
+                PointF64::new(max.x + 0.5, min.y - 0.5),
+                PointF64::new(max.x + 0.5, max.y + 0.5),
+                PointF64::new(min.x - 0.5, max.y + 0.5),
+            ]
+            .iter()
+            .map(|p| self.transform.apply(p))
+            .collect::<Vec<_>>();
+
+            self.fill_image_polygon(&corners, pix);
+            return;
+        }
+
         for y in rect.min().y..=rect.max().y {
-            self.line(
+            self.image_line(
                 PointU32::new(rect.min().x, y),
                 PointU32::new(rect.max().x, y),
                 pix,
@@ -195,6 +286,43 @@ where
         }
     }
 
+    /// Draw a circle filled with the given pixel. Only the pixels whose center
+    /// falls inside the circle are drawn, but the pixel under the center is
+    /// always drawn so that tiny circles are still visible.
+    ///
+    /// The current transform moves the center while the radius is scaled by
+    /// the average scaling factor, so circles stay circles even if the
+    /// transform would turn them into ellipses.
+    pub fn circle(&mut self, center: PointF64, radius: f64, pix: &I::Pixel) {
+        let center = self.transform.apply(&center);
+        let radius = radius * self.transform.determinant().abs().sqrt();
+
+        let (width, height) = self.dimensions();
+        let mut drawn = false;
+
+        let ymin = (center.y - radius - 0.5).ceil().max(0.0) as u32;
+        let ymax = (center.y + radius - 0.5)
+            .floor()
+            .min(f64::from(height) - 1.0);
+
+        for y in (ymin..).take_while(|y| f64::from(*y) <= ymax) {
+            let dy = f64::from(y) + 0.5 - center.y;
+            let half = (radius * radius - dy * dy).max(0.0).sqrt();
+
+            let xmin = (center.x - half - 0.5).ceil().max(0.0) as u32;
+            let xmax = (center.x + half - 0.5).floor().min(f64::from(width) - 1.0);
+
+            for x in (xmin..).take_while(|x| f64::from(*x) <= xmax) {
+                self.draw_pixel(x, y, pix);
+                drawn = true;
+            }
+        }
+
+        if !drawn && center.x >= 0.0 && center.y >= 0.0 {
+            self.draw_pixel(center.x as u32, center.y as u32, pix);
+        }
+    }
+
     /// Draw a closed path formed by the given set of points. Note that the line
     /// between the first and the last point is automatically drawn.
     pub fn closed_path<P: IntoIterator<Item = PointU32>>(&mut self, points: P, pix: &I::Pixel) {
@@ -215,10 +343,21 @@ where
     /// the polygon fill algorithm. Doesn't work with self intersecting polygons
     /// and it does no checks to prevent that.
     pub fn polygon(&mut self, polygon: &Polygon<u32>, pix: &I::Pixel) {
+        if !self.transform.is_identity() {
+            let points = polygon
+                .points()
+                .iter()
+                .map(|p| self.to_image(*p))
+                .collect::<Vec<_>>();
+
+            self.fill_image_polygon(&points, pix);
+            return;
+        }
+
         let (ymin, ymax) = polygon
             .points()
             .iter()
-            .fold((u32::max_value(), u32::min_value()), |(ymin, ymax), pt| {
+            .fold((u32::MAX, u32::MIN), |(ymin, ymax), pt| {
                 (ymin.min(pt.y), ymax.max(pt.y))
             });
 
@@ -238,7 +377,7 @@ where
                     let line = LineEquation::between(&p0.cast::<f64>(), &p1.cast::<f64>());
 
                     let x = line.x_at(f64::from(y)).unwrap();
-                    debug_assert!(x >= 0.0 && x <= f64::from(u32::max_value()));
+                    debug_assert!(x >= 0.0 && x <= f64::from(u32::MAX));
 
                     x as u32
                 })
@@ -258,6 +397,54 @@ where
         }
     }
 
+    /// Fill the polygon with the given vertices in image coordinates using the
+    /// even-odd rule. Only the pixels whose center is inside the polygon and
+    /// inside the image are drawn.
+    fn fill_image_polygon(&mut self, points: &[PointF64], pix: &I::Pixel) {
+        let (width, height) = self.dimensions();
+        if points.len() < 3 || width == 0 || height == 0 {
+            return;
+        }
+
+        let (ymin, ymax) = points
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(ymin, ymax), pt| {
+                (ymin.min(pt.y), ymax.max(pt.y))
+            });
+
+        let ymin = ymin.ceil().max(0.0) as u32;
+        let ymax = ymax.min(f64::from(height) - 1.0);
+
+        let mut xs = vec![];
+        for y in (ymin..).take_while(|y| f64::from(*y) <= ymax) {
+            let yc = f64::from(y);
+
+            xs.clear();
+            for (i, a) in points.iter().enumerate() {
+                let b = &points[(i + 1) % points.len()];
+
+                if (a.y <= yc && yc < b.y) || (b.y <= yc && yc < a.y) {
+                    xs.push(a.x + (yc - a.y) * (b.x - a.x) / (b.y - a.y));
+                }
+            }
+
+            xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            for span in xs.chunks(2) {
+                if span.len() < 2 {
+                    break;
+                }
+
+                let xmin = span[0].ceil().max(0.0) as u32;
+                let xmax = span[1].floor().min(f64::from(width) - 1.0);
+
+                for x in (xmin..).take_while(|x| f64::from(*x) <= xmax) {
+                    self.draw_pixel(x, y, pix);
+                }
+            }
+        }
+    }
+
     // TODO: implement a version of polygon that works with self-intersecting
     // polygons. Example points for a self-intersecting polygon:
     //
@@ -285,9 +472,14 @@ where
 {
     /// Draw an antialiased line using a variation of [`Xiaolin Wu's line
     /// algorithm`](https://en.wikipedia.org/wiki/Xiaolin_Wu%27s_line_algorithm).
-    pub fn antialiased_line(&mut self, mut start: PointU32, mut end: PointU32, pix: &I::Pixel) {
+    pub fn antialiased_line(&mut self, start: PointU32, end: PointU32, pix: &I::Pixel) {
         use std::mem;
 
+        let (mut start, mut end) = match self.image_segment(start, end) {
+            Some(segment) => segment,
+            None => return,
+        };
+
         let mut dx = (<i64 as From<u32>>::from(end.x) - <i64 as From<u32>>::from(start.x)).abs();
         let mut dy = (<i64 as From<u32>>::from(end.y) - <i64 as From<u32>>::from(start.y)).abs();
 
@@ -374,6 +566,50 @@ where
     }
 }
 
+/// Clip the segment to the `[0, xmax]` x `[0, ymax]` rectangle using the
+/// [Liang-Barsky
+/// algorithm](https://en.wikipedia.org/wiki/Liang%E2%80%93Barsky_algorithm).
+/// Return None if the segment is completely outside the rectangle.
+fn clip_segment(
+    start: PointF64,
+    end: PointF64,
+    xmax: f64,
+    ymax: f64,
+) -> Option<(PointF64, PointF64)> {
+    let (dx, dy) = (end.x - start.x, end.y - start.y);
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+
+    let bounds = [
+        (-dx, start.x),
+        (dx, xmax - start.x),
+        (-dy, start.y),
+        (dy, ymax - start.y),
+    ];
+
+    for (p, q) in &bounds {
+        if *p == 0.0 {
+            // parallel to the boundary, either completely inside or outside
+            if *q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if *p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+
+    if t0 > t1 {
+        return None;
+    }
+
+    let at = |t: f64| PointF64::new(start.x + t * dx, start.y + t * dy);
+    Some((at(t0), at(t1)))
+}
+
 /// Noop Blender
 pub struct NoopBlender;
 
@@ -391,3 +627,103 @@ impl<P: image::Pixel> Blender<P> for DefaultBlender {
         dst.blend(src);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(img: &image::GrayImage) -> Vec<(u32, u32)> {
+        img.enumerate_pixels()
+            .filter(|(_, _, p)| p[0] > 0)
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    #[test]
+    fn test_transform_stack() {
+        let white = image::Luma([255]);
+        let mut img = image::GrayImage::new(10, 10);
+
+        {
+            let mut drawer = Drawer::new_with_no_blending(&mut img);
+
+            drawer.push_transform(&Transform::translation(2.0, 3.0));
+            drawer.push_transform(&Transform::scaling(2.0, 2.0));
+            drawer.line(PointU32::new(0, 0), PointU32::new(1, 0), &white);
+
+            drawer.pop_transform();
+            drawer.line(PointU32::new(0, 1), PointU32::new(0, 2), &white);
+
+            drawer.pop_transform();
+            drawer.pop_transform();
+            assert!(drawer.transform().is_identity());
+        }
+
+        assert_eq!(lit(&img), vec![(2, 3), (3, 3), (4, 3), (2, 4), (2, 5)]);
+    }
+
+    #[test]
+    fn test_transform_rect() {
+        let white = image::Luma([255]);
+        let mut img = image::GrayImage::new(10, 10);
+
+        {
+            let mut drawer = Drawer::new_with_no_blending(&mut img);
+
+            // a rect rotated by 90 degrees around the origin and moved back in
+            // the image
+            drawer.push_transform(
+                &Transform::rotation(std::f64::consts::FRAC_PI_2).translate(8.0, 0.0),
+            );
+            drawer.rect(&BoundingBox::from_dimensions(4, 2), &white);
+        }
+
+        // the 5x3 pixels of the rect become 3x5 ones
+        let expected = (0..5)
+            .flat_map(|y| (6..9).map(move |x| (x, y)))
+            .collect::<Vec<_>>();
+        assert_eq!(lit(&img), expected);
+
+        let mut img = image::GrayImage::new(10, 10);
+        {
+            let mut drawer = Drawer::new_with_no_blending(&mut img);
+
+            // the same rect with only its last two rows inside the image
+            drawer.push_transform(
+                &Transform::rotation(std::f64::consts::FRAC_PI_2).translate(1.0, 0.0),
+            );
+            drawer.rect(&BoundingBox::from_dimensions(4, 2), &white);
+        }
+
+        let expected = (0..5)
+            .flat_map(|y| (0..2).map(move |x| (x, y)))
+            .collect::<Vec<_>>();
+        assert_eq!(lit(&img), expected);
+    }
+
+    #[test]
+    fn test_transform_clipping() {
+        let white = image::Luma([255]);
+        let mut img = image::GrayImage::new(10, 10);
+
+        {
+            let mut drawer = Drawer::new_with_no_blending(&mut img);
+            drawer.push_transform(&Transform::translation(-2.0, 0.0));
+
+            // the part of the line on the left of the image is cut
+            drawer.line(PointU32::new(0, 0), PointU32::new(4, 4), &white);
+
+            // completely outside shapes aren't drawn at all
+            drawer.line(PointU32::new(0, 6), PointU32::new(1, 9), &white);
+            drawer.triangle(
+                PointU32::new(0, 6),
+                PointU32::new(1, 6),
+                PointU32::new(0, 9),
+                &white,
+            );
+            drawer.circle(PointF64::new(0.0, 8.0), 0.4, &white);
+        }
+
+        assert_eq!(lit(&img), vec![(0, 2), (1, 3), (2, 4)]);
+    }
+}
@@ -2,7 +2,7 @@
 #![deny(missing_docs, warnings)]
 
 use std::f64;
-use std::num::ParseFloatError;
+use std::num::{NonZeroU32, NonZeroUsize, ParseFloatError};
 use std::path::PathBuf;
 use std::str::FromStr;
 
@@ -12,7 +12,7 @@ use num::complex::{Complex64, ParseComplexError};
 
 use structopt::StructOpt;
 
-use geo::{PointF64, PointU32};
+use geo::{Metric, PointF64, PointU32};
 
 use matto::art::delaunay;
 use matto::art::dithering;
@@ -20,18 +20,25 @@ use matto::art::dragon;
 use matto::art::fractree;
 use matto::art::julia::{FractalPoint, JuliaGenIter};
 use matto::art::mondrian;
+use matto::art::palette;
 use matto::art::patchwork;
+use matto::art::poisson::PointDistribution;
 use matto::art::primi;
-use matto::art::primi::Shape;
+use matto::art::primi::shapes::{
+    Circle, Ellipse, QuadraticBezier, Quadrilateral, RotatedEllipse, RotatedRectangle, ThickLine,
+};
+use matto::art::primi::{
+    Alpha, AnyShape, ErrorMetric, PrimifyConfig, Shape, ShapeKind, Strategy, WeightMap,
+};
+use matto::art::quadtree;
 use matto::art::quantize;
 use matto::art::runes;
 use matto::art::sierpinski;
 use matto::art::stippling;
-use matto::art::voronoi;
-
-const LIGHT_GREEN: [u8; 3] = [0x17, 0xB9, 0x78];
-const RED: [u8; 3] = [0xF6, 0x72, 0x80];
-const DARK_BLUE: [u8; 3] = [0x1D, 0x27, 0x86];
+use matto::art::voronoi::{self, SiteWeights};
+use matto::color::luminance;
+use matto::color::theme;
+use matto::color::Theme;
 
 fn parse_complex(s: &str) -> Result<Complex64, ParseComplexError<ParseFloatError>> {
     Complex64::from_str(s.trim())
@@ -40,6 +47,20 @@ fn parse_complex(s: &str) -> Result<Complex64, ParseComplexError<ParseFloatError
 /// Have fun with some generative art
 #[derive(StructOpt, Debug)]
 #[structopt(name = "matto")]
+pub struct Opt {
+    /// The color theme to use. It can be either the name of a builtin theme or
+    /// the path to a json or toml theme file. Quantize, palette, primirs and
+    /// low-poly take their colors from the input image and reject it.
+    #[structopt(short = "t", long = "theme", global = true)]
+    theme: Option<Theme>,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+/// All the available commands.
+#[derive(StructOpt, Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Command {
     /// Generate the dragon fractals.
     #[structopt(name = "dragons")]
@@ -66,6 +87,10 @@ pub enum Command {
     #[structopt(name = "quantize")]
     Quantize(Quantize),
 
+    /// Extract the dominant colors of an image.
+    #[structopt(name = "palette")]
+    Palette(Palette),
+
     /// Generate some Sierpinski triangles.
     #[structopt(name = "sierpinski")]
     Sierpinski(Sierpinski),
@@ -82,14 +107,26 @@ pub enum Command {
     #[structopt(name = "runes")]
     Runes(Runes),
 
-    /// Generate something similar to a proper delaunay triangulation.
+    /// Generate a random Delaunay triangulation.
     #[structopt(name = "delaunay")]
     Delaunay(Delaunay),
 
+    /// Turn an image into a low poly one.
+    #[structopt(name = "low-poly")]
+    LowPoly(LowPoly),
+
     /// Generate some Voronoi Diagrams.
     #[structopt(name = "voronoi")]
     Voronoi(Voronoi),
 
+    /// Turn an image into a stained glass mosaic.
+    #[structopt(name = "mosaic")]
+    Mosaic(Mosaic),
+
+    /// Subdivide an image with a quadtree that is finer on the details.
+    #[structopt(name = "quadtree")]
+    Quadtree(QuadtreeSubdivision),
+
     /// Generate some art according to the Patchwork algorithm.
     #[structopt(name = "patchwork")]
     Patchwork(Patchwork),
@@ -111,6 +148,17 @@ pub enum Command {
     TangledWeb(TangledWeb),
 }
 
+impl Command {
+    /// Return whether the command draws with the colors of a theme, the other
+    /// commands take their colors from the input image.
+    fn uses_theme(&self) -> bool {
+        !matches!(
+            self,
+            Command::Quantize(_) | Command::Palette(_) | Command::Primirs(_) | Command::LowPoly(_)
+        )
+    }
+}
+
 /// Julia Set settings.
 #[derive(StructOpt, Debug)]
 pub struct Julia {
@@ -200,6 +248,41 @@ pub struct Quantize {
     img_path: PathBuf,
 }
 
+/// Extract a palette of the dominant colors of an image. The colors are
+/// printed as hex codes and saved as a swatch image and optionally as a theme.
+#[derive(StructOpt, Debug)]
+pub struct Palette {
+    /// Number of colors to extract.
+    #[structopt(short = "n", long = "colors", default_value = "8")]
+    ncolors: NonZeroUsize,
+
+    /// How to sort the colors, one of frequency, hue or luminance.
+    #[structopt(short = "s", long = "sort", default_value = "frequency")]
+    sort: palette::PaletteSort,
+
+    /// Side of the square of each color in the swatch image.
+    #[structopt(long = "swatch-size", default_value = "64")]
+    swatch_size: NonZeroU32,
+
+    /// Where to write the swatch image.
+    #[structopt(
+        short = "o",
+        long = "output",
+        default_value = "palette.png",
+        parse(from_os_str)
+    )]
+    output_path: PathBuf,
+
+    /// Where to write the palette as a theme file, json or toml depending on
+    /// the extension. The most frequent color is used as the background.
+    #[structopt(long = "theme-output", parse(from_os_str))]
+    theme_path: Option<PathBuf>,
+
+    /// Image to extract the palette from.
+    #[structopt(name = "FILE", parse(from_os_str))]
+    img_path: PathBuf,
+}
+
 /// Draw a Sierpinski Triangle.
 #[derive(StructOpt, Debug)]
 pub struct Sierpinski {
@@ -241,6 +324,11 @@ pub struct Primirs {
     #[structopt(short = "m", long = "mutations", default_value = "100")]
     nmutations: u32,
 
+    /// Number of mutations hill climbing evaluates in parallel before moving
+    /// to the best one.
+    #[structopt(long = "mutation-batch", default_value = "8")]
+    mutation_batch: u32,
+
     /// delta in x that determines how big the shapes will be.
     #[structopt(long = "dx", default_value = "16")]
     dx: u32,
@@ -253,6 +341,96 @@ pub struct Primirs {
     #[structopt(long = "scale-down", default_value = "1")]
     scale_down: u32,
 
+    /// The kind of shapes to use, one of triangle, rectangle,
+    /// rotated-rectangle, ellipse, rotated-ellipse, circle, quadrilateral, line,
+    /// bezier or mixed. mixed picks the best kind of shape at each step.
+    #[structopt(long = "shape", default_value = "triangle")]
+    shape: ShapeKind,
+
+    /// Opacity of the shapes, either a number between 1 and 255 or auto to
+    /// let the algorithm choose the best one for each shape.
+    #[structopt(short = "a", long = "alpha", default_value = "128")]
+    alpha: Alpha,
+
+    /// How to search for the shapes, one of hill-climbing, restarts or
+    /// annealing.
+    #[structopt(long = "strategy", default_value = "hill-climbing")]
+    strategy: Strategy,
+
+    /// Number of random shapes hill-climbing starts from at each step.
+    #[structopt(long = "candidates")]
+    candidates: Option<u32>,
+
+    /// Number of independent hill climbings the restarts strategy runs at each
+    /// step.
+    #[structopt(long = "restarts")]
+    restarts: Option<u32>,
+
+    /// Initial and final temperature of the annealing strategy.
+    #[structopt(long = "temperature", number_of_values = 2)]
+    temperature: Option<Vec<f64>>,
+
+    /// How the error between the image and the shapes is measured, either
+    /// rgb or lab. lab is closer to human perception, but slower.
+    #[structopt(long = "metric", default_value = "rgb")]
+    metric: ErrorMetric,
+
+    /// Grayscale image whose white regions are the most important ones and
+    /// will get more shapes. It's stretched to cover the whole image.
+    #[structopt(long = "weights", parse(from_os_str))]
+    weights_path: Option<PathBuf>,
+
+    /// Give more importance to the edges of the image, ignored if --weights is
+    /// given.
+    #[structopt(long = "edge-weights")]
+    edge_weights: bool,
+
+    /// Print the progress after each step.
+    #[structopt(short = "v", long = "verbose")]
+    verbose: bool,
+
+    /// Also save the result as an SVG image of the same size of the original.
+    #[structopt(long = "svg", parse(from_os_str))]
+    svg_path: Option<PathBuf>,
+
+    /// Also save the list of shapes as JSON. The shapes are relative to the
+    /// scaled down image.
+    #[structopt(long = "json", parse(from_os_str))]
+    json_path: Option<PathBuf>,
+
+    /// Save an intermediate frame every time this many shapes are added.
+    #[structopt(long = "frames")]
+    frames: Option<usize>,
+
+    /// Where to save the frames. If it ends with .gif the frames are saved as
+    /// an animated gif, otherwise as a sequence of images named after it, for
+    /// example frames.png becomes frames-0001.png, frames-0002.png and so on.
+    #[structopt(
+        long = "frames-output",
+        default_value = "frames.gif",
+        parse(from_os_str)
+    )]
+    frames_path: PathBuf,
+
+    /// How long each frame of the animated gif is shown in milliseconds.
+    #[structopt(long = "frame-delay", default_value = "100")]
+    frame_delay: u16,
+
+    /// Start from the shapes saved with --json or --checkpoint and keep adding
+    /// --shapes more. The scale down must be the same as the one the shapes
+    /// were generated with.
+    #[structopt(long = "resume", parse(from_os_str))]
+    resume_path: Option<PathBuf>,
+
+    /// Periodically save the shapes found so far as JSON so that a run can be
+    /// resumed if interrupted.
+    #[structopt(long = "checkpoint", parse(from_os_str))]
+    checkpoint_path: Option<PathBuf>,
+
+    /// Save a checkpoint every time this many shapes are added.
+    #[structopt(long = "checkpoint-every", default_value = "10")]
+    checkpoint_every: usize,
+
     /// Where to write the "primitized" image.
     #[structopt(
         short = "o",
@@ -330,13 +508,22 @@ pub struct Runes {
     output_path: PathBuf,
 }
 
-/// Generate something similar to a proper delaunay triangulation.
+/// Generate a random Delaunay triangulation.
 #[derive(StructOpt, Debug)]
 pub struct Delaunay {
     /// Size of the grid where to put points.
     #[structopt(short = "g", long = "grid-size", default_value = "25")]
     grid_size: u32,
 
+    /// How the random points are scattered, either uniform or poisson. Poisson
+    /// points are evenly spaced, but there are only about as many as requested.
+    #[structopt(long = "distribution", default_value = "uniform")]
+    distribution: PointDistribution,
+
+    /// Number of steps of Lloyd's relaxation to spread the points evenly.
+    #[structopt(long = "relax", default_value = "0")]
+    relax: usize,
+
     /// Width of the image.
     #[structopt(short = "w", long = "width", default_value = "1920")]
     width: u32,
@@ -355,6 +542,37 @@ pub struct Delaunay {
     output_path: PathBuf,
 }
 
+/// Turn an image into a low poly one.
+#[derive(StructOpt, Debug)]
+pub struct LowPoly {
+    /// Number of vertices of the triangles.
+    #[structopt(short = "p", long = "points", default_value = "2000")]
+    npoints: usize,
+
+    /// Fraction of the points placed on the edges of the image, the others
+    /// are scattered randomly.
+    #[structopt(short = "e", long = "edge-ratio", default_value = "0.8")]
+    edge_ratio: f64,
+
+    /// How to color the triangles, either with the mean color of the pixels
+    /// they cover or with the color under their centroid.
+    #[structopt(short = "c", long = "color", default_value = "mean")]
+    color: delaunay::TriangleColor,
+
+    /// Where to write the final image.
+    #[structopt(
+        short = "o",
+        long = "output",
+        default_value = "low-poly.png",
+        parse(from_os_str)
+    )]
+    output_path: PathBuf,
+
+    /// Image to turn into triangles.
+    #[structopt(name = "FILE", parse(from_os_str))]
+    img_path: PathBuf,
+}
+
 /// Generate some Voronoi Diagrams.
 #[derive(StructOpt, Debug)]
 pub struct Voronoi {
@@ -367,6 +585,30 @@ pub struct Voronoi {
     #[structopt(short = "g", long = "gradient-background")]
     gradient_background: bool,
 
+    /// How the random points are scattered, either uniform or poisson. Poisson
+    /// points are evenly spaced, but there are only about as many as requested.
+    #[structopt(long = "distribution", default_value = "uniform")]
+    distribution: PointDistribution,
+
+    /// Number of steps of Lloyd's relaxation to spread the points evenly.
+    #[structopt(long = "relax", default_value = "0")]
+    relax: usize,
+
+    /// How the distance from the points is measured, one of euclidean,
+    /// squared-euclidean, manhattan, chebyshev or minkowski:<p>.
+    #[structopt(short = "m", long = "metric", default_value = "euclidean")]
+    metric: Metric,
+
+    /// Randomly weight the points, either additive, multiplicative or power.
+    /// Power diagrams always use the squared Euclidean distance.
+    #[structopt(long = "weights")]
+    weights: Option<SiteWeights>,
+
+    /// Draw cellular noise colored by the difference between the distances
+    /// to the second closest and the closest point instead of the cells.
+    #[structopt(short = "c", long = "cellular")]
+    cellular: bool,
+
     /// Width of the image.
     #[structopt(short = "w", long = "width", default_value = "1920")]
     width: u32,
@@ -385,6 +627,65 @@ pub struct Voronoi {
     output_path: PathBuf,
 }
 
+/// Turn an image into a stained glass mosaic.
+#[derive(StructOpt, Debug)]
+pub struct Mosaic {
+    /// Number of cells of the mosaic.
+    #[structopt(short = "p", long = "points", default_value = "1000")]
+    npoints: usize,
+
+    /// Put more and smaller cells where the image has more details.
+    #[structopt(short = "a", long = "adaptive")]
+    adaptive: bool,
+
+    /// Width of the lead borders between the cells, 0 to have no borders.
+    /// The color of the borders is the stroke color of the theme.
+    #[structopt(short = "l", long = "lead-width", default_value = "0")]
+    lead_width: f64,
+
+    /// Where to write the final image.
+    #[structopt(
+        short = "o",
+        long = "output",
+        default_value = "mosaic.png",
+        parse(from_os_str)
+    )]
+    output_path: PathBuf,
+
+    /// Image to turn into a mosaic.
+    #[structopt(name = "FILE", parse(from_os_str))]
+    img_path: PathBuf,
+}
+
+/// Subdivide an image with a quadtree that is finer on the details.
+#[derive(StructOpt, Debug)]
+pub struct QuadtreeSubdivision {
+    /// Number of points sampled on the edges of the image.
+    #[structopt(short = "p", long = "points", default_value = "2000")]
+    npoints: usize,
+
+    /// Maximum number of points in a cell, the higher the bigger the cells.
+    #[structopt(short = "c", long = "capacity", default_value = "1")]
+    capacity: usize,
+
+    /// Draw the borders of the cells with the stroke color of the theme.
+    #[structopt(short = "b", long = "borders")]
+    borders: bool,
+
+    /// Where to write the final image.
+    #[structopt(
+        short = "o",
+        long = "output",
+        default_value = "quadtree.png",
+        parse(from_os_str)
+    )]
+    output_path: PathBuf,
+
+    /// Image to subdivide.
+    #[structopt(name = "FILE", parse(from_os_str))]
+    img_path: PathBuf,
+}
+
 /// Generate some art according to the PatchWork algorithm.
 #[derive(StructOpt, Debug)]
 pub struct Patchwork {
@@ -405,6 +706,21 @@ pub struct Patchwork {
     #[structopt(short = "i", long = "iterations", default_value = "3")]
     iterations: usize,
 
+    /// How the random points are scattered, either uniform or poisson. Poisson
+    /// points are evenly spaced, but there are only about as many as requested.
+    #[structopt(long = "distribution", default_value = "uniform")]
+    distribution: PointDistribution,
+
+    /// Scale the hulls by this factor before clipping them to their parent
+    /// polygon, values above 1 make the shapes overlap.
+    #[structopt(short = "g", long = "grow", default_value = "1.0")]
+    grow: f64,
+
+    /// Shrink the filled polygons by this many pixels so that the background
+    /// shows between them.
+    #[structopt(long = "inset", default_value = "0")]
+    inset: f64,
+
     /// Width of the image.
     #[structopt(short = "w", long = "width", default_value = "1920")]
     width: u32,
@@ -429,6 +745,11 @@ pub struct Stippling {
     #[structopt(subcommand)]
     command: StipplingCommand,
 
+    /// How the random points are scattered, either uniform or poisson. Poisson
+    /// points are evenly spaced, but there are only about as many as requested.
+    #[structopt(long = "distribution", default_value = "uniform")]
+    distribution: PointDistribution,
+
     /// Width of the image.
     #[structopt(short = "w", long = "width", default_value = "1920")]
     width: u32,
@@ -457,6 +778,11 @@ pub enum StipplingCommand {
     /// Stippling some rectangles.
     #[structopt(name = "rects")]
     StipplingRects(StipplingRects),
+
+    /// Stippling an image so that the dots follow its darkness. The size of
+    /// the result is the size of the image, width and height are ignored.
+    #[structopt(name = "image")]
+    Image(StipplingImage),
 }
 
 /// Stippling some bands to give a gradient-like look.
@@ -492,6 +818,37 @@ pub struct StipplingRects {
     minimum_area: u32,
 }
 
+/// Stippling an image so that the dots follow its darkness.
+#[derive(StructOpt, Debug)]
+pub struct StipplingImage {
+    /// Path to the image to stipple.
+    #[structopt(parse(from_os_str))]
+    img_path: PathBuf,
+
+    /// The number of dots.
+    #[structopt(short = "p", long = "points", default_value = "5000")]
+    points: usize,
+
+    /// The maximum number of relaxation steps, the more the smoother the dots
+    /// distribution.
+    #[structopt(short = "i", long = "iterations", default_value = "30")]
+    iterations: usize,
+
+    /// The radius of the dots.
+    #[structopt(short = "r", long = "radius", default_value = "1")]
+    radius: f64,
+
+    /// The radius of the dots in the darkest areas, if present the radius of
+    /// every dot grows from `radius` to this value according to the darkness
+    /// around it.
+    #[structopt(long = "max-radius")]
+    max_radius: Option<f64>,
+
+    /// Whether to save the image as an svg or png.
+    #[structopt(long = "svg")]
+    svg: bool,
+}
+
 /// Generate some art inspired by Mondrian's Composition in Red, Blue and
 /// Yellow.
 #[derive(StructOpt, Debug)]
@@ -533,6 +890,12 @@ pub struct Dither {
     #[structopt(long = "rgb")]
     rgb: bool,
 
+    /// Dither with the given number of dominant colors of the image instead
+    /// of evenly spaced levels, it implies --rgb. Without it the colors of the
+    /// theme are used if one is given.
+    #[structopt(short = "p", long = "palette")]
+    palette: Option<usize>,
+
     /// Where to write the dithered image.
     #[structopt(
         short = "o",
@@ -581,49 +944,71 @@ pub struct TangledWeb {
 }
 
 fn main() {
-    let command = Command::from_args();
+    let opt = Opt::from_args();
+    let theme = opt.theme.as_ref();
 
-    match command {
-        Command::Dragons { iterations } => spawn_dragons(iterations),
-        Command::Horns { iterations } => spawn_horns(iterations),
+    if theme.is_some() && !opt.command.uses_theme() {
+        structopt::clap::Error::with_description(
+            "the --theme option is not supported by this command",
+            structopt::clap::ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+
+    match opt.command {
+        Command::Dragons { iterations } => spawn_dragons(iterations, theme),
+        Command::Horns { iterations } => spawn_horns(iterations, theme),
         Command::Julia(ref config) => match config.set_type {
             None | Some(JuliaSet::All) => {
-                mandelbrot(config);
-                planets(config);
-                dragon_like(config);
-                black_holes(config);
+                mandelbrot(config, theme);
+                planets(config, theme);
+                dragon_like(config, theme);
+                black_holes(config, theme);
             }
-            Some(JuliaSet::Mandelbrot) => mandelbrot(config),
-            Some(JuliaSet::Planets) => planets(config),
-            Some(JuliaSet::DragonLikeSpiral) => dragon_like(config),
-            Some(JuliaSet::BlackHoles) => black_holes(config),
+            Some(JuliaSet::Mandelbrot) => mandelbrot(config, theme),
+            Some(JuliaSet::Planets) => planets(config, theme),
+            Some(JuliaSet::DragonLikeSpiral) => dragon_like(config, theme),
+            Some(JuliaSet::BlackHoles) => black_holes(config, theme),
             Some(JuliaSet::Custom {
                 ref start,
                 ref end,
                 ref c,
                 ref name,
-            }) => create_julia_set(config, name, start, end, |f, it| {
+            }) => create_julia_set(config, theme, name, start, end, |f, it| {
                 FractalPoint::julia(f, *c, it)
             }),
         },
         Command::Quantize(ref config) => quantize_image(config),
-        Command::Sierpinski(ref config) => spawn_sierpinski(config),
+        Command::Palette(ref config) => extract_palette(config),
+        Command::Sierpinski(ref config) => spawn_sierpinski(config, theme),
         Command::Primirs(ref config) => primirs(config),
-        Command::FractalTree(ref config) => fractal_tree(config),
-        Command::Runes(ref config) => runes(config),
-        Command::Delaunay(ref config) => delaunay(config),
-        Command::Voronoi(ref config) => voronoi(config),
-        Command::Patchwork(ref config) => patchwork(config),
-        Command::Stippling(ref config) => stippling(config),
-        Command::Mondrian(ref config) => mondrian(config),
-        Command::Dither(ref config) => dither(config),
-        Command::TangledWeb(ref config) => tangled_web(config),
+        Command::FractalTree(ref config) => fractal_tree(config, theme),
+        Command::Runes(ref config) => runes(config, theme),
+        Command::Delaunay(ref config) => delaunay(config, theme),
+        Command::LowPoly(ref config) => low_poly(config),
+        Command::Voronoi(ref config) => voronoi(config, theme),
+        Command::Mosaic(ref config) => mosaic(config, theme),
+        Command::Quadtree(ref config) => quadtree_subdivision(config, theme),
+        Command::Patchwork(ref config) => patchwork(config, theme),
+        Command::Stippling(ref config) => stippling(config, theme),
+        Command::Mondrian(ref config) => mondrian(config, theme),
+        Command::Dither(ref config) => dither(config, theme),
+        Command::TangledWeb(ref config) => tangled_web(config, theme),
     }
 }
 
-fn mandelbrot(config: &Julia) {
+/// Return the theme passed by the user or the builtin theme with the given
+/// name if the user didn't choose any.
+fn theme_or_builtin(theme: Option<&Theme>, name: &str) -> Theme {
+    theme
+        .cloned()
+        .unwrap_or_else(|| Theme::builtin(name).expect("unknown builtin theme"))
+}
+
+fn mandelbrot(config: &Julia, theme: Option<&Theme>) {
     create_julia_set(
         config,
+        theme,
         "mandelbrot",
         &PointF64::new(-3.0, -1.2),
         &PointF64::new(1.0, 1.2),
@@ -631,11 +1016,12 @@ fn mandelbrot(config: &Julia) {
     );
 }
 
-fn planets(config: &Julia) {
+fn planets(config: &Julia, theme: Option<&Theme>) {
     let c = Complex64::new(-0.4, 0.6);
 
     create_julia_set(
         config,
+        theme,
         "planets",
         &PointF64::new(-3.0, -1.2),
         &PointF64::new(2.0, 1.2),
@@ -643,11 +1029,12 @@ fn planets(config: &Julia) {
     );
 }
 
-fn dragon_like(config: &Julia) {
+fn dragon_like(config: &Julia, theme: Option<&Theme>) {
     let c = Complex64::new(-0.8, 0.156);
 
     create_julia_set(
         config,
+        theme,
         "dragon_like",
         &PointF64::new(-3.0, -1.2),
         &PointF64::new(2.0, 1.2),
@@ -655,11 +1042,12 @@ fn dragon_like(config: &Julia) {
     );
 }
 
-fn black_holes(config: &Julia) {
+fn black_holes(config: &Julia, theme: Option<&Theme>) {
     let c = Complex64::new(0.285, 0.01);
 
     create_julia_set(
         config,
+        theme,
         "black_holes",
         &PointF64::new(-1.2, -1.2),
         &PointF64::new(1.2, 1.0),
@@ -667,8 +1055,14 @@ fn black_holes(config: &Julia) {
     );
 }
 
-fn create_julia_set<F>(config: &Julia, name: &str, start: &PointF64, end: &PointF64, gen: F)
-where
+fn create_julia_set<F>(
+    config: &Julia,
+    theme: Option<&Theme>,
+    name: &str,
+    start: &PointF64,
+    end: &PointF64,
+    gen: F,
+) where
     F: Fn(Complex64, u32) -> FractalPoint,
 {
     let stepx = (end.x - start.x) / f64::from(config.width);
@@ -686,28 +1080,34 @@ where
 
     println!("Fractal: {}", name);
 
-    let imgbuf = frac_it
-        .into_image()
-        .expect("error while generating fractal");
+    let imgbuf = match theme {
+        Some(theme) => frac_it.into_themed_image(theme),
+        None => frac_it.into_image(),
+    }
+    .expect("error while generating fractal");
     let img = image::ImageRgb8(imgbuf);
 
     // let img = img.resize_exact(width, height, image::Lanczos3);
 
-    img.save(&format!("{}.png", name))
+    img.save(format!("{}.png", name))
         .expect("cannot save output image");
 }
 
-fn spawn_dragons(iterations: u32) {
+fn spawn_dragons(iterations: u32, theme: Option<&Theme>) {
     println!("Dragons!");
 
+    let theme = theme_or_builtin(theme, "dragons");
+
     let red = dragon::dragon(iterations, dragon::Move::Left);
-    let red_img = dragon::dragon_to_image(&red, 1920, 1080, 1480, 730, 2, [255, 0, 0]);
+    let red_img = dragon::dragon_to_image(&red, 1920, 1080, 1480, 730, 2, theme.cycle_palette(0).0);
 
     let blue = dragon::dragon(iterations, dragon::Move::Up);
-    let blue_img = dragon::dragon_to_image(&blue, 1920, 1080, 500, 730, 2, [0, 0, 255]);
+    let blue_img =
+        dragon::dragon_to_image(&blue, 1920, 1080, 500, 730, 2, theme.cycle_palette(1).0);
 
     let green = dragon::dragon(iterations, dragon::Move::Right);
-    let green_img = dragon::dragon_to_image(&green, 1920, 1080, 500, 350, 2, [0, 255, 0]);
+    let green_img =
+        dragon::dragon_to_image(&green, 1920, 1080, 500, 350, 2, theme.cycle_palette(2).0);
 
     let redblue_img = overlap_images(&red_img, &blue_img).unwrap();
     let rgb_img = overlap_images(&redblue_img, &green_img).unwrap();
@@ -719,17 +1119,21 @@ fn spawn_dragons(iterations: u32) {
     rgb_img.save("rgb-dragon.png").unwrap();
 }
 
-fn spawn_horns(iterations: u32) {
+fn spawn_horns(iterations: u32, theme: Option<&Theme>) {
     println!("Horns!");
 
+    let theme = theme_or_builtin(theme, "horns");
+
     let red = dragon::horns(iterations, dragon::Move::Left);
-    let red_img = dragon::dragon_to_image(&red, 1920, 1080, 1480, 530, 2, RED);
+    let red_img = dragon::dragon_to_image(&red, 1920, 1080, 1480, 530, 2, theme.cycle_palette(0).0);
 
     let blue = dragon::horns(iterations, dragon::Move::Up);
-    let blue_img = dragon::dragon_to_image(&blue, 1920, 1080, 550, 790, 2, DARK_BLUE);
+    let blue_img =
+        dragon::dragon_to_image(&blue, 1920, 1080, 550, 790, 2, theme.cycle_palette(1).0);
 
     let green = dragon::horns(iterations, dragon::Move::Right);
-    let green_img = dragon::dragon_to_image(&green, 1920, 1080, 960, 550, 2, LIGHT_GREEN);
+    let green_img =
+        dragon::dragon_to_image(&green, 1920, 1080, 960, 550, 2, theme.cycle_palette(2).0);
 
     red_img.save("red-horns.png").unwrap();
     blue_img.save("blue-horns.png").unwrap();
@@ -779,28 +1183,67 @@ fn quantize_image(config: &Quantize) {
         .expect("cannot save quantized file");
 }
 
-fn spawn_sierpinski(config: &Sierpinski) {
-    let mut img =
-        image::RgbImage::from_pixel(config.width, config.height, image::Rgb([0x40, 0xbe, 0xcd]));
+fn extract_palette(config: &Palette) {
+    let img = image::open(&config.img_path)
+        .expect("cannot open source image file")
+        .to_rgb();
+
+    let freqs = palette::extract_palette(img.pixels().cloned(), config.ncolors.get(), config.sort);
+    let colors = freqs.iter().map(|(c, _)| *c).collect::<Vec<_>>();
+
+    for c in &colors {
+        println!("{}", theme::to_hex(*c));
+    }
+
+    palette::swatch(&colors, config.swatch_size.get())
+        .expect("cannot create swatch image")
+        .save(&config.output_path)
+        .expect("cannot save swatch image");
+
+    if let Some(ref theme_path) = config.theme_path {
+        let background = freqs
+            .iter()
+            .max_by_key(|(c, f)| (*f, c.0))
+            .map(|(c, _)| *c)
+            .expect("cannot create a theme from an empty palette");
+
+        let fill = colors
+            .iter()
+            .cloned()
+            .filter(|c| *c != background)
+            .collect::<Vec<_>>();
+        // a single color image would make the shapes invisible on the
+        // background, fill them with black or white instead
+        let fill = if fill.is_empty() {
+            if luminance(background.0) > 127.5 {
+                vec![image::Rgb([0, 0, 0])]
+            } else {
+                vec![image::Rgb([255, 255, 255])]
+            }
+        } else {
+            fill
+        };
+
+        Theme::new(background, fill, None)
+            .expect("cannot create a theme from an empty palette")
+            .save(theme_path)
+            .expect("cannot save theme file");
+    }
+}
+
+fn spawn_sierpinski(config: &Sierpinski, theme: Option<&Theme>) {
+    let theme = theme_or_builtin(theme, "sierpinski");
+
+    let mut img = image::RgbImage::from_pixel(config.width, config.height, theme.background());
 
     if config.fancy {
-        sierpinski::fancy_sierpinski(
-            &mut img,
-            config.divide_steps,
-            false,
-            &[
-                image::Rgb([0x02, 0x44, 0x0c]),
-                image::Rgb([0x78, 0x94, 0x00]),
-                image::Rgb([0xe4, 0xd5, 0x65]),
-                image::Rgb([0xf3, 0xf5, 0xe7]),
-            ],
-        );
+        sierpinski::fancy_sierpinski(&mut img, config.divide_steps, false, theme.palette());
     } else {
         sierpinski::fancy_sierpinski(
             &mut img,
             config.divide_steps,
             true,
-            &[image::Rgb([0xf3, 0xf5, 0xe7])],
+            &[theme.stroke_or_fill()],
         );
     }
 
@@ -809,58 +1252,211 @@ fn spawn_sierpinski(config: &Sierpinski) {
 }
 
 fn primirs(config: &Primirs) {
+    match config.shape {
+        ShapeKind::Triangle => primirs_with_shape::<geo::Triangle<u32>>(config),
+        ShapeKind::Rectangle => primirs_with_shape::<geo::BoundingBox<u32>>(config),
+        ShapeKind::RotatedRectangle => primirs_with_shape::<RotatedRectangle>(config),
+        ShapeKind::Ellipse => primirs_with_shape::<Ellipse>(config),
+        ShapeKind::RotatedEllipse => primirs_with_shape::<RotatedEllipse>(config),
+        ShapeKind::Circle => primirs_with_shape::<Circle>(config),
+        ShapeKind::Quadrilateral => primirs_with_shape::<Quadrilateral>(config),
+        ShapeKind::Line => primirs_with_shape::<ThickLine>(config),
+        ShapeKind::QuadraticBezier => primirs_with_shape::<QuadraticBezier>(config),
+        ShapeKind::Mixed => primirs_with_shape::<AnyShape>(config),
+    }
+}
+
+fn primirs_with_shape<S>(config: &Primirs)
+where
+    S: Shape + serde::Serialize + serde::de::DeserializeOwned,
+{
     let img = image::open(&config.img_path).expect("cannot open source image file");
     let rgba = img.to_rgba();
 
-    let primitized = if config.scale_down > 1 {
-        let resized = image::imageops::resize(
+    let mut primify_config = PrimifyConfig::new()
+        .shapes(config.nshapes)
+        .mutations(config.nmutations)
+        .mutation_batch(config.mutation_batch)
+        .delta(config.dx, config.dy)
+        .alpha(config.alpha)
+        .strategy(primirs_strategy(config))
+        .metric(config.metric);
+
+    if let Some(ref weights_path) = config.weights_path {
+        let mask = image::open(weights_path).expect("cannot open weights image");
+        primify_config = primify_config.weights(WeightMap::from_mask(&mask.to_luma()));
+    } else if config.edge_weights {
+        primify_config = primify_config.weights(WeightMap::from_edges(&img.to_luma()));
+    }
+
+    if let Some(k) = config.frames {
+        primify_config = primify_config.frame_every(k);
+    }
+
+    let is_gif = config
+        .frames_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+
+    let mut gif_encoder = if config.frames.is_some() && is_gif {
+        let f = std::fs::File::create(&config.frames_path).expect("cannot create frames file");
+        Some(image::gif::Encoder::new(f))
+    } else {
+        None
+    };
+    let mut nframes = 0;
+
+    let on_progress = |progress: &primi::Progress<image::Rgba<u8>, S>| {
+        if config.verbose {
+            println!(
+                "step {}/{} shapes {} error {:?}",
+                progress.step, progress.steps, progress.shapes, progress.error
+            );
+        }
+
+        if let Some(ref checkpoint_path) = config.checkpoint_path {
+            let every = config.checkpoint_every.max(1);
+            if (progress.improved && progress.shapes.is_multiple_of(every))
+                || progress.step == progress.steps
+            {
+                save_checkpoint(checkpoint_path, &progress.current.to_json().unwrap())
+                    .expect("cannot save checkpoint");
+            }
+        }
+
+        if !progress.frame {
+            return;
+        }
+
+        nframes += 1;
+        match gif_encoder {
+            Some(ref mut encoder) => {
+                // gif delays are in hundredths of a second
+                let delay = num::rational::Ratio::from_integer(config.frame_delay / 10);
+                let frame =
+                    image::Frame::from_parts(progress.current.best_image.clone(), 0, 0, delay);
+
+                encoder
+                    .encode_frames(std::iter::once(frame))
+                    .expect("cannot save frame");
+            }
+            None => progress
+                .current
+                .best_image
+                .save(frame_path(&config.frames_path, nframes))
+                .expect("cannot save frame"),
+        }
+    };
+
+    let resized = if config.scale_down > 1 {
+        Some(image::imageops::resize(
             &rgba,
             img.width() / config.scale_down,
             img.height() / config.scale_down,
             image::Triangle,
-        );
+        ))
+    } else {
+        None
+    };
 
-        primi::primify::<_, geo::Triangle<u32>>(
-            &resized,
-            config.nshapes,
-            config.nmutations,
-            config.dx,
-            config.dy,
-        )
-        .map(|prim| {
-            let mut upscaled_img =
-                image::RgbaImage::from_pixel(rgba.width(), rgba.height(), prim.dominant_color);
+    let target = resized.as_ref().unwrap_or(&rgba);
+    let prim = match config.resume_path {
+        Some(ref resume_path) => {
+            let json = std::fs::read_to_string(resume_path).expect("cannot read shapes file");
+            let start = primi::ShapeList::<S>::from_json(&json).expect("bad shapes file");
 
-            for shape in prim.shapes {
-                let upscaled_shape = shape.upscale(config.scale_down);
+            primi::resume(target, &start, &primify_config, on_progress).expect("primirs error")
+        }
+        None => {
+            primi::primify::<_, S>(target, &primify_config, on_progress).expect("primirs error")
+        }
+    };
 
-                upscaled_shape.draw(&rgba, &mut upscaled_img);
-            }
+    println!("best error {:?}", prim.best_error);
 
-            (upscaled_img, prim.best_error)
-        })
-    } else {
-        primi::primify::<_, geo::Triangle<u32>>(
-            &rgba,
-            config.nshapes,
-            config.nmutations,
-            config.dx,
-            config.dy,
-        )
-        .map(|prim| (prim.best_image, prim.best_error))
-    };
+    if let Some(ref svg_path) = config.svg_path {
+        let mut f = std::fs::File::create(svg_path).expect("cannot create svg file");
+        prim.write_svg(&mut f, rgba.dimensions())
+            .expect("error writing svg");
+    }
+
+    if let Some(ref json_path) = config.json_path {
+        let json = prim.to_json().expect("cannot serialize shapes");
+        std::fs::write(json_path, json).expect("cannot save shapes file");
+    }
+
+    let best_image = if resized.is_some() {
+        let mut upscaled_img =
+            image::RgbaImage::from_pixel(rgba.width(), rgba.height(), prim.dominant_color);
 
-    let (best_image, best_error) = primitized.expect("primirs error");
+        for (shape, color) in &prim.shapes {
+            let upscaled_shape = shape.upscale(config.scale_down);
 
-    println!("best error {:?}", best_error);
+            upscaled_shape.draw(color, &mut upscaled_img);
+        }
+
+        upscaled_img
+    } else {
+        prim.best_image
+    };
 
     best_image
         .save(&config.output_path)
         .expect("cannot save primitized file");
 }
 
-fn fractal_tree(config: &FractalTree) {
-    let mut img = image::GrayImage::from_pixel(config.width, config.height, image::Luma([0]));
+/// Save the checkpoint by writing a temporary file first and then by renaming
+/// it so that a crash never leaves a truncated checkpoint behind.
+fn save_checkpoint(path: &std::path::Path, json: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Return the path of the `i`th frame of a sequence of images named after
+/// `path`.
+fn frame_path(path: &std::path::Path, i: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(|| "frame".into(), |s| s.to_string_lossy());
+    let ext = path
+        .extension()
+        .map_or_else(|| "png".into(), |s| s.to_string_lossy());
+
+    path.with_file_name(format!("{}-{:04}.{}", stem, i, ext))
+}
+
+/// Return the search strategy with the parameters chosen by the user.
+fn primirs_strategy(config: &Primirs) -> Strategy {
+    match config.strategy {
+        Strategy::HillClimbing { candidates } => Strategy::HillClimbing {
+            candidates: config.candidates.unwrap_or(candidates),
+        },
+        Strategy::RandomRestarts { restarts } => Strategy::RandomRestarts {
+            restarts: config.restarts.unwrap_or(restarts),
+        },
+        Strategy::SimulatedAnnealing {
+            initial_temperature,
+            final_temperature,
+        } => match config.temperature {
+            Some(ref t) => Strategy::SimulatedAnnealing {
+                initial_temperature: t[0],
+                final_temperature: t[1],
+            },
+            None => Strategy::SimulatedAnnealing {
+                initial_temperature,
+                final_temperature,
+            },
+        },
+    }
+}
+
+fn fractal_tree(config: &FractalTree, theme: Option<&Theme>) {
+    let theme = theme_or_builtin(theme, "night");
+
+    let mut img = image::RgbImage::from_pixel(config.width, config.height, theme.background());
 
     fractree::fractal_tree(
         &mut img,
@@ -870,85 +1466,177 @@ fn fractal_tree(config: &FractalTree) {
         config.branching_angle_step,
         f64::from(config.height) / 3.0,
         config.branch_len_factor,
-        &image::Luma([0xFF]),
+        &theme.stroke_or_fill(),
     );
 
     img.save(&config.output_path).expect("cannot save image");
 }
 
-fn runes(config: &Runes) {
-    let white_pix = image::Luma([0xFF]);
-    let black_pix = image::Luma([0]);
+fn runes(config: &Runes, theme: Option<&Theme>) {
+    let theme = theme_or_builtin(theme, "bw");
 
-    let mut imgbuf =
-        image::GrayImage::from_pixel(config.ntiles * config.width, config.height, white_pix);
+    let mut imgbuf = image::RgbImage::from_pixel(
+        config.ntiles * config.width,
+        config.height,
+        theme.background(),
+    );
 
     for i in 0..config.ntiles {
         let mut rune = imgbuf.sub_image(i * config.width, 0, config.width, config.height);
-        runes::draw_random_rune(&mut rune, config.npoints, &black_pix);
+        runes::draw_random_rune(&mut rune, config.npoints, &theme.stroke_or_fill());
     }
 
     imgbuf.save(&config.output_path).expect("cannot save image");
 }
 
-fn delaunay(config: &Delaunay) {
+fn delaunay(config: &Delaunay, theme: Option<&Theme>) {
     let mut color_config = matto::color::RandomColorConfig::new()
         .hue(matto::color::KnownHue::Blue)
         .luminosity(matto::color::Luminosity::Light);
+    let mut rng = rand::thread_rng();
 
     let alpha = 0xd6;
+    let with_alpha = |c: image::Rgb<u8>| image::Rgba([c[0], c[1], c[2], alpha]);
 
-    let mut img = image::RgbaImage::from_pixel(
-        config.width,
-        config.height,
-        image::Rgba(matto::color::random_color(&mut color_config).to_rgba(alpha)),
-    );
+    let mut random_pixel = || match theme {
+        Some(theme) => with_alpha(theme.random_fill(&mut rng)),
+        None => image::Rgba(matto::color::random_color(&mut color_config).to_rgba(alpha)),
+    };
 
-    delaunay::random_triangulation(&mut img, &mut color_config, config.grid_size, alpha);
+    let background = match theme {
+        Some(theme) => with_alpha(theme.background()),
+        None => random_pixel(),
+    };
+
+    let mut img = image::RgbaImage::from_pixel(config.width, config.height, background);
+
+    delaunay::random_triangulation(
+        &mut img,
+        config.grid_size,
+        config.distribution,
+        config.relax,
+        random_pixel,
+    );
 
     img.save(&config.output_path).expect("cannot save image");
 }
 
-fn voronoi(config: &Voronoi) {
+fn low_poly(config: &LowPoly) {
+    let img = image::open(&config.img_path)
+        .expect("cannot open source image file")
+        .to_rgb();
+
+    delaunay::low_poly(&img, config.npoints, config.edge_ratio, config.color)
+        .save(&config.output_path)
+        .expect("cannot save image");
+}
+
+fn voronoi(config: &Voronoi, theme: Option<&Theme>) {
     let mut color_config =
         matto::color::RandomColorConfig::new().luminosity(matto::color::Luminosity::Bright);
+    let mut rng = rand::thread_rng();
 
-    let mut img = image::RgbImage::new(config.width, config.height);
+    let mut random_pixel = || match theme {
+        Some(theme) => theme.random_fill(&mut rng),
+        None => image::Rgb(matto::color::random_color(&mut color_config).to_rgb()),
+    };
 
-    if config.gradient_background {
-        let color1 = matto::color::random_color(&mut color_config).to_rgb();
-        let color2 = matto::color::random_color(&mut color_config).to_rgb();
+    let mut voronoi_config = voronoi::VoronoiConfig::new()
+        .points(config.npoints)
+        .distribution(config.distribution)
+        .relax(config.relax)
+        .metric(config.metric);
+    if let Some(weights) = config.weights {
+        voronoi_config = voronoi_config.weights(weights);
+    }
 
-        voronoi::gradient_voronoi(
-            &mut img,
-            image::Rgb(color1),
-            image::Rgb(color2),
-            config.npoints,
-        )
+    let mut img = image::RgbImage::new(config.width, config.height);
+
+    if config.gradient_background || config.cellular {
+        let (color1, color2) = match theme {
+            Some(theme) => (
+                theme.palette()[0],
+                theme.palette()[theme.palette().len() - 1],
+            ),
+            None => (random_pixel(), random_pixel()),
+        };
+
+        if config.cellular {
+            voronoi::cellular_voronoi(&mut img, color1, color2, &voronoi_config);
+        } else {
+            voronoi::gradient_voronoi(&mut img, color1, color2, &voronoi_config);
+        }
     } else {
-        voronoi::random_voronoi(&mut img, &mut color_config, config.npoints);
+        voronoi::random_voronoi(&mut img, &voronoi_config, random_pixel);
     }
 
     img.save(&config.output_path).expect("cannot save image");
 }
 
-fn patchwork(config: &Patchwork) {
+fn mosaic(config: &Mosaic, theme: Option<&Theme>) {
+    let img = image::open(&config.img_path)
+        .expect("cannot open source image file")
+        .to_rgb();
+
+    let lead = if config.lead_width > 0.0 {
+        let color = theme
+            .and_then(|t| t.stroke())
+            .unwrap_or(image::Rgb([0x20, 0x20, 0x20]));
+
+        Some((config.lead_width, color))
+    } else {
+        None
+    };
+
+    voronoi::mosaic(&img, config.npoints, config.adaptive, lead)
+        .save(&config.output_path)
+        .expect("cannot save image");
+}
+
+fn quadtree_subdivision(config: &QuadtreeSubdivision, theme: Option<&Theme>) {
+    let img = image::open(&config.img_path)
+        .expect("cannot open source image file")
+        .to_rgb();
+
+    let border = if config.borders {
+        Some(
+            theme
+                .and_then(|t| t.stroke())
+                .unwrap_or(image::Rgb([0x20, 0x20, 0x20])),
+        )
+    } else {
+        None
+    };
+
+    quadtree::quadtree_subdivision(&img, config.npoints, config.capacity, border)
+        .save(&config.output_path)
+        .expect("cannot save image");
+}
+
+fn patchwork(config: &Patchwork, theme: Option<&Theme>) {
+    let theme = theme_or_builtin(theme, "patchwork");
+
     let mut img = image::RgbImage::new(config.width, config.height);
 
     patchwork::random_patchwork(
         &mut img,
+        &theme,
         config.npoints,
         config.clusters,
         config.iterations,
         config.fill_polygons,
+        config.distribution,
+        config.grow,
+        config.inset,
     );
 
     img.save(&config.output_path).expect("cannot save image");
 }
 
-fn stippling(config: &Stippling) {
-    let mut img =
-        image::RgbImage::from_pixel(config.width, config.height, image::Rgb([0xFF, 0xFF, 0xFF]));
+fn stippling(config: &Stippling, theme: Option<&Theme>) {
+    let theme = theme_or_builtin(theme, "bw");
+
+    let mut img = image::RgbImage::from_pixel(config.width, config.height, theme.background());
 
     match config.command {
         StipplingCommand::Gradient(ref gradient_config) => {
@@ -957,8 +1645,9 @@ fn stippling(config: &Stippling) {
                 gradient_config.bands,
                 gradient_config.first_band_points,
                 gradient_config.grow_coeff,
-                image::Rgb([0, 0, 0]),
+                theme.stroke_or_fill(),
                 stippling::Direction::TopToBottom,
+                config.distribution,
             );
         }
         StipplingCommand::StipplingRects(ref rects_config) => {
@@ -967,39 +1656,108 @@ fn stippling(config: &Stippling) {
                 rects_config.iterations,
                 rects_config.points,
                 rects_config.minimum_area,
-                image::Rgb([0, 0, 0]),
+                theme.stroke_or_fill(),
+                config.distribution,
             );
         }
+        StipplingCommand::Image(ref image_config) => {
+            return stippling_image(config, image_config, &theme);
+        }
     }
 
     img.save(&config.output_path).expect("cannot save image");
 }
 
-fn mondrian(config: &Mondrian) {
-    let mut img = image::RgbImage::new(config.width, config.height);
+fn stippling_image(config: &Stippling, image_config: &StipplingImage, theme: &Theme) {
+    let source = image::open(&image_config.img_path)
+        .expect("cannot open image file")
+        .to_luma();
+
+    let stipples = stippling::weighted(
+        &source,
+        image_config.points,
+        image_config.iterations,
+        (
+            image_config.radius,
+            image_config.max_radius.unwrap_or(image_config.radius),
+        ),
+        config.distribution,
+    );
 
-    let fill_palette = [
-        image::Rgb([0x8d, 0x22, 0x02]),
-        image::Rgb([0x0b, 0x18, 0x3b]),
-        image::Rgb([0xd0, 0x95, 0x02]),
-    ];
+    if !image_config.svg {
+        let mut img =
+            image::RgbImage::from_pixel(source.width(), source.height(), theme.background());
+
+        stippling::draw_stipples(&mut img, &stipples, theme.stroke_or_fill());
+
+        img.save(&config.output_path).expect("cannot save image");
+        return;
+    }
+
+    let mut f = std::fs::File::create(config.output_path.with_extension("svg")).unwrap();
+    stippling::write_svg(
+        &mut f,
+        source.dimensions(),
+        &stipples,
+        theme.background(),
+        theme.stroke_or_fill(),
+    )
+    .expect("error writing svg");
+}
+
+fn mondrian(config: &Mondrian, theme: Option<&Theme>) {
+    let theme = theme_or_builtin(theme, "mondrian");
+
+    let mut img = image::RgbImage::new(config.width, config.height);
 
     mondrian::generate(
         &mut img,
         config.iterations,
         config.minimum_area,
-        image::Rgb([0xe6, 0xeb, 0xc3]),
-        &fill_palette,
+        theme.background(),
+        theme.palette(),
+        theme.stroke().unwrap_or(image::Rgb([0, 0, 0])),
         10,
     );
 
     img.save(&config.output_path).expect("cannot save image");
 }
 
-fn dither(config: &Dither) {
+fn dither(config: &Dither, theme: Option<&Theme>) {
     let img = image::open(&config.img_path).expect("cannot load image file");
 
-    let step = u8::max_value() / config.levels;
+    let colors = match (config.palette, theme) {
+        (Some(ncolors), _) => Some(
+            palette::extract_palette(
+                img.to_rgb().pixels().cloned(),
+                ncolors,
+                palette::PaletteSort::Frequency,
+            )
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect::<Vec<_>>(),
+        ),
+        (None, Some(theme)) => Some(
+            std::iter::once(theme.background())
+                .chain(theme.palette().iter().cloned())
+                .chain(theme.stroke())
+                .collect(),
+        ),
+        (None, None) => None,
+    };
+
+    if let Some(colors) = colors {
+        let rgb = img.to_rgb();
+        let matcher = palette::PaletteMatcher::new(&colors);
+
+        dithering::dither(&rgb, |c| matcher.closest(c).unwrap_or(*c))
+            .save(&config.output_path)
+            .expect("cannot save image");
+
+        return;
+    }
+
+    let step = u8::MAX / config.levels;
 
     if config.rgb {
         let dithered = dithering::dither(&img.to_rgb(), |l| {
@@ -1022,11 +1780,18 @@ fn dither(config: &Dither) {
     }
 }
 
-fn tangled_web(config: &TangledWeb) {
+fn tangled_web(config: &TangledWeb, theme: Option<&Theme>) {
+    let theme = theme_or_builtin(theme, "tangled-web");
+
     if !config.svg {
-        let mut img = image::RgbImage::new(config.width, config.height);
+        let mut img = image::RgbImage::from_pixel(config.width, config.height, theme.background());
 
-        matto::art::tangled_web::generate_img(&mut img, config.iterations, config.circle_divisions);
+        matto::art::tangled_web::generate_img(
+            &mut img,
+            config.iterations,
+            config.circle_divisions,
+            theme.stroke_or_fill(),
+        );
 
         img.save(&config.output_path).expect("cannot save image");
         return;
@@ -1038,6 +1803,7 @@ fn tangled_web(config: &TangledWeb) {
         (config.width, config.height),
         config.iterations,
         config.circle_divisions,
+        theme.stroke_or_fill(),
     )
     .expect("error writing svg");
 }